@@ -0,0 +1,166 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use wasm_game_engine::core::ecs::{Component, Entity, Sprite, Transform, Velocity, World};
+
+const ENTITY_COUNT: usize = 10_000;
+
+type LegacyEntity = u32;
+
+// The original `World` layout: one boxed component per (entity, type) pair,
+// kept here so both layouts can be measured side by side.
+struct LegacyWorld {
+    next_entity_id: LegacyEntity,
+    entities: Vec<LegacyEntity>,
+    components: HashMap<LegacyEntity, HashMap<TypeId, Box<dyn Any>>>,
+}
+
+impl LegacyWorld {
+    fn new() -> Self {
+        Self {
+            next_entity_id: 1,
+            entities: Vec::new(),
+            components: HashMap::new(),
+        }
+    }
+    
+    fn create_entity(&mut self) -> LegacyEntity {
+        let entity = self.next_entity_id;
+        self.next_entity_id += 1;
+        self.entities.push(entity);
+        self.components.insert(entity, HashMap::new());
+        entity
+    }
+    
+    fn add_component<T: Component + 'static>(&mut self, entity: LegacyEntity, component: T) {
+        if let Some(entity_components) = self.components.get_mut(&entity) {
+            entity_components.insert(TypeId::of::<T>(), Box::new(component));
+        }
+    }
+    
+    fn get_component<T: Component + 'static>(&self, entity: LegacyEntity) -> Option<&T> {
+        self.components.get(&entity)?
+            .get(&TypeId::of::<T>())?
+            .downcast_ref::<T>()
+    }
+    
+    fn get_component_mut<T: Component + 'static>(&mut self, entity: LegacyEntity) -> Option<&mut T> {
+        self.components.get_mut(&entity)?
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<T>()
+    }
+}
+
+fn legacy_world() -> LegacyWorld {
+    let mut world = LegacyWorld::new();
+    for i in 0..ENTITY_COUNT {
+        let entity = world.create_entity();
+        world.add_component(entity, Transform::new(i as f32, 0.0));
+        world.add_component(entity, Sprite::new(16.0, 16.0));
+        world.add_component(entity, Velocity::new(1.0, 2.0));
+    }
+    world
+}
+
+fn archetype_world() -> World {
+    let mut world = World::new();
+    for i in 0..ENTITY_COUNT {
+        let entity = world.create_entity();
+        world.add_component(entity, Transform::new(i as f32, 0.0));
+        world.add_component(entity, Sprite::new(16.0, 16.0));
+        world.add_component(entity, Velocity::new(1.0, 2.0));
+    }
+    world
+}
+
+fn bench_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_transforms_10k");
+    
+    let legacy = legacy_world();
+    group.bench_function("legacy_hashmap", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            for entity in &legacy.entities {
+                if let Some(transform) = legacy.get_component::<Transform>(*entity) {
+                    sum += transform.x;
+                }
+            }
+            black_box(sum)
+        })
+    });
+    
+    let world = archetype_world();
+    group.bench_function("archetype_get_component", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            for entity in world.get_entities() {
+                if let Some(transform) = world.get_component::<Transform>(entity) {
+                    sum += transform.x;
+                }
+            }
+            black_box(sum)
+        })
+    });
+    
+    group.bench_function("archetype_query", |b| {
+        b.iter(|| {
+            let sum: f32 = world.query::<&Transform>().iter().map(|(_, transform)| transform.x).sum();
+            black_box(sum)
+        })
+    });
+    
+    group.finish();
+}
+
+fn bench_integrate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("integrate_velocity_10k");
+    let dt = 1.0 / 60.0;
+    
+    let mut legacy = legacy_world();
+    group.bench_function("legacy_hashmap", |b| {
+        b.iter(|| {
+            for i in 0..legacy.entities.len() {
+                let entity = legacy.entities[i];
+                let Some(velocity) = legacy.get_component::<Velocity>(entity).copied() else {
+                    continue;
+                };
+                if let Some(transform) = legacy.get_component_mut::<Transform>(entity) {
+                    transform.x += velocity.x * dt;
+                    transform.y += velocity.y * dt;
+                }
+            }
+        })
+    });
+    
+    let mut world = archetype_world();
+    let entities: Vec<Entity> = world.get_entities().collect();
+    group.bench_function("archetype_get_component", |b| {
+        b.iter(|| {
+            for &entity in &entities {
+                let Some(velocity) = world.get_component::<Velocity>(entity).map(|velocity| *velocity) else {
+                    continue;
+                };
+                if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+                    transform.x += velocity.x * dt;
+                    transform.y += velocity.y * dt;
+                }
+            }
+        })
+    });
+    
+    group.bench_function("archetype_query", |b| {
+        b.iter(|| {
+            for (_, (mut transform, velocity)) in &mut world.query::<(&mut Transform, &Velocity)>() {
+                transform.x += velocity.x * dt;
+                transform.y += velocity.y * dt;
+            }
+        })
+    });
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_read, bench_integrate);
+criterion_main!(benches);
@@ -0,0 +1,161 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, LitStr};
+
+// `#[derive(Component)]` implements `wasm_game_engine::core::ecs::Component`.
+// Storage defaults to dense archetype tables; components that are added and
+// removed often can opt into sparse sets with `#[component(storage = "sparse")]`.
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    
+    let storage = match parse_storage(&input) {
+        Ok(storage) => storage,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    
+    // Components are stored by `TypeId`, so every type parameter must be 'static
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!('static));
+    }
+    // ...and `Component` requires `Clone + Send + Sync`, which generic types
+    // only get when their parameters have them too
+    input.generics.make_where_clause().predicates.push(parse_quote!(Self: Clone + Send + Sync));
+    
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::wasm_game_engine::core::ecs::Component for #name #type_generics #where_clause {
+            const STORAGE: ::wasm_game_engine::core::ecs::StorageType = ::wasm_game_engine::core::ecs::StorageType::#storage;
+        }
+    };
+    expanded.into()
+}
+
+fn parse_storage(input: &DeriveInput) -> syn::Result<proc_macro2::Ident> {
+    let mut storage = format_ident!("Dense");
+    
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("component")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("storage") {
+                return Err(meta.error("unknown component attribute, expected `storage`"));
+            }
+            
+            let value: LitStr = meta.value()?.parse()?;
+            storage = match value.value().as_str() {
+                "dense" => format_ident!("Dense"),
+                "sparse" => format_ident!("Sparse"),
+                _ => return Err(syn::Error::new(value.span(), "expected `\"dense\"` or `\"sparse\"`")),
+            };
+            Ok(())
+        })?;
+    }
+    
+    Ok(storage)
+}
+
+// `#[derive(Bundle)]` implements `wasm_game_engine::core::ecs::Bundle` for a
+// struct whose fields are all components, adding each field in order
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().map(|field| {
+                let name = field.ident.as_ref().expect("named field without a name");
+                quote! { #name }
+            }).collect::<Vec<_>>(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len()).map(|index| {
+                let index = syn::Index::from(index);
+                quote! { #index }
+            }).collect(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return syn::Error::new_spanned(&input.ident, "`Bundle` can only be derived for structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+    
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!('static));
+    }
+    
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::wasm_game_engine::core::ecs::Bundle for #name #type_generics #where_clause {
+            fn insert_into(self, world: &mut ::wasm_game_engine::core::ecs::World, entity: ::wasm_game_engine::core::ecs::Entity) {
+                #(world.add_component(entity, self.#fields);)*
+            }
+        }
+    };
+    expanded.into()
+}
+
+// `#[derive(Reflect)]` implements `wasm_game_engine::core::ecs::Reflect` for a
+// struct with named fields, each of which must be `Reflect` itself (`f32`,
+// `u32`, `bool`, `Option<T>` or another reflected struct)
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return syn::Error::new_spanned(&input.ident, "`Reflect` needs named fields")
+                    .to_compile_error()
+                    .into();
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(&input.ident, "`Reflect` can only be derived for structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+    
+    let idents: Vec<_> = fields.iter().map(|field| field.ident.as_ref().expect("named field without a name")).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.to_string()).collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    
+    let name = &input.ident;
+    let type_name = name.to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::wasm_game_engine::core::ecs::Reflect for #name #type_generics #where_clause {
+            fn field_type() -> ::wasm_game_engine::core::ecs::FieldType {
+                ::wasm_game_engine::core::ecs::FieldType::Struct(::wasm_game_engine::core::ecs::StructInfo::new(
+                    #type_name,
+                    vec![#(::wasm_game_engine::core::ecs::FieldInfo::new(
+                        #names,
+                        <#types as ::wasm_game_engine::core::ecs::Reflect>::field_type(),
+                    )),*],
+                ))
+            }
+            
+            fn field(&self, name: &str) -> Option<&dyn ::wasm_game_engine::core::ecs::Reflect> {
+                match name {
+                    #(#names => Some(&self.#idents),)*
+                    _ => None,
+                }
+            }
+            
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn ::wasm_game_engine::core::ecs::Reflect> {
+                match name {
+                    #(#names => Some(&mut self.#idents),)*
+                    _ => None,
+                }
+            }
+            
+            fn field_names(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+        }
+    };
+    expanded.into()
+}
@@ -19,6 +19,7 @@ class GameEngine {
         this.lastTime = 0;
         this.sprites = new Map();
         this.nextSpriteId = 1;
+        this.prefabs = new Map();
         
         // Input state
         this.keys = {};
@@ -103,7 +104,13 @@ class GameEngine {
                 y = 0,
                 width = 50,
                 height = 50,
-                color = { r: 1.0, g: 1.0, b: 1.0, a: 1.0 }
+                color = { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
+                texture,
+                sourceRect,
+                flipX = false,
+                flipY = false,
+                atlas,
+                region
             } = options;
             
             const wasmSpriteId = this.wasmEngine.create_sprite(x, y, width, height);
@@ -121,12 +128,98 @@ class GameEngine {
             // Set initial color
             this.wasmEngine.set_sprite_color(wasmSpriteId, color.r, color.g, color.b, color.a);
             
+            if (texture != null || sourceRect || flipX || flipY) {
+                const { x: sourceX = 0, y: sourceY = 0, width: sourceWidth = 0, height: sourceHeight = 0 } = sourceRect || {};
+                this.wasmEngine.set_sprite_property(wasmSpriteId, 'Sprite', JSON.stringify({
+                    texture_id: texture ?? null,
+                    source_x: sourceX,
+                    source_y: sourceY,
+                    source_width: sourceWidth,
+                    source_height: sourceHeight,
+                    flip_x: flipX,
+                    flip_y: flipY
+                }));
+            }
+            if (atlas != null && region != null) {
+                this.wasmEngine.set_sprite_region(wasmSpriteId, atlas, region);
+            }
+            
             return sprite;
         } else {
             return this.fallbackEngine.createSprite(options);
         }
     }
     
+    // Prefabs: `components` maps component names to fields, e.g.
+    // { Transform: { x: 0, y: 0 }, Sprite: { width: 32, height: 32 } }
+    registerPrefab(name, components) {
+        this.prefabs.set(name, components);
+        if (this.useWasm) {
+            this.wasmEngine.register_prefab(name, JSON.stringify(components));
+        }
+    }
+    
+    instantiatePrefab(name, overrides = {}) {
+        const prefab = this.prefabs.get(name);
+        if (!prefab) {
+            throw new Error(`Unknown prefab "${name}"`);
+        }
+        
+        const transform = { ...prefab.Transform, ...overrides.Transform };
+        const spriteData = { ...prefab.Sprite, ...overrides.Sprite };
+        const options = {
+            x: transform.x ?? 0,
+            y: transform.y ?? 0,
+            width: spriteData.width ?? 1,
+            height: spriteData.height ?? 1,
+            color: {
+                r: spriteData.color_r ?? 1.0,
+                g: spriteData.color_g ?? 1.0,
+                b: spriteData.color_b ?? 1.0,
+                a: spriteData.color_a ?? 1.0
+            }
+        };
+        
+        if (!this.useWasm) {
+            return this.fallbackEngine.createSprite(options);
+        }
+        
+        const wasmSpriteId = this.wasmEngine.instantiate_prefab(name, JSON.stringify(overrides));
+        const sprite = {
+            id: `sprite_${this.nextSpriteId++}`,
+            wasmId: wasmSpriteId,
+            x: options.x,
+            y: options.y,
+            width: options.width,
+            height: options.height,
+            color: options.color
+        };
+        this.sprites.set(sprite.id, sprite);
+        return sprite;
+    }
+    
+    // Worlds (WebAssembly engine only): several can be alive at once, but
+    // only the active one is updated and rendered
+    createWorld(name) {
+        return this.useWasm && this.wasmEngine.create_world(name);
+    }
+    
+    setActiveWorld(name) {
+        return this.useWasm && this.wasmEngine.set_active_world(name);
+    }
+    
+    getActiveWorld() {
+        return this.useWasm ? this.wasmEngine.active_world() : 'main';
+    }
+    
+    cloneWorld(source, target) {
+        return this.useWasm && this.wasmEngine.clone_world(source, target);
+    }
+    
+    removeWorld(name) {
+        return this.useWasm && this.wasmEngine.remove_world(name);
+    }
+    
     moveSprite(spriteId, x, y) {
         if (this.useWasm) {
             const sprite = this.sprites.get(spriteId);
@@ -160,6 +253,287 @@ class GameEngine {
         }
     }
     
+    // Scenes (WebAssembly engine only): saveScene() returns JSON for level
+    // files, saveGame() a compact Uint8Array for savegames. Loading either
+    // replaces the active world's sprites and returns the new ones.
+    saveScene() {
+        return this.useWasm ? this.wasmEngine.save_scene() : null;
+    }
+    
+    loadScene(json) {
+        if (!this.useWasm) {
+            return [];
+        }
+        const text = typeof json === 'string' ? json : JSON.stringify(json);
+        return this.trackLoadedSprites(this.wasmEngine.load_scene(text));
+    }
+    
+    saveGame() {
+        return this.useWasm ? this.wasmEngine.save_game() : null;
+    }
+    
+    loadGame(bytes) {
+        if (!this.useWasm) {
+            return [];
+        }
+        return this.trackLoadedSprites(this.wasmEngine.load_game(bytes));
+    }
+    
+    // Starts loading a texture (relative to the page) and returns its id.
+    // Loading the same path again gives the same id.
+    loadTexture(path) {
+        return this.useWasm ? this.wasmEngine.load_texture(path) : null;
+    }
+    
+    releaseTexture(textureId) {
+        return this.useWasm ? this.wasmEngine.release_texture(textureId) : false;
+    }
+    
+    // Starts loading a sprite shader and returns its id. The file holds both
+    // stages, each after a `#shader vertex` or `#shader fragment` line.
+    loadShader(path) {
+        return this.useWasm ? this.wasmEngine.load_shader(path) : null;
+    }
+    
+    releaseShader(shaderId) {
+        return this.useWasm ? this.wasmEngine.release_shader(shaderId) : false;
+    }
+    
+    // Draws sprites with a shader from `loadShader`, or the built-in one for
+    // null. Sprites use the built-in shader until it's loaded.
+    setSpriteShader(shaderId) {
+        return this.useWasm ? this.wasmEngine.set_sprite_shader(shaderId) : false;
+    }
+    
+    // Starts loading a sound (relative to the page) and returns its id
+    loadSound(path) {
+        return this.useWasm ? this.wasmEngine.load_sound(path) : null;
+    }
+    
+    releaseSound(soundId) {
+        return this.useWasm ? this.wasmEngine.release_sound(soundId) : false;
+    }
+    
+    // Sounds still loading play nothing
+    playSound(soundId, volume = 1.0) {
+        return this.useWasm ? this.wasmEngine.play_sound(soundId, volume) : false;
+    }
+    
+    // Loops until `stopAllSounds`
+    playMusic(soundId) {
+        return this.useWasm ? this.wasmEngine.play_music(soundId) : false;
+    }
+    
+    stopAllSounds() {
+        if (this.useWasm) {
+            this.wasmEngine.stop_all_sounds();
+        }
+    }
+    
+    // 'loading', 'loaded' or 'failed: <reason>'; undefined for unknown ids
+    getTextureState(textureId) {
+        return this.useWasm ? this.wasmEngine.texture_state(textureId) : undefined;
+    }
+    
+    // From 0 to 1 over the assets currently loading, e.g. for a loading screen
+    getLoadProgress() {
+        return this.useWasm ? this.wasmEngine.load_progress() : 1;
+    }
+    
+    // Fetches an archive made by the `pack-assets` tool and loads assets from
+    // it from then on; paths it doesn't hold are still fetched one by one.
+    // Resolves to how many assets it holds.
+    async loadAssetBundle(url) {
+        if (!this.useWasm) {
+            return 0;
+        }
+        const response = await fetch(url);
+        if (!response.ok) {
+            throw new Error(`Failed to load ${url}: ${response.status} ${response.statusText}`);
+        }
+        const bytes = new Uint8Array(await response.arrayBuffer());
+        return this.wasmEngine.use_asset_bundle(bytes);
+    }
+    
+    // Fetches an atlas made by the `pack-atlas` tool from its layout JSON and
+    // starts loading its image, found next to the layout. Resolves to the
+    // atlas's id, which is also its texture id.
+    async loadAtlas(url) {
+        if (!this.useWasm) {
+            return null;
+        }
+        const layout = await fetchText(url);
+        const { image } = JSON.parse(layout);
+        if (!image) {
+            throw new Error(`${url} doesn't name its image`);
+        }
+        const imagePath = url.slice(0, url.lastIndexOf('/') + 1) + image;
+        return this.wasmEngine.load_atlas(imagePath, layout);
+    }
+    
+    // Packs already loaded images (anything canvas can draw, e.g. images,
+    // bitmaps or canvases) into one atlas texture and returns its id.
+    // `images` maps region names to images.
+    buildAtlas(images, { padding = 1, extrusion = 0 } = {}) {
+        if (!this.useWasm) {
+            return null;
+        }
+        const entries = Object.entries(images);
+        const canvas = document.createElement('canvas');
+        const context = canvas.getContext('2d', { willReadFrequently: true });
+        const sizes = [];
+        const chunks = [];
+        for (const [, image] of entries) {
+            const width = image.naturalWidth || image.width;
+            const height = image.naturalHeight || image.height;
+            canvas.width = width;
+            canvas.height = height;
+            context.clearRect(0, 0, width, height);
+            context.drawImage(image, 0, 0);
+            sizes.push(width, height);
+            chunks.push(context.getImageData(0, 0, width, height).data);
+        }
+        
+        const pixels = new Uint8Array(chunks.reduce((total, chunk) => total + chunk.length, 0));
+        let offset = 0;
+        for (const chunk of chunks) {
+            pixels.set(chunk, offset);
+            offset += chunk.length;
+        }
+        return this.wasmEngine.build_atlas(
+            entries.map(([name]) => name),
+            new Uint32Array(sizes),
+            pixels,
+            padding,
+            extrusion
+        );
+    }
+    
+    // The names of an atlas's regions
+    getAtlasRegions(atlasId) {
+        return this.useWasm ? this.wasmEngine.atlas_regions(atlasId) : [];
+    }
+    
+    // Draws a sprite with a named region of an atlas, keeping its size
+    setSpriteRegion(spriteId, atlasId, region) {
+        const sprite = this.sprites.get(spriteId);
+        if (sprite && this.useWasm) {
+            this.wasmEngine.set_sprite_region(sprite.wasmId, atlasId, region);
+        }
+    }
+    
+    // Reloads loaded assets when they change, swapping them in without
+    // restarting the game. With `url`, listens for changed paths from a dev
+    // server's event stream (one path per message); otherwise polls each
+    // asset's headers every `interval` milliseconds. Returns a function that
+    // stops watching.
+    watchAssets({ url, interval = 1000 } = {}) {
+        if (!this.useWasm) {
+            return () => {};
+        }
+        
+        if (url) {
+            const events = new EventSource(url);
+            events.onmessage = (event) => this.wasmEngine.reload_asset(event.data);
+            return () => events.close();
+        }
+        
+        const versions = new Map();
+        let polling = false;
+        const poll = async () => {
+            // Skip a tick rather than overlap a slow poll
+            if (polling) {
+                return;
+            }
+            polling = true;
+            for (const path of this.wasmEngine.asset_paths()) {
+                try {
+                    const response = await fetch(path, { method: 'HEAD', cache: 'no-store' });
+                    const version = response.headers.get('ETag') || response.headers.get('Last-Modified');
+                    if (version && versions.has(path) && versions.get(path) !== version) {
+                        this.wasmEngine.reload_asset(path);
+                    }
+                    versions.set(path, version);
+                } catch {
+                    // Try again on the next poll
+                }
+            }
+            polling = false;
+        };
+        const timer = setInterval(poll, interval);
+        return () => clearInterval(timer);
+    }
+    
+    // Fetches a Tiled map (.tmj or .tmx) along with its external tilesets and
+    // spawns it into the current world. Resolves to the sprites of its tile
+    // objects.
+    async loadTiledMap(url) {
+        if (!this.useWasm) {
+            return [];
+        }
+        const contents = await fetchText(url);
+        const sources = contents.trimStart().startsWith('<')
+            ? Array.from(contents.matchAll(/<tileset[^>]*\ssource="([^"]+)"/g), (match) => match[1])
+            : (JSON.parse(contents).tilesets || []).map((tileset) => tileset.source).filter(Boolean);
+        
+        const tilesets = {};
+        for (const source of sources) {
+            tilesets[source] = await fetchText(new URL(source, new URL(url, document.baseURI)));
+        }
+        return this.trackLoadedSprites(this.wasmEngine.load_tiled_map(contents, JSON.stringify(tilesets)));
+    }
+    
+    trackLoadedSprites(wasmIds) {
+        // Drop records of sprites the load removed
+        for (const [spriteId, sprite] of this.sprites) {
+            try {
+                this.wasmEngine.get_sprite_property(sprite.wasmId, 'Sprite');
+            } catch {
+                this.sprites.delete(spriteId);
+            }
+        }
+        
+        return Array.from(wasmIds, (wasmId) => {
+            const transform = JSON.parse(this.wasmEngine.get_sprite_property(wasmId, 'Transform'));
+            const data = JSON.parse(this.wasmEngine.get_sprite_property(wasmId, 'Sprite'));
+            const sprite = {
+                id: `sprite_${this.nextSpriteId++}`,
+                wasmId,
+                x: transform.x,
+                y: transform.y,
+                width: data.width,
+                height: data.height,
+                color: { r: data.color_r, g: data.color_g, b: data.color_b, a: data.color_a }
+            };
+            this.sprites.set(sprite.id, sprite);
+            return sprite;
+        });
+    }
+    
+    // Reflected component fields by path (WebAssembly engine only), e.g.
+    // getSpriteProperty(id, 'Transform.rotation') or
+    // setSpriteProperty(id, 'Sprite', { width: 64 })
+    getSpriteProperty(spriteId, path) {
+        const sprite = this.sprites.get(spriteId);
+        if (!this.useWasm || !sprite) {
+            return undefined;
+        }
+        return JSON.parse(this.wasmEngine.get_sprite_property(sprite.wasmId, path));
+    }
+    
+    setSpriteProperty(spriteId, path, value) {
+        const sprite = this.sprites.get(spriteId);
+        if (!this.useWasm || !sprite) {
+            return false;
+        }
+        this.wasmEngine.set_sprite_property(sprite.wasmId, path, JSON.stringify(value ?? null));
+        return true;
+    }
+    
+    getComponentTypes() {
+        return this.useWasm ? JSON.parse(this.wasmEngine.component_types()) : {};
+    }
+    
     removeSprite(spriteId) {
         if (this.useWasm) {
             const sprite = this.sprites.get(spriteId);
@@ -280,6 +654,21 @@ class GameEngine {
         }
     }
     
+    // What the last frame took to render, for profiling. The WebGL engine
+    // draws sprites in batches, so draw calls are usually far fewer.
+    getRenderStats() {
+        if (this.useWasm) {
+            return {
+                drawCalls: this.wasmEngine.draw_calls(),
+                sprites: this.wasmEngine.sprites_drawn()
+            };
+        } else {
+            // The fallback engine draws each sprite on its own
+            const sprites = this.fallbackEngine.sprites.size;
+            return { drawCalls: sprites, sprites };
+        }
+    }
+    
     // Color utilities
     static Color = {
         WHITE: { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
@@ -311,6 +700,14 @@ class GameEngine {
     };
 }
 
+async function fetchText(url) {
+    const response = await fetch(url);
+    if (!response.ok) {
+        throw new Error(`Failed to load ${url}: ${response.status} ${response.statusText}`);
+    }
+    return response.text();
+}
+
 // Key code constants for convenience
 GameEngine.Keys = {
     W: 'KeyW',
@@ -0,0 +1,487 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use super::io::{AssetError, AssetIo, BoxFuture};
+use super::lz;
+
+// Many assets packed into one file, so a game can ship them as a single
+// download. The layout, little-endian throughout:
+//
+//     "WGEA"  u32 version  u32 entry count
+//     per entry: u16 path length, path (UTF-8), u64 data offset,
+//                u32 stored size, u32 size, u8 flags, u64 content hash
+//     data
+//
+// Offsets are relative to the start of the data. The hash is FNV-1a of the
+// uncompressed bytes, checked on every read. Entries with identical contents
+// share their data.
+const MAGIC: &[u8; 4] = b"WGEA";
+const VERSION: u32 = 1;
+const FLAG_COMPRESSED: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArchiveError {
+    Invalid(String),
+    UnsupportedVersion(u32),
+    NotFound(String),
+    // The index is fine but an entry's data isn't what was packed
+    Corrupt { path: String, message: String },
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Invalid(message) => write!(f, "invalid archive: {}", message),
+            ArchiveError::UnsupportedVersion(version) => {
+                write!(f, "archive version {} is not supported (expected {})", version, VERSION)
+            }
+            ArchiveError::NotFound(path) => write!(f, "`{}` is not in the archive", path),
+            ArchiveError::Corrupt { path, message } => write!(f, "`{}` is corrupt: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub offset: u64,
+    pub stored_size: u32,
+    pub size: u32,
+    pub compressed: bool,
+    pub hash: u64,
+}
+
+// FNV-1a, 64-bit. Fast and good enough to catch corruption and spot
+// duplicates; not meant to resist tampering.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Builds an archive in memory. Paths use forward slashes, as `AssetIo` does.
+pub struct ArchiveWriter {
+    files: BTreeMap<String, Vec<u8>>,
+    compress: bool,
+}
+
+impl Default for ArchiveWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArchiveWriter {
+    pub fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            compress: false,
+        }
+    }
+    
+    // Compresses each entry, keeping it stored as-is if that doesn't make it
+    // smaller
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+    
+    // Replaces any entry already at `path`
+    pub fn add(&mut self, path: &str, bytes: Vec<u8>) {
+        self.files.insert(normalize(path), bytes);
+    }
+    
+    // Adds every file under `root`, named by its path relative to it. Returns
+    // how many were added.
+    pub fn add_dir(&mut self, root: &Path) -> std::io::Result<usize> {
+        let mut count = 0;
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let relative = path.strip_prefix(root).expect("walked from root");
+                    let name = relative.to_string_lossy();
+                    self.add(&name, std::fs::read(&path)?);
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    
+    pub fn finish(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        // Stored entries by content hash, to share the data of duplicates
+        let mut stored: HashMap<u64, Vec<(&[u8], ArchiveEntry)>> = HashMap::new();
+        
+        for (path, bytes) in &self.files {
+            let too_large = || ArchiveError::Invalid(format!("`{}` is larger than 4 GiB", path));
+            let hash = content_hash(bytes);
+            let duplicate = stored
+                .get(&hash)
+                .and_then(|entries| entries.iter().find(|(contents, _)| *contents == bytes.as_slice()))
+                .map(|&(_, entry)| entry);
+            
+            let entry = match duplicate {
+                Some(entry) => entry,
+                None => {
+                    let compressed = Some(bytes)
+                        .filter(|_| self.compress)
+                        .map(|bytes| lz::compress(bytes))
+                        .filter(|compressed| compressed.len() < bytes.len());
+                    let entry = ArchiveEntry {
+                        offset: data.len() as u64,
+                        stored_size: u32::try_from(compressed.as_ref().map_or(bytes.len(), Vec::len))
+                            .map_err(|_| too_large())?,
+                        size: u32::try_from(bytes.len()).map_err(|_| too_large())?,
+                        compressed: compressed.is_some(),
+                        hash,
+                    };
+                    data.extend_from_slice(compressed.as_deref().unwrap_or(bytes));
+                    stored.entry(hash).or_default().push((bytes, entry));
+                    entry
+                }
+            };
+            
+            let path_len = u16::try_from(path.len())
+                .map_err(|_| ArchiveError::Invalid(format!("path `{}` is too long", path)))?;
+            index.extend_from_slice(&path_len.to_le_bytes());
+            index.extend_from_slice(path.as_bytes());
+            index.extend_from_slice(&entry.offset.to_le_bytes());
+            index.extend_from_slice(&entry.stored_size.to_le_bytes());
+            index.extend_from_slice(&entry.size.to_le_bytes());
+            index.push(if entry.compressed { FLAG_COMPRESSED } else { 0 });
+            index.extend_from_slice(&entry.hash.to_le_bytes());
+        }
+        
+        let mut out = Vec::with_capacity(12 + index.len() + data.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.files.len() as u32).to_le_bytes());
+        out.extend_from_slice(&index);
+        out.extend_from_slice(&data);
+        Ok(out)
+    }
+}
+
+// A packed archive, read whole into memory. Entries are decompressed and
+// checked as they're read.
+pub struct Archive {
+    bytes: Vec<u8>,
+    data_start: usize,
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl Archive {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, ArchiveError> {
+        let mut reader = Reader { bytes: &bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(ArchiveError::Invalid("not an asset archive".to_string()));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+        
+        let count = reader.u32()?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let path_len = reader.u16()? as usize;
+            let path = std::str::from_utf8(reader.take(path_len)?)
+                .map_err(|_| ArchiveError::Invalid("entry path is not UTF-8".to_string()))?
+                .to_string();
+            let entry = ArchiveEntry {
+                offset: reader.u64()?,
+                stored_size: reader.u32()?,
+                size: reader.u32()?,
+                compressed: reader.take(1)?[0] & FLAG_COMPRESSED != 0,
+                hash: reader.u64()?,
+            };
+            entries.insert(path, entry);
+        }
+        
+        let data_start = reader.position;
+        let data_len = (bytes.len() - data_start) as u64;
+        if let Some((path, _)) = entries
+            .iter()
+            .find(|(_, entry)| entry.offset.saturating_add(entry.stored_size as u64) > data_len)
+        {
+            return Err(ArchiveError::Invalid(format!("data for `{}` is past the end", path)));
+        }
+        Ok(Self {
+            bytes,
+            data_start,
+            entries,
+        })
+    }
+    
+    pub fn load(path: &Path) -> Result<Self, ArchiveError> {
+        let bytes = std::fs::read(path).map_err(|error| ArchiveError::Invalid(error.to_string()))?;
+        Self::from_bytes(bytes)
+    }
+    
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+    
+    pub fn entry(&self, path: &str) -> Option<&ArchiveEntry> {
+        self.entries.get(path)
+    }
+    
+    // Every entry's path, sorted
+    pub fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.entries.keys().map(String::as_str).collect();
+        paths.sort_unstable();
+        paths
+    }
+    
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, ArchiveError> {
+        let entry = self.entries.get(path).ok_or_else(|| ArchiveError::NotFound(path.to_string()))?;
+        let corrupt = |message: String| ArchiveError::Corrupt {
+            path: path.to_string(),
+            message,
+        };
+        
+        // Bounds were checked when the index was read
+        let start = self.data_start + entry.offset as usize;
+        let stored = &self.bytes[start..start + entry.stored_size as usize];
+        let bytes = if entry.compressed {
+            lz::decompress(stored, entry.size as usize).map_err(corrupt)?
+        } else if stored.len() == entry.size as usize {
+            stored.to_vec()
+        } else {
+            return Err(corrupt(format!("expected {} bytes, got {}", entry.size, stored.len())));
+        };
+        
+        if content_hash(&bytes) != entry.hash {
+            return Err(corrupt("content hash doesn't match".to_string()));
+        }
+        Ok(bytes)
+    }
+}
+
+// Serves assets from an archive, optionally falling back to another source
+// for paths it doesn't have
+pub struct ArchiveIo {
+    archive: Archive,
+    fallback: Option<Box<dyn AssetIo>>,
+}
+
+impl ArchiveIo {
+    pub fn new(archive: Archive) -> Self {
+        Self { archive, fallback: None }
+    }
+    
+    pub fn with_fallback(mut self, io: impl AssetIo + 'static) -> Self {
+        self.fallback = Some(Box::new(io));
+        self
+    }
+    
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+}
+
+impl AssetIo for ArchiveIo {
+    fn read(&self, path: &str) -> BoxFuture<Result<Vec<u8>, AssetError>> {
+        let path = normalize(path);
+        if let (false, Some(fallback)) = (self.archive.contains(&path), &self.fallback) {
+            return fallback.read(&path);
+        }
+        
+        let result = self.archive.read(&path).map_err(|error| match error {
+            ArchiveError::NotFound(path) => AssetError::NotFound(path),
+            error => AssetError::Io {
+                path: path.clone(),
+                message: error.to_string(),
+            },
+        });
+        Box::pin(std::future::ready(result))
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ArchiveError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or_else(|| ArchiveError::Invalid("index is truncated".to_string()))?;
+        self.position += len;
+        Ok(bytes)
+    }
+    
+    fn u16(&mut self) -> Result<u16, ArchiveError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")))
+    }
+    
+    fn u32(&mut self) -> Result<u32, ArchiveError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+    
+    fn u64(&mut self) -> Result<u64, ArchiveError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll, Waker};
+    
+    use super::*;
+    
+    // Archive reads are ready straight away
+    fn read_now(io: &dyn AssetIo, path: &str) -> Result<Vec<u8>, AssetError> {
+        match io.read(path).as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("read of `{}` wasn't ready", path),
+        }
+    }
+    
+    fn sample(compress: bool) -> Vec<u8> {
+        let mut writer = ArchiveWriter::new();
+        writer.set_compression(compress);
+        writer.add("empty.txt", Vec::new());
+        writer.add("images/hero.png", (0..=255).collect());
+        writer.add("levels\\level1.json", b"{\"tiles\": [0, 0, 0, 0]}".repeat(100));
+        writer.add("copy.json", b"{\"tiles\": [0, 0, 0, 0]}".repeat(100));
+        writer.finish().unwrap()
+    }
+    
+    #[test]
+    fn round_trips_entries() {
+        for compress in [false, true] {
+            let archive = Archive::from_bytes(sample(compress)).unwrap();
+            assert_eq!(archive.paths(), ["copy.json", "empty.txt", "images/hero.png", "levels/level1.json"]);
+            assert_eq!(archive.read("empty.txt").unwrap(), b"");
+            assert_eq!(archive.read("images/hero.png").unwrap(), (0..=255).collect::<Vec<u8>>());
+            assert_eq!(archive.read("levels/level1.json").unwrap(), b"{\"tiles\": [0, 0, 0, 0]}".repeat(100));
+            assert_eq!(archive.read("missing.txt"), Err(ArchiveError::NotFound("missing.txt".to_string())));
+        }
+    }
+    
+    #[test]
+    fn compresses_only_what_shrinks() {
+        let archive = Archive::from_bytes(sample(true)).unwrap();
+        let level = archive.entry("levels/level1.json").unwrap();
+        assert!(level.compressed && level.stored_size < level.size / 10);
+        let hero = archive.entry("images/hero.png").unwrap();
+        assert!(!hero.compressed && hero.stored_size == hero.size);
+    }
+    
+    #[test]
+    fn stores_duplicates_once() {
+        let archive = Archive::from_bytes(sample(false)).unwrap();
+        assert_eq!(archive.entry("copy.json").unwrap().offset, archive.entry("levels/level1.json").unwrap().offset);
+        assert_eq!(archive.read("copy.json").unwrap(), archive.read("levels/level1.json").unwrap());
+    }
+    
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = sample(false);
+        bytes[..4].copy_from_slice(b"PK\x03\x04");
+        assert_eq!(
+            Archive::from_bytes(bytes).err(),
+            Some(ArchiveError::Invalid("not an asset archive".to_string()))
+        );
+        assert!(matches!(Archive::from_bytes(Vec::new()), Err(ArchiveError::Invalid(_))));
+    }
+    
+    #[test]
+    fn rejects_newer_versions() {
+        let mut bytes = sample(false);
+        bytes[4..8].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(Archive::from_bytes(bytes).err(), Some(ArchiveError::UnsupportedVersion(2)));
+    }
+    
+    #[test]
+    fn truncated_archives_are_an_error() {
+        let bytes = sample(true);
+        for len in 0..bytes.len() {
+            let result = Archive::from_bytes(bytes[..len].to_vec());
+            assert!(matches!(result, Err(ArchiveError::Invalid(_))), "truncated to {}", len);
+        }
+    }
+    
+    #[test]
+    fn corrupt_data_fails_the_hash_check() {
+        for compress in [false, true] {
+            let mut bytes = sample(compress);
+            // The hero image is stored as is either way, and ends the data
+            let last = bytes.len() - 1;
+            bytes[last] ^= 0xff;
+            let archive = Archive::from_bytes(bytes).unwrap();
+            match archive.read("images/hero.png") {
+                Err(ArchiveError::Corrupt { path, message }) => {
+                    assert_eq!(path, "images/hero.png");
+                    assert!(message.contains("hash"), "{}", message);
+                }
+                other => panic!("expected corruption, got {:?}", other),
+            }
+        }
+    }
+    
+    #[test]
+    fn corrupt_data_never_panics() {
+        let bytes = sample(true);
+        for index in 0..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[index] ^= 0x5a;
+            if let Ok(archive) = Archive::from_bytes(corrupt) {
+                for path in archive.paths() {
+                    let _ = archive.read(path);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn content_hash_is_fnv_1a() {
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(content_hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+    
+    #[test]
+    fn io_falls_back_for_missing_paths() {
+        let mut fallback = ArchiveWriter::new();
+        fallback.add("extra.txt", b"from the fallback".to_vec());
+        let fallback = ArchiveIo::new(Archive::from_bytes(fallback.finish().unwrap()).unwrap());
+        
+        let io = ArchiveIo::new(Archive::from_bytes(sample(true)).unwrap());
+        assert_eq!(read_now(&io, "./levels/level1.json").unwrap().len(), 2300);
+        assert_eq!(read_now(&io, "extra.txt"), Err(AssetError::NotFound("extra.txt".to_string())));
+        
+        let io = io.with_fallback(fallback);
+        assert_eq!(read_now(&io, "extra.txt").unwrap(), b"from the fallback");
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+// Shared by every clone of a handle. Dropping the last one tells the server
+// the asset is no longer used.
+pub(super) struct HandleInner {
+    pub(super) id: u32,
+    drops: Sender<u32>,
+}
+
+impl HandleInner {
+    pub(super) fn new(id: u32, drops: Sender<u32>) -> Self {
+        Self { id, drops }
+    }
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        // The server may be gone already, in which case there's nothing to free
+        let _ = self.drops.send(self.id);
+    }
+}
+
+// A counted reference to an asset of type `T` in an `AssetServer`. The asset
+// stays loaded while any clone of its handle is alive.
+pub struct Handle<T> {
+    inner: Arc<HandleInner>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(super) fn new(inner: Arc<HandleInner>) -> Self {
+        Self { inner, marker: PhantomData }
+    }
+    
+    // Identifies the asset within its server, e.g. for `Sprite::texture_id`.
+    // The id doesn't keep the asset loaded by itself.
+    pub fn id(&self) -> u32 {
+        self.inner.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id())
+    }
+}
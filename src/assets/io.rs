@@ -0,0 +1,113 @@
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+// Asset loading runs on one thread, so futures needn't be `Send`
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetError {
+    NotFound(String),
+    Io { path: String, message: String },
+    // The bytes were read but aren't a valid asset of the requested type
+    Decode { path: String, message: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound(path) => write!(f, "asset `{}` not found", path),
+            AssetError::Io { path, message } => write!(f, "failed to read `{}`: {}", path, message),
+            AssetError::Decode { path, message } => write!(f, "failed to decode `{}`: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// Where asset bytes come from. Paths use forward slashes and are relative to
+// the source's root.
+pub trait AssetIo {
+    fn read(&self, path: &str) -> BoxFuture<Result<Vec<u8>, AssetError>>;
+}
+
+// Reads assets from a directory, for native tools and tests. The reads
+// themselves are blocking, behind the same interface as `FetchIo`.
+pub struct FileIo {
+    root: PathBuf,
+}
+
+impl FileIo {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl AssetIo for FileIo {
+    fn read(&self, path: &str) -> BoxFuture<Result<Vec<u8>, AssetError>> {
+        let result = std::fs::read(self.root.join(path)).map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => AssetError::NotFound(path.to_string()),
+            _ => AssetError::Io {
+                path: path.to_string(),
+                message: error.to_string(),
+            },
+        });
+        Box::pin(std::future::ready(result))
+    }
+}
+
+// Fetches assets over HTTP in the browser, relative to `base_url`
+pub struct FetchIo {
+    base_url: String,
+}
+
+impl FetchIo {
+    pub fn new(base_url: &str) -> Self {
+        let mut base_url = base_url.to_string();
+        if !base_url.is_empty() && !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Self { base_url }
+    }
+}
+
+impl AssetIo for FetchIo {
+    fn read(&self, path: &str) -> BoxFuture<Result<Vec<u8>, AssetError>> {
+        let url = format!("{}{}", self.base_url, path);
+        let path = path.to_string();
+        Box::pin(async move {
+            let io_error = |error: JsValue| AssetError::Io {
+                path: path.clone(),
+                message: js_message(&error),
+            };
+            
+            let window = web_sys::window().ok_or_else(|| io_error("no window to fetch from".into()))?;
+            let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&url))
+                .await
+                .map_err(io_error)?
+                .dyn_into()
+                .map_err(io_error)?;
+            if response.status() == 404 {
+                return Err(AssetError::NotFound(path));
+            }
+            if !response.ok() {
+                return Err(io_error(format!("{} {}", response.status(), response.status_text()).into()));
+            }
+            
+            let buffer = JsFuture::from(response.array_buffer().map_err(io_error)?).await.map_err(io_error)?;
+            Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+        })
+    }
+}
+
+pub(super) fn js_message(error: &JsValue) -> String {
+    error
+        .dyn_ref::<js_sys::Error>()
+        .map(|error| String::from(error.message()))
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| format!("{:?}", error))
+}
@@ -0,0 +1,199 @@
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioBuffer, AudioContext, HtmlImageElement, WebGl2RenderingContext};
+
+use super::io::{js_message, BoxFuture};
+use crate::core::ecs::Scene;
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::Texture;
+
+// Turns the bytes read for a path into an asset. Registered with
+// `AssetServer::register_loader`, one per asset type.
+pub trait AssetLoader: 'static {
+    type Asset: 'static;
+    
+    fn load(&self, path: &str, bytes: Vec<u8>) -> BoxFuture<Result<Self::Asset, String>>;
+}
+
+// Binary blobs, as read
+pub struct BytesLoader;
+
+impl AssetLoader for BytesLoader {
+    type Asset = Vec<u8>;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<Vec<u8>, String>> {
+        Box::pin(std::future::ready(Ok(bytes)))
+    }
+}
+
+// UTF-8 text, e.g. shader sources
+pub struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = String;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<String, String>> {
+        let result = String::from_utf8(bytes).map_err(|error| error.to_string());
+        Box::pin(std::future::ready(result))
+    }
+}
+
+pub struct JsonLoader;
+
+impl AssetLoader for JsonLoader {
+    type Asset = serde_json::Value;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<serde_json::Value, String>> {
+        let result = serde_json::from_slice(&bytes).map_err(|error| error.to_string());
+        Box::pin(std::future::ready(result))
+    }
+}
+
+// Images in any format the browser can decode
+pub struct ImageLoader;
+
+impl ImageLoader {
+    async fn decode(bytes: Vec<u8>) -> Result<HtmlImageElement, JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes.as_slice()));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        
+        let image = HtmlImageElement::new()?;
+        image.set_src(&url);
+        let decoded = JsFuture::from(image.decode()).await;
+        web_sys::Url::revoke_object_url(&url)?;
+        decoded.map_err(|_| JsValue::from_str("not a supported image"))?;
+        Ok(image)
+    }
+}
+
+impl AssetLoader for ImageLoader {
+    type Asset = HtmlImageElement;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<HtmlImageElement, String>> {
+        Box::pin(async move { Self::decode(bytes).await.map_err(|error| js_message(&error)) })
+    }
+}
+
+// Images uploaded to the GPU
+pub struct TextureLoader {
+    gl: WebGl2RenderingContext,
+}
+
+impl TextureLoader {
+    pub fn new(gl: &WebGl2RenderingContext) -> Self {
+        Self { gl: gl.clone() }
+    }
+}
+
+impl AssetLoader for TextureLoader {
+    type Asset = Texture;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<Texture, String>> {
+        let gl = self.gl.clone();
+        Box::pin(async move {
+            let image = ImageLoader::decode(bytes).await.map_err(|error| js_message(&error))?;
+            Texture::from_image(&gl, &image).map_err(|error| js_message(&error))
+        })
+    }
+}
+
+// Sounds in any format the browser can decode
+pub struct AudioLoader {
+    context: AudioContext,
+}
+
+impl AudioLoader {
+    pub fn new(context: &AudioContext) -> Self {
+        Self { context: context.clone() }
+    }
+}
+
+impl AssetLoader for AudioLoader {
+    type Asset = AudioBuffer;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<AudioBuffer, String>> {
+        let context = self.context.clone();
+        Box::pin(async move {
+            let data = js_sys::Uint8Array::from(bytes.as_slice()).buffer();
+            let promise = context.decode_audio_data(&data).map_err(|error| js_message(&error))?;
+            let buffer = JsFuture::from(promise).await.map_err(|error| js_message(&error))?;
+            buffer.dyn_into().map_err(|_| "decoding didn't produce an audio buffer".to_string())
+        })
+    }
+}
+
+// Shader programs from one file holding both stages, each after a line
+// naming it:
+//
+//     #shader vertex
+//     ...
+//     #shader fragment
+//     ...
+pub struct ShaderLoader {
+    gl: WebGl2RenderingContext,
+}
+
+impl ShaderLoader {
+    pub fn new(gl: &WebGl2RenderingContext) -> Self {
+        Self { gl: gl.clone() }
+    }
+    
+    // The vertex and fragment sources
+    pub fn split_stages(source: &str) -> Result<(String, String), String> {
+        let mut stages: [Option<String>; 2] = [None, None];
+        let mut current = None;
+        for line in source.lines() {
+            if let Some(stage) = line.trim().strip_prefix("#shader") {
+                let index = match stage.trim() {
+                    "vertex" => 0,
+                    "fragment" => 1,
+                    other => return Err(format!("unknown shader stage `{}`", other)),
+                };
+                if stages[index].is_some() {
+                    return Err(format!("more than one {} stage", stage.trim()));
+                }
+                stages[index] = Some(String::new());
+                current = Some(index);
+            } else if let Some(index) = current {
+                let stage = stages[index].as_mut().expect("current stage exists");
+                stage.push_str(line);
+                stage.push('\n');
+            }
+        }
+        
+        let [vertex, fragment] = stages;
+        let vertex = vertex.ok_or("no `#shader vertex` stage")?;
+        let fragment = fragment.ok_or("no `#shader fragment` stage")?;
+        Ok((vertex, fragment))
+    }
+}
+
+impl AssetLoader for ShaderLoader {
+    type Asset = ShaderProgram;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<ShaderProgram, String>> {
+        let result = String::from_utf8(bytes)
+            .map_err(|error| error.to_string())
+            .and_then(|source| Self::split_stages(&source))
+            .and_then(|(vertex, fragment)| {
+                ShaderProgram::new(&self.gl, &vertex, &fragment).map_err(|error| js_message(&error))
+            });
+        Box::pin(std::future::ready(result))
+    }
+}
+
+// Scenes saved as JSON or binary
+pub struct SceneLoader;
+
+impl AssetLoader for SceneLoader {
+    type Asset = Scene;
+    
+    fn load(&self, _path: &str, bytes: Vec<u8>) -> BoxFuture<Result<Scene, String>> {
+        let result = match std::str::from_utf8(&bytes) {
+            Ok(json) if !json.starts_with("WGES") => Scene::from_json(json).map_err(|error| error.to_string()),
+            _ => Scene::from_binary(&bytes).map_err(|error| error.to_string()),
+        };
+        Box::pin(std::future::ready(result))
+    }
+}
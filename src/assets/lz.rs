@@ -0,0 +1,223 @@
+// A small LZ77 codec in the style of LZ4 blocks, for asset archives. Each
+// sequence is a token byte (literal count in the high nibble, match length
+// minus 4 in the low one; 15 means more length bytes follow), the literals,
+// then a little-endian u16 offset back into the output. The last sequence has
+// literals only.
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 16;
+
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2);
+    // Last position + 1 of each hashed 4-byte sequence, 0 for none
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut position = 0;
+    
+    while position + MIN_MATCH <= input.len() {
+        let sequence = u32::from_le_bytes(input[position..position + 4].try_into().expect("4 bytes"));
+        let hash = (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], position + 1);
+        
+        if let Some(candidate) = candidate.checked_sub(1) {
+            if position - candidate <= MAX_OFFSET && input[candidate..candidate + 4] == input[position..position + 4] {
+                let mut len = MIN_MATCH;
+                while position + len < input.len() && input[candidate + len] == input[position + len] {
+                    len += 1;
+                }
+                write_sequence(&mut out, &input[anchor..position], Some((position - candidate, len)));
+                position += len;
+                anchor = position;
+                continue;
+            }
+        }
+        position += 1;
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+pub(crate) fn decompress(input: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    let truncated = || "compressed data is truncated".to_string();
+    // Each input byte makes at most 255 or so output bytes, so a corrupt size
+    // can't reserve more than the data could fill
+    let mut out = Vec::with_capacity(size.min(input.len().saturating_mul(256)));
+    let mut position = 0;
+    
+    loop {
+        let token = *input.get(position).ok_or_else(truncated)?;
+        position += 1;
+        
+        let literals = read_length(input, &mut position, (token >> 4) as usize).ok_or_else(truncated)?;
+        let end = position.checked_add(literals).filter(|&end| end <= input.len()).ok_or_else(truncated)?;
+        out.extend_from_slice(&input[position..end]);
+        position = end;
+        if position == input.len() {
+            break;
+        }
+        
+        let offset = input.get(position..position + 2).ok_or_else(truncated)?;
+        let offset = u16::from_le_bytes([offset[0], offset[1]]) as usize;
+        position += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(format!("match offset {} is out of range", offset));
+        }
+        let len = read_length(input, &mut position, (token & 0x0f) as usize).ok_or_else(truncated)? + MIN_MATCH;
+        if out.len() + len > size {
+            return Err("data is longer than expected".to_string());
+        }
+        // Matches may overlap what they produce, so copy byte by byte
+        for _ in 0..len {
+            out.push(out[out.len() - offset]);
+        }
+    }
+    
+    if out.len() != size {
+        return Err(format!("expected {} bytes, got {}", size, out.len()));
+    }
+    Ok(out)
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push((literals.len().min(15) as u8) << 4 | match_len.min(15) as u8);
+    write_length(out, literals.len());
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        write_length(out, match_len);
+    }
+}
+
+// The part of a length that didn't fit its nibble
+fn write_length(out: &mut Vec<u8>, len: usize) {
+    if len < 15 {
+        return;
+    }
+    let mut rest = len - 15;
+    while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+    }
+    out.push(rest as u8);
+}
+
+fn read_length(input: &[u8], position: &mut usize, nibble: usize) -> Option<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *input.get(*position)?;
+            *position += 1;
+            len = len.checked_add(byte as usize)?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Deterministic bytes with no repeats worth matching
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect()
+    }
+    
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = compress(input);
+        assert_eq!(decompress(&compressed, input.len()).unwrap(), input);
+        compressed
+    }
+    
+    #[test]
+    fn round_trips_empty_input() {
+        assert_eq!(round_trip(&[]), [0]);
+    }
+    
+    #[test]
+    fn round_trips_short_input() {
+        for len in 1..16 {
+            round_trip(&noise(len));
+        }
+    }
+    
+    #[test]
+    fn round_trips_incompressible_input() {
+        let input = noise(100_000);
+        // Only the tokens and length bytes are added
+        assert!(round_trip(&input).len() < input.len() + input.len() / 200);
+    }
+    
+    #[test]
+    fn round_trips_repetitive_input() {
+        let zeros = vec![0u8; 100_000];
+        assert!(round_trip(&zeros).len() < 500);
+        
+        let text = "{\"x\": 1, \"y\": 2}\n".repeat(1000);
+        assert!(round_trip(text.as_bytes()).len() < text.len() / 10);
+    }
+    
+    #[test]
+    fn round_trips_matches_further_back_than_an_offset_reaches() {
+        let block = noise(1000);
+        let mut input = block.clone();
+        input.extend(noise(MAX_OFFSET + 10).iter().map(|byte| byte ^ 0x5a));
+        input.extend_from_slice(&block);
+        round_trip(&input);
+    }
+    
+    #[test]
+    fn truncated_input_is_an_error() {
+        let input = "abcabcabcabc-".repeat(50);
+        let compressed = compress(input.as_bytes());
+        for len in 0..compressed.len() {
+            assert!(decompress(&compressed[..len], input.len()).is_err(), "truncated to {}", len);
+        }
+    }
+    
+    #[test]
+    fn corrupt_input_never_panics() {
+        let input = format!("{}{}", "abcabcabcabc-".repeat(50), String::from_utf8_lossy(&noise(200)));
+        let compressed = compress(input.as_bytes());
+        for index in 0..compressed.len() {
+            for flip in [0x01, 0x10, 0x80, 0xff] {
+                let mut corrupt = compressed.clone();
+                corrupt[index] ^= flip;
+                // Either an error or the wrong bytes, which the archive's
+                // hash catches
+                let _ = decompress(&corrupt, input.len());
+            }
+        }
+    }
+    
+    #[test]
+    fn wrong_size_is_an_error() {
+        let compressed = compress(b"hello hello hello hello");
+        assert!(decompress(&compressed, 22).is_err());
+        assert!(decompress(&compressed, 24).is_err());
+    }
+    
+    #[test]
+    fn huge_sizes_are_an_error() {
+        let compressed = compress(b"small");
+        assert!(decompress(&compressed, usize::MAX).is_err());
+    }
+    
+    #[test]
+    fn out_of_range_offset_is_an_error() {
+        // One literal, then a match 2 bytes back
+        let error = decompress(&[0x10, b'a', 2, 0, 0x00], 5).unwrap_err();
+        assert!(error.contains("out of range"), "{}", error);
+    }
+}
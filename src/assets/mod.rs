@@ -0,0 +1,18 @@
+pub mod archive;
+pub mod handle;
+pub mod io;
+mod lz;
+pub mod loader;
+pub mod server;
+pub mod watch;
+
+pub use archive::{content_hash, Archive, ArchiveEntry, ArchiveError, ArchiveIo, ArchiveWriter};
+pub use handle::Handle;
+pub use io::{AssetError, AssetIo, BoxFuture, FetchIo, FileIo};
+pub use loader::{
+    AssetLoader, AudioLoader, BytesLoader, ImageLoader, JsonLoader, SceneLoader, ShaderLoader, TextLoader, TextureLoader,
+};
+pub use server::{AssetServer, LoadProgress, LoadState};
+pub use watch::AssetWatcher;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watch::FileWatcher;
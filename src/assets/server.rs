@@ -0,0 +1,536 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::{self, Future};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use super::handle::{Handle, HandleInner};
+use super::io::{AssetError, AssetIo, BoxFuture};
+use super::loader::AssetLoader;
+use super::watch::AssetWatcher;
+
+// How far the current batch of loads has come, for loading screens. A batch
+// starts when a load is started with none running and ends when they've all
+// finished, failed loads included.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadProgress {
+    pub finished: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadProgress {
+    // From 0 to 1, and 1 with nothing loading
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.finished as f32 / self.total as f32
+        }
+    }
+    
+    pub fn is_done(&self) -> bool {
+        self.finished == self.total
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadState {
+    // Waiting for its data, see `AssetServer::loading`
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+struct Entry<T> {
+    path: Option<String>,
+    state: LoadState,
+    asset: Option<T>,
+    // Set when a load fails after an earlier one succeeded, see `reload_error`
+    reload_error: Option<String>,
+    // Lets a path be loaded again while the asset is still in use without
+    // keeping it alive
+    handle: Weak<HandleInner>,
+}
+
+struct Storage<T> {
+    entries: HashMap<u32, Entry<T>>,
+}
+
+// Lets the server free assets by id without knowing their type
+trait AnyStorage {
+    // Removes the asset if nothing has picked its handle back up since it
+    // was dropped, returning its path
+    fn free(&mut self, id: u32) -> Option<Option<String>>;
+    fn is_loading(&self, id: u32) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn free(&mut self, id: u32) -> Option<Option<String>> {
+        if self.entries.get(&id)?.handle.strong_count() > 0 {
+            return None;
+        }
+        self.entries.remove(&id).map(|entry| entry.path)
+    }
+    
+    fn is_loading(&self, id: u32) -> bool {
+        self.entries.get(&id).is_some_and(|entry| entry.state == LoadState::Loading)
+    }
+    
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Stores a finished load's result in the server, returning whether it failed
+type Completion = Box<dyn FnOnce(&mut AssetServer) -> bool>;
+// Turns a read of an asset's bytes into a load of the asset
+type StartLoad = Rc<dyn Fn(u32, String, BoxFuture<Result<Vec<u8>, AssetError>>) -> BoxFuture<Completion>>;
+
+// Owns every loaded texture, shader, sound and so on, handing out `Handle`s
+// to them. Loading the same path twice gives the same asset, and an asset is
+// freed by `free_unused` once the last handle to it is dropped.
+//
+// With an `AssetIo` to read from and a loader registered for the asset type,
+// `load` reads and decodes the asset in the background: `update` moves
+// finished loads into the server every frame. Without either, the asset waits
+// for `finish_load`.
+//
+// Assets can be reloaded while the game runs, from `reload` or an
+// `AssetWatcher`. The new version replaces the old in place, so handles and
+// ids stay valid.
+pub struct AssetServer {
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    // The storage each live asset id is in
+    types: HashMap<u32, TypeId>,
+    paths: HashMap<(TypeId, String), u32>,
+    next_id: u32,
+    drops: Sender<u32>,
+    dropped: Receiver<u32>,
+    io: Option<Rc<dyn AssetIo>>,
+    loaders: HashMap<TypeId, StartLoad>,
+    tasks: Vec<Task>,
+    progress: LoadProgress,
+    watcher: Option<Box<dyn AssetWatcher>>,
+    // Ids of the assets swapped by a reload, see `take_reloaded`
+    reloaded: Vec<u32>,
+}
+
+struct Task {
+    id: u32,
+    // Reloads don't count towards progress
+    reload: bool,
+    future: BoxFuture<Completion>,
+}
+
+impl AssetServer {
+    pub fn new() -> Self {
+        let (drops, dropped) = mpsc::channel();
+        Self {
+            storages: HashMap::new(),
+            types: HashMap::new(),
+            paths: HashMap::new(),
+            // 0 stays free, so ids can't be mistaken for unset ones
+            next_id: 1,
+            drops,
+            dropped,
+            io: None,
+            loaders: HashMap::new(),
+            tasks: Vec::new(),
+            progress: LoadProgress::default(),
+            watcher: None,
+            reloaded: Vec::new(),
+        }
+    }
+    
+    pub fn with_io(io: impl AssetIo + 'static) -> Self {
+        let mut server = Self::new();
+        server.set_io(io);
+        server
+    }
+    
+    // Changes where assets are read from. Loads already running finish from
+    // the old source.
+    pub fn set_io(&mut self, io: impl AssetIo + 'static) {
+        self.io = Some(Rc::new(io));
+    }
+    
+    // Makes `load` decode assets of the loader's type, replacing any loader
+    // registered for it before
+    pub fn register_loader<L: AssetLoader>(&mut self, loader: L) {
+        let loader = Rc::new(loader);
+        let start: StartLoad = Rc::new(move |id, path, read| {
+            let loader = loader.clone();
+            Box::pin(async move {
+                let result = match read.await {
+                    Ok(bytes) => loader.load(&path, bytes).await.map_err(|message| AssetError::Decode {
+                        path: path.clone(),
+                        message,
+                    }),
+                    Err(error) => Err(error),
+                };
+                let completion: Completion = Box::new(move |server: &mut AssetServer| {
+                    let failed = result.is_err();
+                    server.finish_load::<L::Asset>(id, result.map_err(|error| error.to_string()));
+                    failed
+                });
+                completion
+            })
+        });
+        self.loaders.insert(TypeId::of::<L::Asset>(), start);
+    }
+    
+    // Adds an asset that doesn't come from a path
+    pub fn add<T: 'static>(&mut self, asset: T) -> Handle<T> {
+        self.insert(None, LoadState::Loaded, Some(asset))
+    }
+    
+    // Starts tracking the asset at `path`, or returns the handle of the one
+    // already tracked there. A new asset starts out `Loading` until
+    // `finish_load` gives it its data; one that failed to load is tried again.
+    pub fn load<T: 'static>(&mut self, path: &str) -> Handle<T> {
+        let Some(&id) = self.paths.get(&(TypeId::of::<T>(), path.to_string())) else {
+            let handle = self.insert(Some(path.to_string()), LoadState::Loading, None);
+            self.start_load(TypeId::of::<T>(), handle.id(), path, false);
+            return handle;
+        };
+        
+        let handle = self.revive::<T>(id);
+        let entry = self.entry_mut::<T>(id).expect("tracked path without an entry");
+        if matches!(entry.state, LoadState::Failed(_)) {
+            entry.state = LoadState::Loading;
+            self.start_load(TypeId::of::<T>(), id, path, false);
+        }
+        handle
+    }
+    
+    // Returns false if there's no reading or decoding the asset
+    fn start_load(&mut self, type_id: TypeId, id: u32, path: &str, reload: bool) -> bool {
+        let (Some(io), Some(start)) = (&self.io, self.loaders.get(&type_id)) else {
+            return false;
+        };
+        let future = start(id, path.to_string(), io.read(path));
+        if !reload {
+            if !self.tasks.iter().any(|task| !task.reload) {
+                self.progress = LoadProgress::default();
+            }
+            self.progress.total += 1;
+        }
+        self.tasks.push(Task { id, reload, future });
+        true
+    }
+    
+    // Reads the assets loaded from `path` again. Each keeps its current
+    // version until the new one has loaded, and keeps it if the reload fails.
+    // Returns how many reloads were started.
+    pub fn reload(&mut self, path: &str) -> usize {
+        let assets: Vec<(TypeId, u32)> = self.paths
+            .iter()
+            .filter(|((_, asset_path), _)| asset_path == path)
+            .map(|(&(type_id, _), &id)| (type_id, id))
+            .collect();
+        
+        let mut started = 0;
+        for (type_id, id) in assets {
+            // A load that's still running reads the latest version anyway
+            if self.storages[&type_id].is_loading(id) {
+                continue;
+            }
+            if self.start_load(type_id, id, path, true) {
+                started += 1;
+            }
+        }
+        started
+    }
+    
+    // Reloads the assets the watcher reports as changed from `update` on
+    pub fn set_watcher(&mut self, watcher: impl AssetWatcher + 'static) {
+        self.watcher = Some(Box::new(watcher));
+    }
+    
+    // The ids of the assets reloaded since the last call, e.g. for respawning
+    // a level whose scene changed
+    pub fn take_reloaded(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.reloaded)
+    }
+    
+    // Every path an asset is tracked under, sorted
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.paths.keys().map(|(_, path)| path.clone()).collect();
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+    
+    // Stores the loads that have finished, reloads changed assets and frees
+    // unused ones. Call once a frame.
+    pub fn update(&mut self) {
+        self.poll_loads(&mut Context::from_waker(Waker::noop()));
+        let paths = self.paths();
+        let changed = self.watcher.as_mut().map_or_else(Vec::new, |watcher| watcher.changed(&paths));
+        for path in changed {
+            self.reload(&path);
+        }
+        self.free_unused();
+    }
+    
+    // Resolves once every running load has finished, for code that can
+    // await instead of polling `update`
+    pub fn finish_loading(&mut self) -> impl Future<Output = ()> + '_ {
+        future::poll_fn(move |context| {
+            self.poll_loads(context);
+            if self.tasks.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+    }
+    
+    pub fn progress(&self) -> LoadProgress {
+        self.progress
+    }
+    
+    fn poll_loads(&mut self, context: &mut Context<'_>) {
+        let mut completions = Vec::new();
+        self.tasks.retain_mut(|task| match task.future.as_mut().poll(context) {
+            Poll::Ready(completion) => {
+                completions.push((task.id, task.reload, completion));
+                false
+            }
+            Poll::Pending => true,
+        });
+        for (id, reload, completion) in completions {
+            let failed = completion(self);
+            if reload {
+                if !failed {
+                    self.reloaded.push(id);
+                }
+            } else {
+                self.progress.finished += 1;
+                self.progress.failed += failed as usize;
+            }
+        }
+    }
+    
+    // Adds an asset under `path` as loaded, replacing the one already there
+    // while keeping its handles valid
+    pub fn set<T: 'static>(&mut self, path: &str, asset: T) -> Handle<T> {
+        let handle = self.load::<T>(path);
+        self.finish_load(handle.id(), Ok(asset));
+        handle
+    }
+    
+    // Hands the data for an asset that's `Loading` to the server, or marks it
+    // failed. An asset that already has data keeps it and stays `Loaded`,
+    // with the error kept for `reload_error`. Returns false if the id isn't a
+    // tracked `T`.
+    pub fn finish_load<T: 'static>(&mut self, id: u32, result: Result<T, String>) -> bool {
+        let Some(entry) = self.entry_mut::<T>(id) else {
+            return false;
+        };
+        match result {
+            Ok(asset) => {
+                entry.asset = Some(asset);
+                entry.state = LoadState::Loaded;
+                entry.reload_error = None;
+            }
+            Err(error) if entry.asset.is_some() => {
+                entry.state = LoadState::Loaded;
+                entry.reload_error = Some(error);
+            }
+            Err(error) => entry.state = LoadState::Failed(error),
+        }
+        true
+    }
+    
+    // Why the asset's latest load failed while it kept its previous version,
+    // until a load succeeds again
+    pub fn reload_error<T: 'static>(&self, handle: &Handle<T>) -> Option<&str> {
+        self.storage::<T>()?.entries.get(&handle.id())?.reload_error.as_deref()
+    }
+    
+    // The ids and paths of the `T`s still waiting for their data
+    pub fn loading<T: 'static>(&self) -> Vec<(u32, String)> {
+        let Some(storage) = self.storage::<T>() else {
+            return Vec::new();
+        };
+        let mut loading: Vec<(u32, String)> = storage
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.state == LoadState::Loading)
+            .filter_map(|(&id, entry)| Some((id, entry.path.clone()?)))
+            .collect();
+        loading.sort_unstable();
+        loading
+    }
+    
+    pub fn get<T: 'static>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.get_by_id(handle.id())
+    }
+    
+    pub fn get_mut<T: 'static>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entry_mut::<T>(handle.id())?.asset.as_mut()
+    }
+    
+    // Looks an asset up by a handle's id, for components that store ids
+    pub fn get_by_id<T: 'static>(&self, id: u32) -> Option<&T> {
+        self.storage::<T>()?.entries.get(&id)?.asset.as_ref()
+    }
+    
+    // A new handle to a tracked asset, if it's still in use
+    pub fn handle<T: 'static>(&self, id: u32) -> Option<Handle<T>> {
+        let inner = self.storage::<T>()?.entries.get(&id)?.handle.upgrade()?;
+        Some(Handle::new(inner))
+    }
+    
+    pub fn load_state<T: 'static>(&self, handle: &Handle<T>) -> &LoadState {
+        &self.storage::<T>()
+            .and_then(|storage| storage.entries.get(&handle.id()))
+            .expect("handle from another asset server")
+            .state
+    }
+    
+    pub fn is_loaded<T: 'static>(&self, handle: &Handle<T>) -> bool {
+        *self.load_state(handle) == LoadState::Loaded
+    }
+    
+    pub fn path<T: 'static>(&self, handle: &Handle<T>) -> Option<&str> {
+        self.storage::<T>()?.entries.get(&handle.id())?.path.as_deref()
+    }
+    
+    // The number of tracked assets, loaded or not
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+    
+    // Frees the assets whose last handle has been dropped, returning how
+    // many went. Dropping the asset releases what it holds, e.g. a texture's
+    // GPU memory.
+    pub fn free_unused(&mut self) -> usize {
+        let mut freed = 0;
+        while let Ok(id) = self.dropped.try_recv() {
+            let Some(&type_id) = self.types.get(&id) else {
+                continue;
+            };
+            let storage = self.storages.get_mut(&type_id).expect("asset type without storage");
+            if let Some(path) = storage.free(id) {
+                self.types.remove(&id);
+                if let Some(path) = path {
+                    self.paths.remove(&(type_id, path));
+                }
+                freed += 1;
+            }
+        }
+        freed
+    }
+    
+    fn insert<T: 'static>(&mut self, path: Option<String>, state: LoadState, asset: Option<T>) -> Handle<T> {
+        let id = self.next_id;
+        self.next_id += 1;
+        
+        let inner = Arc::new(HandleInner::new(id, self.drops.clone()));
+        let entry = Entry {
+            path: path.clone(),
+            state,
+            asset,
+            reload_error: None,
+            handle: Arc::downgrade(&inner),
+        };
+        self.storage_mut::<T>().entries.insert(id, entry);
+        self.types.insert(id, TypeId::of::<T>());
+        if let Some(path) = path {
+            self.paths.insert((TypeId::of::<T>(), path), id);
+        }
+        Handle::new(inner)
+    }
+    
+    // A handle to a tracked asset, making a new one if the last was dropped
+    // but the asset not freed yet
+    fn revive<T: 'static>(&mut self, id: u32) -> Handle<T> {
+        if let Some(handle) = self.handle(id) {
+            return handle;
+        }
+        let inner = Arc::new(HandleInner::new(id, self.drops.clone()));
+        self.entry_mut::<T>(id).expect("revived an untracked asset").handle = Arc::downgrade(&inner);
+        Handle::new(inner)
+    }
+    
+    fn entry_mut<T: 'static>(&mut self, id: u32) -> Option<&mut Entry<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .expect("storage of the wrong type")
+            .entries
+            .get_mut(&id)
+    }
+    
+    fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        Some(storage.as_any().downcast_ref().expect("storage of the wrong type"))
+    }
+    
+    fn storage_mut<T: 'static>(&mut self) -> &mut Storage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T> { entries: HashMap::new() }))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("storage of the wrong type")
+    }
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn failed_loads_keep_the_previous_version() {
+        let mut server = AssetServer::new();
+        let handle = server.set("level.json", 1u32);
+        
+        assert!(server.finish_load::<u32>(handle.id(), Err("bad data".to_string())));
+        assert!(server.is_loaded(&handle));
+        assert_eq!(server.get(&handle), Some(&1));
+        assert_eq!(server.reload_error(&handle), Some("bad data"));
+        
+        server.set("level.json", 2u32);
+        assert_eq!(server.get(&handle), Some(&2));
+        assert_eq!(server.reload_error(&handle), None);
+    }
+    
+    #[test]
+    fn failed_first_loads_fail() {
+        let mut server = AssetServer::new();
+        let handle = server.load::<u32>("missing.json");
+        assert_eq!(*server.load_state(&handle), LoadState::Loading);
+        
+        server.finish_load::<u32>(handle.id(), Err("not found".to_string()));
+        assert_eq!(*server.load_state(&handle), LoadState::Failed("not found".to_string()));
+        assert_eq!(server.get(&handle), None);
+        assert_eq!(server.reload_error(&handle), None);
+        
+        // Loading again retries
+        server.load::<u32>("missing.json");
+        assert_eq!(*server.load_state(&handle), LoadState::Loading);
+    }
+}
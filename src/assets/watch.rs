@@ -0,0 +1,60 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant, SystemTime};
+
+// Tells an `AssetServer` which of its assets changed at their source, so it
+// can reload them
+pub trait AssetWatcher {
+    // Which of `paths` changed since the last call
+    fn changed(&mut self, paths: &[String]) -> Vec<String>;
+}
+
+// Watches files under a directory natively by polling their modification
+// times, at most once per `interval`. Not built for the browser, which has no
+// clock `Instant` can read; there the page polls or listens to a dev server
+// and calls `AssetServer::reload`.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileWatcher {
+    root: PathBuf,
+    interval: Duration,
+    last_check: Option<Instant>,
+    modified: HashMap<String, SystemTime>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileWatcher {
+    pub fn new(root: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            root: root.into(),
+            interval,
+            last_check: None,
+            modified: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AssetWatcher for FileWatcher {
+    fn changed(&mut self, paths: &[String]) -> Vec<String> {
+        if self.last_check.is_some_and(|last_check| last_check.elapsed() < self.interval) {
+            return Vec::new();
+        }
+        self.last_check = Some(Instant::now());
+        
+        let mut changed = Vec::new();
+        for path in paths {
+            // Missing files count as changed once they're back
+            let Ok(modified) = std::fs::metadata(self.root.join(path)).and_then(|metadata| metadata.modified()) else {
+                continue;
+            };
+            // The first sighting only records the time
+            if self.modified.insert(path.clone(), modified).is_some_and(|previous| previous != modified) {
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
@@ -1,11 +1,11 @@
 use wasm_bindgen::prelude::*;
 use web_sys::{AudioContext, AudioBuffer, AudioBufferSourceNode, GainNode};
-use std::collections::HashMap;
+
+use crate::assets::{AssetServer, Handle};
 
 pub struct AudioManager {
     audio_context: Option<AudioContext>,
     master_gain: Option<GainNode>,
-    sound_buffers: HashMap<String, AudioBuffer>,
     sound_sources: Vec<AudioBufferSourceNode>,
 }
 
@@ -24,43 +24,36 @@ impl AudioManager {
         Ok(Self {
             audio_context,
             master_gain,
-            sound_buffers: HashMap::new(),
             sound_sources: Vec::new(),
         })
     }
     
     pub fn set_master_volume(&self, volume: f32) -> Result<(), JsValue> {
         if let Some(ref gain) = self.master_gain {
-            gain.gain().set_value(volume.max(0.0).min(1.0));
-        }
-        Ok(())
-    }
-    
-    pub fn load_sound(&mut self, name: &str, audio_data: &[u8]) -> Result<(), JsValue> {
-        if let Some(ref ctx) = self.audio_context {
-            let array_buffer = js_sys::ArrayBuffer::new(audio_data.len() as u32);
-            let uint8_array = js_sys::Uint8Array::new(&array_buffer);
-            uint8_array.copy_from(audio_data);
-            
-            // Note: In a real implementation, we'd need to decode the audio data
-            // For now, this is a placeholder structure
-            console_log!("Audio loading not fully implemented yet - placeholder");
+            gain.gain().set_value(volume.clamp(0.0, 1.0));
         }
         Ok(())
     }
     
-    pub fn play_sound(&mut self, name: &str) -> Result<(), JsValue> {
-        self.play_sound_with_volume(name, 1.0)
+    // Sounds are `AudioBuffer` assets from the `AssetServer`; one that hasn't
+    // finished loading plays nothing
+    pub fn play_sound(&mut self, assets: &AssetServer, sound: &Handle<AudioBuffer>) -> Result<(), JsValue> {
+        self.play_sound_with_volume(assets, sound, 1.0)
     }
     
-    pub fn play_sound_with_volume(&mut self, name: &str, volume: f32) -> Result<(), JsValue> {
+    pub fn play_sound_with_volume(
+        &mut self,
+        assets: &AssetServer,
+        sound: &Handle<AudioBuffer>,
+        volume: f32,
+    ) -> Result<(), JsValue> {
         if let (Some(ref ctx), Some(ref master_gain)) = (&self.audio_context, &self.master_gain) {
-            if let Some(buffer) = self.sound_buffers.get(name) {
+            if let Some(buffer) = assets.get(sound) {
                 let source = ctx.create_buffer_source()?;
                 let gain = ctx.create_gain()?;
                 
                 source.set_buffer(Some(buffer));
-                gain.gain().set_value(volume.max(0.0).min(1.0));
+                gain.gain().set_value(volume.clamp(0.0, 1.0));
                 
                 source.connect_with_audio_node(&gain)?;
                 gain.connect_with_audio_node(master_gain)?;
@@ -74,9 +67,10 @@ impl AudioManager {
         Ok(())
     }
     
-    pub fn play_sound_looped(&mut self, name: &str) -> Result<(), JsValue> {
+    // Loops until `stop_all_sounds`
+    pub fn play_music(&mut self, assets: &AssetServer, music: &Handle<AudioBuffer>) -> Result<(), JsValue> {
         if let (Some(ref ctx), Some(ref master_gain)) = (&self.audio_context, &self.master_gain) {
-            if let Some(buffer) = self.sound_buffers.get(name) {
+            if let Some(buffer) = assets.get(music) {
                 let source = ctx.create_buffer_source()?;
                 source.set_buffer(Some(buffer));
                 source.set_loop(true);
@@ -91,7 +85,7 @@ impl AudioManager {
     
     pub fn stop_all_sounds(&mut self) {
         for source in &self.sound_sources {
-            let _ = source.stop();
+            let _ = AsRef::<web_sys::AudioScheduledSourceNode>::as_ref(source).stop();
         }
         self.sound_sources.clear();
     }
@@ -102,7 +96,12 @@ impl AudioManager {
             let gain = ctx.create_gain()?;
             
             oscillator.frequency().set_value(frequency);
-            oscillator.set_type(&wave_type.parse().unwrap_or(web_sys::OscillatorType::Sine));
+            oscillator.set_type(match wave_type {
+                "square" => web_sys::OscillatorType::Square,
+                "sawtooth" => web_sys::OscillatorType::Sawtooth,
+                "triangle" => web_sys::OscillatorType::Triangle,
+                _ => web_sys::OscillatorType::Sine,
+            });
             
             gain.gain().set_value(0.1); // Lower volume for oscillator
             
@@ -121,6 +120,10 @@ impl AudioManager {
         self.create_oscillator(440.0, "sine")
     }
     
+    pub fn audio_context(&self) -> Option<&AudioContext> {
+        self.audio_context.as_ref()
+    }
+    
     pub fn is_audio_available(&self) -> bool {
         self.audio_context.is_some()
     }
@@ -0,0 +1,76 @@
+// Packs an asset directory into one archive for `ArchiveIo`:
+//
+//     pack-assets <asset dir> <output file> [--compress] [--list]
+//
+// Entries are named by their paths relative to the asset directory.
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use wasm_game_engine::assets::{Archive, ArchiveWriter};
+
+const USAGE: &str = "usage: pack-assets <asset dir> <output file> [--compress] [--list]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("pack-assets: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut compress = false;
+    let mut list = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--compress" => compress = true,
+            "--list" => list = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`\n{}", flag, USAGE)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths).map_err(|_| USAGE.to_string())?;
+    
+    let mut writer = ArchiveWriter::new();
+    writer.set_compression(compress);
+    writer
+        .add_dir(&input)
+        .map_err(|error| format!("failed to read {}: {}", input.display(), error))?;
+    if writer.is_empty() {
+        return Err(format!("no files in {}", input.display()));
+    }
+    
+    let bytes = writer.finish().map_err(|error| error.to_string())?;
+    let packed = bytes.len();
+    // Read the archive back, which also checks every entry
+    let archive = Archive::from_bytes(bytes.clone()).map_err(|error| error.to_string())?;
+    let mut original = 0;
+    for path in archive.paths() {
+        let entry = archive.entry(path).expect("listed entry exists");
+        archive.read(path).map_err(|error| error.to_string())?;
+        original += entry.size as usize;
+        if list {
+            println!(
+                "{:>10} {:>10} {:016x} {}",
+                entry.size, entry.stored_size, entry.hash, path
+            );
+        }
+    }
+    
+    std::fs::write(&output, bytes).map_err(|error| format!("failed to write {}: {}", output.display(), error))?;
+    println!(
+        "packed {} assets ({} bytes) into {} ({} bytes)",
+        archive.len(),
+        original,
+        output.display(),
+        packed
+    );
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// Packs a directory of PNG images into one texture atlas:
+//
+//     pack-atlas <image dir> <output png> [--padding N] [--extrude N] [--max-size N]
+//
+// Writes the atlas image and, next to it, its layout as JSON with the same
+// name. Each image's region is named by its path relative to the directory,
+// without the extension, e.g. "player/idle".
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use wasm_game_engine::graphics::{decode_png, AtlasBuilder};
+
+const USAGE: &str = "usage: pack-atlas <image dir> <output png> [--padding N] [--extrude N] [--max-size N]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("pack-atlas: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut builder = AtlasBuilder::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> Result<u32, String> {
+            let value = args.next().ok_or_else(|| format!("{} needs a value\n{}", name, USAGE))?;
+            value.parse().map_err(|_| format!("invalid {} `{}`", name, value))
+        };
+        match arg.as_str() {
+            "--padding" => builder = builder.with_padding(value("--padding")?),
+            "--extrude" => builder = builder.with_extrusion(value("--extrude")?),
+            "--max-size" => builder = builder.with_max_size(value("--max-size")?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`\n{}", flag, USAGE)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths).map_err(|_| USAGE.to_string())?;
+    
+    let mut images = Vec::new();
+    find_pngs(&input, &mut images).map_err(|error| format!("failed to read {}: {}", input.display(), error))?;
+    if images.is_empty() {
+        return Err(format!("no PNG images in {}", input.display()));
+    }
+    // Sorted so the same images always pack the same way
+    images.sort();
+    for path in &images {
+        let bytes = std::fs::read(path).map_err(|error| format!("failed to read {}: {}", path.display(), error))?;
+        let (width, height, pixels) = decode_png(&bytes).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let name = path
+            .strip_prefix(&input)
+            .expect("found under the input directory")
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        builder.add(&name, pixels, width, height).map_err(|error| error.to_string())?;
+    }
+    
+    let mut atlas = builder.build().map_err(|error| error.to_string())?;
+    let file_name = output.file_name().ok_or_else(|| format!("{} is not a file name", output.display()))?;
+    atlas.layout.image = file_name.to_string_lossy().into_owned();
+    let layout_path = output.with_extension("json");
+    std::fs::write(&output, atlas.encode_png())
+        .map_err(|error| format!("failed to write {}: {}", output.display(), error))?;
+    std::fs::write(&layout_path, atlas.layout.to_json())
+        .map_err(|error| format!("failed to write {}: {}", layout_path.display(), error))?;
+    
+    println!(
+        "packed {} images into {} ({}x{}) and {}",
+        images.len(),
+        output.display(),
+        atlas.layout.width,
+        atlas.layout.height,
+        layout_path.display()
+    );
+    Ok(())
+}
+
+fn find_pngs(dir: &Path, found: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_pngs(&path, found)?;
+        } else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
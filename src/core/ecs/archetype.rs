@@ -0,0 +1,427 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+use super::borrow::{BorrowFlag, Ref};
+use super::change::ComponentTicks;
+use super::{Component, Entity};
+
+// Type-erased storage for a single component type. Each column is a plain
+// `Vec<T>`, so all components of one type in an archetype sit next to each
+// other in memory.
+pub(crate) trait Column {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn empty_clone(&self) -> Box<dyn Column>;
+    fn clone_column(&self) -> Box<dyn Column>;
+    fn swap_remove(&mut self, row: usize);
+    fn move_row(&mut self, row: usize, target: &mut dyn Column);
+}
+
+impl<T: Component + 'static> Column for Vec<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    
+    fn empty_clone(&self) -> Box<dyn Column> {
+        Box::new(Vec::<T>::new())
+    }
+    
+    fn clone_column(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+    
+    fn swap_remove(&mut self, row: usize) {
+        Vec::swap_remove(self, row);
+    }
+    
+    fn move_row(&mut self, row: usize, target: &mut dyn Column) {
+        let value = Vec::swap_remove(self, row);
+        target
+            .as_any_mut()
+            .downcast_mut::<Vec<T>>()
+            .expect("column type mismatch")
+            .push(value);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct EntityLocation {
+    pub archetype: usize,
+    pub row: usize,
+}
+
+// A column plus its runtime borrow state, so queries running against a shared
+// `&World` can still hand out `&mut T` without aliasing. `ticks` is parallel
+// to the column and covered by the same borrow.
+struct ColumnCell {
+    data: UnsafeCell<Box<dyn Column>>,
+    ticks: UnsafeCell<Vec<ComponentTicks>>,
+    borrow: BorrowFlag,
+}
+
+impl ColumnCell {
+    fn new(data: Box<dyn Column>) -> Self {
+        Self {
+            data: UnsafeCell::new(data),
+            ticks: UnsafeCell::new(Vec::new()),
+            borrow: BorrowFlag::new(),
+        }
+    }
+    
+    fn get_mut(&mut self) -> &mut dyn Column {
+        self.data.get_mut().as_mut()
+    }
+    
+    fn ticks_mut(&mut self) -> &mut Vec<ComponentTicks> {
+        self.ticks.get_mut()
+    }
+    
+    fn swap_remove(&mut self, row: usize) {
+        self.get_mut().swap_remove(row);
+        self.ticks_mut().swap_remove(row);
+    }
+    
+    // Copies the column under a shared borrow, so a clone never sees a
+    // live query's writes half-done
+    fn clone_cell(&self) -> Self {
+        if !self.borrow.borrow() {
+            panic!("cannot clone the world while a component column is borrowed mutably");
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        let (data, ticks) = unsafe { ((*self.data.get()).clone_column(), (*self.ticks.get()).clone()) };
+        self.borrow.release();
+        
+        Self {
+            data: UnsafeCell::new(data),
+            ticks: UnsafeCell::new(ticks),
+            borrow: BorrowFlag::new(),
+        }
+    }
+}
+
+// A table holding every entity that has exactly the same set of components.
+// `types` is kept sorted and `columns` is parallel to it.
+pub struct Archetype {
+    types: Vec<TypeId>,
+    columns: Vec<ColumnCell>,
+    entities: Vec<Entity>,
+    add_edges: HashMap<TypeId, usize>,
+    remove_edges: HashMap<TypeId, usize>,
+}
+
+impl Clone for Archetype {
+    fn clone(&self) -> Self {
+        Self {
+            types: self.types.clone(),
+            columns: self.columns.iter().map(ColumnCell::clone_cell).collect(),
+            entities: self.entities.clone(),
+            add_edges: self.add_edges.clone(),
+            remove_edges: self.remove_edges.clone(),
+        }
+    }
+}
+
+impl Archetype {
+    fn new(types: Vec<TypeId>, columns: Vec<Box<dyn Column>>) -> Self {
+        Self {
+            types,
+            columns: columns.into_iter().map(ColumnCell::new).collect(),
+            entities: Vec::new(),
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+    
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+    
+    pub fn types(&self) -> &[TypeId] {
+        &self.types
+    }
+    
+    pub fn has(&self, type_id: TypeId) -> bool {
+        self.column_index(type_id).is_some()
+    }
+    
+    fn column_index(&self, type_id: TypeId) -> Option<usize> {
+        self.types.binary_search(&type_id).ok()
+    }
+    
+    fn column_mut<T: Component + 'static>(&mut self) -> Option<(&mut Vec<T>, &mut Vec<ComponentTicks>)> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let cell = &mut self.columns[index];
+        let column = cell.data.get_mut().as_any_mut().downcast_mut::<Vec<T>>()?;
+        Some((column, cell.ticks.get_mut()))
+    }
+    
+    // Mutable access to a single component, marking it changed at `tick`
+    pub(crate) fn get_mut<T: Component + 'static>(&mut self, row: usize, tick: u64) -> Option<&mut T> {
+        let (column, ticks) = self.column_mut::<T>()?;
+        ticks.get_mut(row)?.set_changed(tick);
+        column.get_mut(row)
+    }
+    
+    // Overwrites the component at `row`, or returns it back if this
+    // archetype doesn't store `T`
+    pub(crate) fn replace<T: Component + 'static>(&mut self, row: usize, component: T, tick: u64) -> Result<(), T> {
+        match self.column_mut::<T>() {
+            Some((column, ticks)) => {
+                column[row] = component;
+                ticks[row].set_changed(tick);
+                Ok(())
+            }
+            None => Err(component),
+        }
+    }
+    
+    // Appends a component for the entity most recently moved into this
+    // archetype, stamped as added at `tick`
+    pub(crate) fn push<T: Component + 'static>(&mut self, component: T, tick: u64) {
+        let (column, ticks) = self.column_mut::<T>().expect("archetype is missing the pushed column");
+        column.push(component);
+        ticks.push(ComponentTicks::new(tick));
+    }
+    
+    // Borrows a single component, panicking if the column is already
+    // borrowed mutably by a live query
+    pub(crate) fn get<T: Component + 'static>(&self, row: usize) -> Option<Ref<'_, T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let cell = &self.columns[index];
+        if !cell.borrow.borrow() {
+            panic!("`{}` is already borrowed mutably", type_name::<T>());
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        let column = unsafe { &*cell.data.get() };
+        match column.as_any().downcast_ref::<Vec<T>>().and_then(|column| column.get(row)) {
+            Some(value) => Some(unsafe { Ref::new(&cell.borrow, value) }),
+            None => {
+                cell.borrow.release();
+                None
+            }
+        }
+    }
+    
+    pub(crate) fn borrow<T: Component + 'static>(&self) {
+        if let Some(index) = self.column_index(TypeId::of::<T>()) {
+            if !self.columns[index].borrow.borrow() {
+                panic!("`{}` is already borrowed mutably", type_name::<T>());
+            }
+        }
+    }
+    
+    pub(crate) fn borrow_mut<T: Component + 'static>(&self) {
+        if let Some(index) = self.column_index(TypeId::of::<T>()) {
+            if !self.columns[index].borrow.borrow_mut() {
+                panic!("`{}` is already borrowed", type_name::<T>());
+            }
+        }
+    }
+    
+    pub(crate) fn release<T: Component + 'static>(&self) {
+        if let Some(index) = self.column_index(TypeId::of::<T>()) {
+            self.columns[index].borrow.release();
+        }
+    }
+    
+    pub(crate) fn release_mut<T: Component + 'static>(&self) {
+        if let Some(index) = self.column_index(TypeId::of::<T>()) {
+            self.columns[index].borrow.release_mut();
+        }
+    }
+    
+    // Safety: the caller must hold the mutable borrow of the `T` column
+    pub(crate) unsafe fn column_ptr<T: Component + 'static>(&self) -> Option<*mut T> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let column = &mut *self.columns[index].data.get();
+        column.as_any_mut().downcast_mut::<Vec<T>>().map(|column| column.as_mut_ptr())
+    }
+    
+    // Safety: reads through the pointer need at least a shared borrow of the
+    // `T` column and writes need the mutable one, unless the caller otherwise
+    // guarantees no conflicting access to the same row
+    pub(crate) unsafe fn ticks_ptr<T: Component + 'static>(&self) -> Option<*mut ComponentTicks> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let ticks = &mut *self.columns[index].ticks.get();
+        Some(ticks.as_mut_ptr())
+    }
+    
+    // Drops the entity at `row` and all of its components, returning the
+    // entity that was swapped into its place (if any) so the caller can fix
+    // up its location
+    pub(crate) fn remove(&mut self, row: usize) -> Option<Entity> {
+        for column in self.columns.iter_mut() {
+            column.swap_remove(row);
+        }
+        self.swap_remove_entity(row)
+    }
+    
+    fn swap_remove_entity(&mut self, row: usize) -> Option<Entity> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Archetypes {
+    archetypes: Vec<Archetype>,
+    index: HashMap<Vec<TypeId>, usize>,
+}
+
+impl Archetypes {
+    // Archetype 0 is always the empty archetype new entities start in
+    pub const EMPTY: usize = 0;
+    
+    pub fn new() -> Self {
+        let mut index = HashMap::new();
+        index.insert(Vec::new(), Self::EMPTY);
+        
+        Self {
+            archetypes: vec![Archetype::new(Vec::new(), Vec::new())],
+            index,
+        }
+    }
+    
+    pub fn get(&self, archetype: usize) -> &Archetype {
+        &self.archetypes[archetype]
+    }
+    
+    pub fn get_mut(&mut self, archetype: usize) -> &mut Archetype {
+        &mut self.archetypes[archetype]
+    }
+    
+    pub fn iter(&self) -> std::slice::Iter<'_, Archetype> {
+        self.archetypes.iter()
+    }
+    
+    // Places a freshly created entity in the empty archetype
+    pub fn spawn(&mut self, entity: Entity) -> EntityLocation {
+        let archetype = &mut self.archetypes[Self::EMPTY];
+        archetype.entities.push(entity);
+        EntityLocation {
+            archetype: Self::EMPTY,
+            row: archetype.entities.len() - 1,
+        }
+    }
+    
+    // Finds (or creates) the archetype reached by adding `T` to `source`
+    pub fn add_target<T: Component + 'static>(&mut self, source: usize) -> usize {
+        let type_id = TypeId::of::<T>();
+        if let Some(&target) = self.archetypes[source].add_edges.get(&type_id) {
+            return target;
+        }
+        
+        let mut types = self.archetypes[source].types.clone();
+        let position = types.binary_search(&type_id).unwrap_or_else(|e| e);
+        types.insert(position, type_id);
+        
+        let target = match self.index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let mut columns = self.empty_columns(source);
+                columns.insert(position, Box::new(Vec::<T>::new()));
+                self.insert_archetype(types, columns)
+            }
+        };
+        
+        self.archetypes[source].add_edges.insert(type_id, target);
+        target
+    }
+    
+    // Finds (or creates) the archetype reached by removing `T` from `source`,
+    // or `None` if `source` doesn't store `T`
+    pub fn remove_target<T: Component + 'static>(&mut self, source: usize) -> Option<usize> {
+        let type_id = TypeId::of::<T>();
+        if let Some(&target) = self.archetypes[source].remove_edges.get(&type_id) {
+            return Some(target);
+        }
+        
+        let position = self.archetypes[source].column_index(type_id)?;
+        let mut types = self.archetypes[source].types.clone();
+        types.remove(position);
+        
+        let target = match self.index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let mut columns = self.empty_columns(source);
+                columns.remove(position);
+                self.insert_archetype(types, columns)
+            }
+        };
+        
+        self.archetypes[source].remove_edges.insert(type_id, target);
+        Some(target)
+    }
+    
+    fn empty_columns(&mut self, source: usize) -> Vec<Box<dyn Column>> {
+        self.archetypes[source].columns
+            .iter_mut()
+            .map(|column| column.get_mut().empty_clone())
+            .collect()
+    }
+    
+    fn insert_archetype(&mut self, types: Vec<TypeId>, columns: Vec<Box<dyn Column>>) -> usize {
+        self.archetypes.push(Archetype::new(types.clone(), columns));
+        let archetype = self.archetypes.len() - 1;
+        self.index.insert(types, archetype);
+        archetype
+    }
+    
+    // Moves the entity at `location` into `target`, carrying over every
+    // component both archetypes share. Components `target` doesn't store are
+    // handed to `evict` together with their row. Returns the new location and
+    // the entity (if any) whose row changed in the source.
+    pub fn move_entity(
+        &mut self,
+        location: EntityLocation,
+        target: usize,
+        mut evict: impl FnMut(&mut dyn Column, usize),
+    ) -> (EntityLocation, Option<Entity>) {
+        debug_assert_ne!(location.archetype, target);
+        
+        let (source, destination) = self.pair_mut(location.archetype, target);
+        let entity = source.entities[location.row];
+        
+        for (index, type_id) in source.types.iter().enumerate() {
+            let column = &mut source.columns[index];
+            match destination.column_index(*type_id) {
+                Some(destination_index) => {
+                    let destination_column = &mut destination.columns[destination_index];
+                    column.get_mut().move_row(location.row, destination_column.get_mut());
+                    let ticks = column.ticks_mut().swap_remove(location.row);
+                    destination_column.ticks_mut().push(ticks);
+                }
+                None => {
+                    evict(column.get_mut(), location.row);
+                    column.ticks_mut().swap_remove(location.row);
+                }
+            }
+        }
+        
+        let moved = source.swap_remove_entity(location.row);
+        destination.entities.push(entity);
+        
+        let new_location = EntityLocation {
+            archetype: target,
+            row: destination.entities.len() - 1,
+        };
+        (new_location, moved)
+    }
+    
+    fn pair_mut(&mut self, a: usize, b: usize) -> (&mut Archetype, &mut Archetype) {
+        if a < b {
+            let (left, right) = self.archetypes.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.archetypes.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+}
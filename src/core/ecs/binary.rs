@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+// A compact binary encoding of JSON values, used for binary scenes. Every
+// string (object keys included) is stored once in a table up front and
+// referenced by index; integers are varints and floats are stored as `f32`
+// whenever that loses nothing.
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const UINT: u8 = 3;
+// Stores `-(n + 1)` for a negative `n`
+const NEG_INT: u8 = 4;
+const F32: u8 = 5;
+const F64: u8 = 6;
+const STRING: u8 = 7;
+const ARRAY: u8 = 8;
+const OBJECT: u8 = 9;
+
+pub(crate) fn encode(value: &Value, out: &mut Vec<u8>) {
+    let mut strings = StringTable::default();
+    strings.collect(value);
+    
+    write_varint(out, strings.strings.len() as u64);
+    for string in &strings.strings {
+        write_varint(out, string.len() as u64);
+        out.extend_from_slice(string.as_bytes());
+    }
+    write_value(out, value, &strings);
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    
+    let count = reader.varint()?;
+    let mut strings = Vec::new();
+    for _ in 0..count {
+        let len = reader.varint()? as usize;
+        let bytes = reader.take(len)?;
+        let string = std::str::from_utf8(bytes).map_err(|_| "string is not valid UTF-8".to_string())?;
+        strings.push(string.to_string());
+    }
+    
+    let value = reader.value(&strings, 0)?;
+    if reader.position != bytes.len() {
+        return Err(format!("{} unexpected trailing bytes", bytes.len() - reader.position));
+    }
+    Ok(value)
+}
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl StringTable {
+    fn collect(&mut self, value: &Value) {
+        match value {
+            Value::String(string) => self.intern(string),
+            Value::Array(values) => values.iter().for_each(|value| self.collect(value)),
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    self.intern(key);
+                    self.collect(value);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    fn intern(&mut self, string: &str) {
+        if !self.indices.contains_key(string) {
+            self.indices.insert(string.to_string(), self.strings.len() as u64);
+            self.strings.push(string.to_string());
+        }
+    }
+    
+    fn index(&self, string: &str) -> u64 {
+        self.indices[string]
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value, strings: &StringTable) {
+    match value {
+        Value::Null => out.push(NULL),
+        Value::Bool(false) => out.push(FALSE),
+        Value::Bool(true) => out.push(TRUE),
+        Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                out.push(UINT);
+                write_varint(out, value);
+            } else if let Some(value) = number.as_i64() {
+                out.push(NEG_INT);
+                write_varint(out, !(value as u64));
+            } else {
+                let value = number.as_f64().unwrap_or(0.0);
+                if (value as f32) as f64 == value {
+                    out.push(F32);
+                    out.extend_from_slice(&(value as f32).to_le_bytes());
+                } else {
+                    out.push(F64);
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        Value::String(string) => {
+            out.push(STRING);
+            write_varint(out, strings.index(string));
+        }
+        Value::Array(values) => {
+            out.push(ARRAY);
+            write_varint(out, values.len() as u64);
+            for value in values {
+                write_value(out, value, strings);
+            }
+        }
+        Value::Object(fields) => {
+            out.push(OBJECT);
+            write_varint(out, fields.len() as u64);
+            for (key, value) in fields {
+                write_varint(out, strings.index(key));
+                write_value(out, value, strings);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    // Deeper nesting than any scene needs; stops malicious input from
+    // overflowing the stack
+    const MAX_DEPTH: usize = 128;
+    
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "unexpected end of data".to_string())?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+    
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint is too long".to_string())
+    }
+    
+    fn string(&mut self, strings: &[String]) -> Result<String, String> {
+        let index = self.varint()?;
+        strings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("string index {} out of range", index))
+    }
+    
+    fn value(&mut self, strings: &[String], depth: usize) -> Result<Value, String> {
+        if depth > Self::MAX_DEPTH {
+            return Err("data is nested too deeply".to_string());
+        }
+        
+        let value = match self.byte()? {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            UINT => Value::from(self.varint()?),
+            NEG_INT => Value::from(!self.varint()? as i64),
+            F32 => {
+                let bytes = self.take(4)?.try_into().expect("took 4 bytes");
+                float(f32::from_le_bytes(bytes) as f64)?
+            }
+            F64 => {
+                let bytes = self.take(8)?.try_into().expect("took 8 bytes");
+                float(f64::from_le_bytes(bytes))?
+            }
+            STRING => Value::String(self.string(strings)?),
+            ARRAY => {
+                let len = self.varint()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(self.value(strings, depth + 1)?);
+                }
+                Value::Array(values)
+            }
+            OBJECT => {
+                let len = self.varint()?;
+                let mut fields = Map::new();
+                for _ in 0..len {
+                    let key = self.string(strings)?;
+                    fields.insert(key, self.value(strings, depth + 1)?);
+                }
+                Value::Object(fields)
+            }
+            tag => return Err(format!("unknown value tag {}", tag)),
+        };
+        Ok(value)
+    }
+}
+
+fn float(value: f64) -> Result<Value, String> {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| "non-finite number".to_string())
+}
@@ -0,0 +1,97 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Runtime borrow state for one component column. The low bits count shared
+// borrows; the top bit is set while the column is borrowed mutably.
+pub struct BorrowFlag(AtomicUsize);
+
+impl BorrowFlag {
+    const UNIQUE: usize = !(usize::MAX >> 1);
+    
+    pub fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+    
+    pub fn borrow(&self) -> bool {
+        let previous = self.0.fetch_add(1, Ordering::Acquire);
+        if previous & Self::UNIQUE != 0 {
+            self.0.fetch_sub(1, Ordering::Release);
+            return false;
+        }
+        true
+    }
+    
+    pub fn borrow_mut(&self) -> bool {
+        self.0
+            .compare_exchange(0, Self::UNIQUE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+    
+    pub fn release(&self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+    
+    pub fn release_mut(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
+// Shared borrow of a single component, released when dropped
+pub struct Ref<'a, T> {
+    flag: &'a BorrowFlag,
+    value: &'a T,
+}
+
+impl<'a, T> Ref<'a, T> {
+    // Safety: `flag` must already hold a shared borrow covering `value`
+    pub(crate) unsafe fn new(flag: &'a BorrowFlag, value: &'a T) -> Self {
+        Self { flag, value }
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> Drop for Ref<'_, T> {
+    fn drop(&mut self) {
+        self.flag.release();
+    }
+}
+
+// Unique borrow of a single value, released when dropped
+pub struct RefMut<'a, T> {
+    flag: &'a BorrowFlag,
+    value: &'a mut T,
+}
+
+impl<'a, T> RefMut<'a, T> {
+    // Safety: `flag` must already hold the unique borrow covering `value`
+    pub(crate) unsafe fn new(flag: &'a BorrowFlag, value: &'a mut T) -> Self {
+        Self { flag, value }
+    }
+}
+
+impl<T> Deref for RefMut<'_, T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for RefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T> Drop for RefMut<'_, T> {
+    fn drop(&mut self) {
+        self.flag.release_mut();
+    }
+}
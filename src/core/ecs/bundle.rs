@@ -0,0 +1,47 @@
+use super::{Component, Entity, Sprite, Transform, World};
+use wasm_game_engine_derive::Bundle;
+
+// A group of components added to an entity in one call, e.g.
+// `world.spawn((Transform::new(0.0, 0.0), Velocity::new(1.0, 0.0)))`.
+// Implemented for tuples of up to eight components.
+pub trait Bundle: 'static {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Component + 'static),*> Bundle for ($($name,)*) {
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($name,)*) = self;
+                $(world.add_component(entity, $name);)*
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+
+// The components every drawable sprite needs, e.g.
+// `world.spawn(SpriteBundle::new(x, y, 32.0, 32.0))`
+#[derive(Clone, Copy, Debug, Bundle)]
+pub struct SpriteBundle {
+    pub transform: Transform,
+    pub sprite: Sprite,
+}
+
+impl SpriteBundle {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            transform: Transform::new(x, y),
+            sprite: Sprite::new(width, height),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::ops::{Deref, DerefMut};
+
+// When a component was added to its entity and when it was last mutably
+// accessed, in world ticks. The world tick advances around every system run,
+// so comparing against a system's previous run tells what it hasn't seen yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComponentTicks {
+    added: u64,
+    changed: u64,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u64) -> Self {
+        Self { added: tick, changed: tick }
+    }
+    
+    pub fn added(&self) -> u64 {
+        self.added
+    }
+    
+    pub fn changed(&self) -> u64 {
+        self.changed
+    }
+    
+    pub fn is_added(&self, last_run: u64) -> bool {
+        self.added > last_run
+    }
+    
+    pub fn is_changed(&self, last_run: u64) -> bool {
+        self.changed > last_run
+    }
+    
+    pub(crate) fn set_changed(&mut self, tick: u64) {
+        self.changed = tick;
+    }
+}
+
+// The tick window a query observes: changes stamped after `last_run` are new,
+// and writes made through the query are stamped with `this_run`
+#[derive(Clone, Copy, Debug)]
+pub struct Ticks {
+    last_run: u64,
+    this_run: u64,
+}
+
+impl Ticks {
+    pub(crate) fn new(last_run: u64, this_run: u64) -> Self {
+        Self { last_run, this_run }
+    }
+    
+    pub fn last_run(&self) -> u64 {
+        self.last_run
+    }
+    
+    pub fn this_run(&self) -> u64 {
+        self.this_run
+    }
+}
+
+// Mutable access to a component fetched by a `&mut T` query. Only writing
+// through it (`DerefMut`) marks the component as changed, so systems that
+// merely inspect a value don't trigger `Changed<T>` filters.
+pub struct Mut<'w, T> {
+    value: &'w mut T,
+    component_ticks: &'w mut ComponentTicks,
+    ticks: Ticks,
+}
+
+impl<'w, T> Mut<'w, T> {
+    pub(crate) fn new(value: &'w mut T, component_ticks: &'w mut ComponentTicks, ticks: Ticks) -> Self {
+        Self { value, component_ticks, ticks }
+    }
+    
+    pub fn is_added(&self) -> bool {
+        self.component_ticks.is_added(self.ticks.last_run)
+    }
+    
+    pub fn is_changed(&self) -> bool {
+        self.component_ticks.is_changed(self.ticks.last_run)
+    }
+    
+    // Gives up change tracking and marks the component changed up front
+    pub fn into_inner(self) -> &'w mut T {
+        self.component_ticks.set_changed(self.ticks.this_run);
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.component_ticks.set_changed(self.ticks.this_run);
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    
+    use crate::core::ecs::{Added, Changed, Entity, Stage, Transform, Velocity, World};
+    
+    #[derive(Default)]
+    struct Seen {
+        added: Vec<Entity>,
+        changed: Vec<Entity>,
+        removed: Vec<Entity>,
+    }
+    
+    // A world with a system recording what it sees each run
+    fn watched_world() -> (World, Rc<RefCell<Seen>>) {
+        let seen = Rc::new(RefCell::new(Seen::default()));
+        let log = seen.clone();
+        let mut world = World::new();
+        world.add_system(Stage::Update, move |world: &mut World, _: f32| {
+            let added = world.query_filtered::<&Transform, Added<Transform>>().iter().map(|(entity, _)| entity).collect();
+            let changed = world.query_filtered::<&Transform, Changed<Transform>>().iter().map(|(entity, _)| entity).collect();
+            let removed = world.removed::<Transform>().collect();
+            *log.borrow_mut() = Seen { added, changed, removed };
+        });
+        (world, seen)
+    }
+    
+    #[test]
+    fn systems_see_additions_once() {
+        let (mut world, seen) = watched_world();
+        let entity = world.create_entity();
+        world.add_component(entity, Transform::new(0.0, 0.0));
+        
+        world.update(0.0);
+        assert_eq!((&seen.borrow().added, &seen.borrow().changed), (&vec![entity], &vec![entity]));
+        world.update(0.0);
+        assert!(seen.borrow().added.is_empty() && seen.borrow().changed.is_empty());
+    }
+    
+    #[test]
+    fn systems_see_changes_made_since_their_last_run() {
+        let (mut world, seen) = watched_world();
+        let first = world.create_entity();
+        world.add_component(first, Transform::new(0.0, 0.0));
+        let second = world.create_entity();
+        world.add_component(second, Transform::new(0.0, 0.0));
+        world.update(0.0);
+        
+        world.get_component_mut::<Transform>(second).unwrap().x = 1.0;
+        world.update(0.0);
+        assert!(seen.borrow().added.is_empty());
+        assert_eq!(seen.borrow().changed, [second]);
+        
+        // Other components changing doesn't count
+        world.add_component(first, Velocity::new(1.0, 0.0));
+        world.update(0.0);
+        assert!(seen.borrow().changed.is_empty());
+    }
+    
+    #[test]
+    fn only_writes_through_queries_mark_changes() {
+        let (mut world, seen) = watched_world();
+        let read = world.create_entity();
+        world.add_component(read, Transform::new(0.0, 0.0));
+        let written = world.create_entity();
+        world.add_component(written, Transform::new(0.0, 0.0));
+        world.update(0.0);
+        
+        for (entity, mut transform) in world.query::<&mut Transform>().iter() {
+            if entity == written {
+                transform.x = 1.0;
+            }
+        }
+        world.update(0.0);
+        assert_eq!(seen.borrow().changed, [written]);
+    }
+    
+    #[test]
+    fn systems_see_removals_since_their_last_run() {
+        let (mut world, seen) = watched_world();
+        let removed = world.create_entity();
+        world.add_component(removed, Transform::new(0.0, 0.0));
+        let despawned = world.create_entity();
+        world.add_component(despawned, Transform::new(0.0, 0.0));
+        world.update(0.0);
+        
+        world.remove_component::<Transform>(removed);
+        world.despawn(despawned);
+        world.update(0.0);
+        assert_eq!(seen.borrow().removed, [removed, despawned]);
+        
+        world.clear_trackers();
+        world.update(0.0);
+        assert!(seen.borrow().removed.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+
+use super::{Bundle, Component, Entity, World};
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    pub fn push(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+    
+    pub fn take(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+// Records structural changes (spawning, despawning, adding and removing
+// components) while a system only holds shared borrows of the world, e.g.
+// inside a query loop. The scheduler applies them at the end of each stage;
+// `World::apply_commands` flushes them manually.
+pub struct Commands<'w> {
+    queue: &'w RefCell<CommandQueue>,
+}
+
+impl<'w> Commands<'w> {
+    pub(crate) fn new(queue: &'w RefCell<CommandQueue>) -> Self {
+        Self { queue }
+    }
+    
+    // Queues an arbitrary change to the world
+    pub fn add(&self, command: impl FnOnce(&mut World) + 'static) {
+        self.queue.borrow_mut().push(Box::new(command));
+    }
+    
+    pub fn spawn<B: Bundle>(&self, bundle: B) {
+        self.add(move |world| {
+            world.spawn(bundle);
+        });
+    }
+    
+    pub fn despawn(&self, entity: Entity) {
+        self.add(move |world| {
+            world.despawn(entity);
+        });
+    }
+    
+    // Despawns `entity` along with everything listed in its `Children`,
+    // recursively
+    pub fn despawn_recursive(&self, entity: Entity) {
+        self.add(move |world| {
+            world.despawn_recursive(entity);
+        });
+    }
+    
+    pub fn set_parent(&self, child: Entity, parent: Entity) {
+        self.add(move |world| {
+            world.set_parent(child, parent);
+        });
+    }
+    
+    pub fn insert<B: Bundle>(&self, entity: Entity, bundle: B) {
+        self.add(move |world| world.insert(entity, bundle));
+    }
+    
+    pub fn remove<T: Component + 'static>(&self, entity: Entity) {
+        self.add(move |world| {
+            world.remove_component::<T>(entity);
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::archetype::EntityLocation;
+
+// Handle to an entity. Indices are recycled after despawn, and the generation
+// is bumped each time so a stale handle never resolves to a newer entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+    
+    // Index in the low 32 bits, generation in the high ones
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+    
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+// Serialized as `to_bits`, so scene-local ids (generation 0) come out as
+// plain small numbers
+impl Serialize for Entity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.to_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Entity::from_bits)
+    }
+}
+
+#[derive(Clone)]
+struct EntityMeta {
+    generation: u32,
+    location: Option<EntityLocation>,
+}
+
+// Allocates entity handles and tracks where each live entity is stored
+#[derive(Clone)]
+pub(crate) struct Entities {
+    meta: Vec<EntityMeta>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            meta: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+    
+    pub fn alloc(&mut self) -> Entity {
+        self.len += 1;
+        
+        if let Some(index) = self.free.pop() {
+            let meta = &self.meta[index as usize];
+            return Entity { index, generation: meta.generation };
+        }
+        
+        let index = self.meta.len() as u32;
+        self.meta.push(EntityMeta {
+            generation: 0,
+            location: None,
+        });
+        Entity { index, generation: 0 }
+    }
+    
+    // Retires `entity`, returning where it was stored if it was still alive
+    pub fn free(&mut self, entity: Entity) -> Option<EntityLocation> {
+        let location = self.location(entity)?;
+        
+        let meta = &mut self.meta[entity.index as usize];
+        meta.generation = meta.generation.wrapping_add(1);
+        meta.location = None;
+        
+        self.free.push(entity.index);
+        self.len -= 1;
+        Some(location)
+    }
+    
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.location(entity).is_some()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    
+    pub fn location(&self, entity: Entity) -> Option<EntityLocation> {
+        let meta = self.meta.get(entity.index as usize)?;
+        if meta.generation != entity.generation {
+            return None;
+        }
+        meta.location
+    }
+    
+    pub fn set_location(&mut self, entity: Entity, location: EntityLocation) {
+        self.meta[entity.index as usize].location = Some(location);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::{Transform, World};
+    
+    fn alloc_at(entities: &mut Entities, row: usize) -> Entity {
+        let entity = entities.alloc();
+        entities.set_location(entity, EntityLocation { archetype: 0, row });
+        entity
+    }
+    
+    #[test]
+    fn reuses_freed_indices_with_a_new_generation() {
+        let mut entities = Entities::new();
+        let first = alloc_at(&mut entities, 0);
+        let second = alloc_at(&mut entities, 1);
+        assert_ne!(first.index(), second.index());
+        
+        entities.free(first);
+        assert!(!entities.contains(first));
+        assert!(entities.free(first).is_none());
+        
+        let reused = alloc_at(&mut entities, 0);
+        assert_eq!(reused.index(), first.index());
+        assert_eq!(reused.generation(), first.generation() + 1);
+        assert!(entities.contains(reused) && !entities.contains(first));
+        assert_eq!(entities.len(), 2);
+    }
+    
+    #[test]
+    fn round_trips_through_bits() {
+        let mut entities = Entities::new();
+        let entity = alloc_at(&mut entities, 0);
+        entities.free(entity);
+        let entity = entities.alloc();
+        assert_eq!(entity.generation(), 1);
+        assert_eq!(Entity::from_bits(entity.to_bits()), entity);
+    }
+    
+    #[test]
+    fn stale_handles_miss_the_reused_entity() {
+        let mut world = World::new();
+        let stale = world.create_entity();
+        world.add_component(stale, Transform::new(1.0, 1.0));
+        assert!(world.despawn(stale));
+        assert!(!world.despawn(stale));
+        
+        let fresh = world.create_entity();
+        world.add_component(fresh, Transform::new(2.0, 2.0));
+        assert_eq!(fresh.index(), stale.index());
+        assert!(!world.is_alive(stale) && world.is_alive(fresh));
+        assert!(world.get_component::<Transform>(stale).is_none());
+        assert!(world.get_component_mut::<Transform>(stale).is_none());
+        assert_eq!(world.get_component::<Transform>(fresh).unwrap().x, 2.0);
+        assert_eq!(world.entity_count(), 1);
+    }
+    
+    #[test]
+    fn despawning_moves_the_last_row_into_the_gap() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..3)
+            .map(|index| {
+                let entity = world.create_entity();
+                world.add_component(entity, Transform::new(index as f32, 0.0));
+                entity
+            })
+            .collect();
+        world.despawn(entities[0]);
+        
+        assert_eq!(world.get_component::<Transform>(entities[1]).unwrap().x, 1.0);
+        assert_eq!(world.get_component::<Transform>(entities[2]).unwrap().x, 2.0);
+        assert_eq!(world.query::<&Transform>().iter().count(), 2);
+    }
+}
@@ -0,0 +1,175 @@
+use std::marker::PhantomData;
+
+use super::{Ref, RefMut, World};
+
+// Double-buffered queue of `T` events, stored as a world resource by
+// `World::add_event`. Each `World::update` swaps the buffers, so an event
+// stays readable during the frame it was sent in and the one after, then is
+// dropped.
+#[derive(Clone)]
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    // Ids of `previous[0]` and `current[0]`; ids increase by one per event
+    previous_start: usize,
+    current_start: usize,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+    
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+    
+    // Total number of events ever sent, i.e. the id the next one will get
+    pub fn event_count(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    // Drops the older buffer and starts a new one
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.previous_start = self.current_start;
+        self.current_start = self.previous_start + self.previous.len();
+    }
+    
+    pub fn clear(&mut self) {
+        self.current_start = self.event_count();
+        self.previous_start = self.current_start;
+        self.previous.clear();
+        self.current.clear();
+    }
+    
+    // Events with an id of at least `start`, oldest first
+    fn iter_from(&self, start: usize) -> impl Iterator<Item = &T> {
+        let previous = start.saturating_sub(self.previous_start).min(self.previous.len());
+        let current = start.saturating_sub(self.current_start).min(self.current.len());
+        self.previous[previous..].iter().chain(&self.current[current..])
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Sends `T` events; obtained with `World::event_writer`
+pub struct EventWriter<'w, T: 'static> {
+    events: RefMut<'w, Events<T>>,
+}
+
+impl<'w, T: 'static> EventWriter<'w, T> {
+    pub(crate) fn new(events: RefMut<'w, Events<T>>) -> Self {
+        Self { events }
+    }
+    
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
+    
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.events.current.extend(events);
+    }
+}
+
+// A cursor into `Events<T>` that remembers what it has already read. Keep
+// one per consumer, e.g. captured by the system closure:
+// `let mut hits = EventReader::<CollisionEvent>::new();` and then
+// `for hit in hits.read(&world.events()) { ... }` each run.
+pub struct EventReader<T> {
+    next_id: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> EventReader<T> {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            _marker: PhantomData,
+        }
+    }
+    
+    // Events sent since the previous call, oldest first. Events a reader
+    // didn't get to within two frames are skipped.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> + 'a {
+        let start = self.next_id;
+        self.next_id = events.event_count();
+        events.iter_from(start)
+    }
+    
+    pub fn len(&self, events: &Events<T>) -> usize {
+        events.iter_from(self.next_id).count()
+    }
+    
+    pub fn is_empty(&self, events: &Events<T>) -> bool {
+        self.len(events) == 0
+    }
+    
+    // Marks everything currently queued as read
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.next_id = events.event_count();
+    }
+}
+
+impl<T: 'static> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    // Registers the `T` event queue. Its buffers are swapped at the start of
+    // every `update`. Registering the same type twice is a no-op.
+    pub fn add_event<T: Clone + 'static>(&mut self) {
+        if self.has_resource::<Events<T>>() {
+            return;
+        }
+        self.insert_resource(Events::<T>::new());
+        self.event_updaters.push(|world| {
+            if let Some(mut events) = world.resource_mut::<Events<T>>() {
+                events.update();
+            }
+        });
+    }
+    
+    // Panics if `T` wasn't registered with `add_event`
+    pub fn events<T: 'static>(&self) -> Ref<'_, Events<T>> {
+        self.resource::<Events<T>>()
+            .unwrap_or_else(|| panic!("event `{}` is not registered", std::any::type_name::<T>()))
+    }
+    
+    // Panics if `T` wasn't registered with `add_event`
+    pub fn event_writer<T: 'static>(&self) -> EventWriter<'_, T> {
+        let events = self.resource_mut::<Events<T>>()
+            .unwrap_or_else(|| panic!("event `{}` is not registered", std::any::type_name::<T>()));
+        EventWriter::new(events)
+    }
+    
+    pub fn send_event<T: 'static>(&self, event: T) {
+        self.event_writer::<T>().send(event);
+    }
+    
+    pub(crate) fn update_events(&self) {
+        for update in &self.event_updaters {
+            update(self);
+        }
+    }
+}
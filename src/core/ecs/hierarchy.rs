@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Component, Entity, MapEntities, Transform, Without, World};
+use crate::math::{Mat4, Vec2};
+
+// Entities attached below this one. Maintained by `World::set_parent`;
+// `World::despawn_recursive` removes them together with their parent.
+#[derive(Clone, Debug, Default, Component, Serialize, Deserialize)]
+pub struct Children(pub Vec<Entity>);
+
+// The entity this one's `Transform` is relative to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Component, Serialize, Deserialize)]
+pub struct Parent(pub Entity);
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+        for child in &mut self.0 {
+            *child = map(*child);
+        }
+    }
+}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+        self.0 = map(self.0);
+    }
+}
+
+// World-space placement of an entity, written by `propagate_transforms` from
+// its own `Transform` and those of its ancestors. Stored as a 2D affine
+// transform: the images of the unit axes plus a translation.
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+pub struct GlobalTransform {
+    pub x_axis: Vec2,
+    pub y_axis: Vec2,
+    pub translation: Vec2,
+}
+
+impl GlobalTransform {
+    pub const IDENTITY: GlobalTransform = GlobalTransform {
+        x_axis: Vec2 { x: 1.0, y: 0.0 },
+        y_axis: Vec2 { x: 0.0, y: 1.0 },
+        translation: Vec2 { x: 0.0, y: 0.0 },
+    };
+    
+    pub fn from_transform(transform: &Transform) -> Self {
+        let (sin, cos) = transform.rotation.sin_cos();
+        Self {
+            x_axis: Vec2::new(cos * transform.scale_x, sin * transform.scale_x),
+            y_axis: Vec2::new(-sin * transform.scale_y, cos * transform.scale_y),
+            translation: Vec2::new(transform.x, transform.y),
+        }
+    }
+    
+    // Places `local`, expressed relative to `self`, into world space
+    pub fn mul_transform(&self, local: &Transform) -> Self {
+        let local = Self::from_transform(local);
+        Self {
+            x_axis: self.transform_vector(local.x_axis),
+            y_axis: self.transform_vector(local.y_axis),
+            translation: self.transform_point(local.translation),
+        }
+    }
+    
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.transform_vector(point) + self.translation
+    }
+    
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        self.x_axis * vector.x + self.y_axis * vector.y
+    }
+    
+    pub fn to_mat4(&self) -> Mat4 {
+        Mat4::from_column_major([
+            self.x_axis.x, self.x_axis.y, 0.0, 0.0,
+            self.y_axis.x, self.y_axis.y, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            self.translation.x, self.translation.y, 0.0, 1.0,
+        ])
+    }
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+// Recomputes every `GlobalTransform` from the root entities down. Entities
+// with a `Transform` but no `GlobalTransform` get one first. Run it after
+// gameplay has moved things and before rendering.
+pub fn propagate_transforms(world: &mut World) {
+    let missing: Vec<Entity> = world
+        .query_filtered::<&Transform, Without<GlobalTransform>>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in missing {
+        world.add_component(entity, GlobalTransform::IDENTITY);
+    }
+    
+    let mut pending: Vec<(Entity, GlobalTransform)> = Vec::new();
+    for (_, (transform, mut global, children)) in &mut world.query_filtered::<(&Transform, &mut GlobalTransform, Option<&Children>), Without<Parent>>() {
+        *global = GlobalTransform::from_transform(transform);
+        for &child in children.into_iter().flat_map(|children| &children.0) {
+            pending.push((child, *global));
+        }
+    }
+    
+    while let Some((entity, parent_global)) = pending.pop() {
+        let Some(transform) = world.get_component::<Transform>(entity).map(|transform| *transform) else {
+            continue;
+        };
+        let global = parent_global.mul_transform(&transform);
+        if let Some(slot) = world.get_component_mut::<GlobalTransform>(entity) {
+            *slot = global;
+        }
+        
+        if let Some(children) = world.get_component::<Children>(entity) {
+            pending.extend(children.0.iter().map(|&child| (child, global)));
+        }
+    }
+}
+
+impl World {
+    // Attaches `child` below `parent`, detaching it from any previous parent.
+    // Returns false (and changes nothing) if either entity is dead or the link
+    // would create a cycle.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> bool {
+        if !self.is_alive(child) || !self.is_alive(parent) || self.is_ancestor(child, parent) {
+            return false;
+        }
+        
+        self.remove_parent(child);
+        self.add_component(child, Parent(parent));
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.add_component(parent, Children(vec![child])),
+        }
+        true
+    }
+    
+    // Detaches `child` from its parent, making it a root again
+    pub fn remove_parent(&mut self, child: Entity) {
+        let Some(Parent(parent)) = self.remove_component::<Parent>(child) else {
+            return;
+        };
+        
+        let now_empty = match self.get_component_mut::<Children>(parent) {
+            Some(children) => {
+                children.0.retain(|&entity| entity != child);
+                children.0.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            self.remove_component::<Children>(parent);
+        }
+    }
+    
+    // Whether `ancestor` is `entity` itself or anywhere above it
+    fn is_ancestor(&self, ancestor: Entity, entity: Entity) -> bool {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if entity == ancestor {
+                return true;
+            }
+            current = self.get_component::<Parent>(entity).map(|parent| parent.0);
+        }
+        false
+    }
+    
+    // Unlinks `entity` from the hierarchy ahead of despawning it: it leaves
+    // its parent's `Children`, and its own children become roots
+    pub(crate) fn detach_hierarchy(&mut self, entity: Entity) {
+        if self.has_component::<Parent>(entity) {
+            self.remove_parent(entity);
+        }
+        
+        if let Some(children) = self.remove_component::<Children>(entity) {
+            for child in children.0 {
+                self.remove_component::<Parent>(child);
+            }
+        }
+    }
+}
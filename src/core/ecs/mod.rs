@@ -0,0 +1,664 @@
+mod archetype;
+mod binary;
+mod borrow;
+mod bundle;
+mod change;
+mod commands;
+mod entity;
+mod event;
+mod hierarchy;
+mod name;
+mod parallel;
+mod prefab;
+mod query;
+mod reflect;
+mod resource;
+mod scene;
+mod schedule;
+mod sparse;
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use archetype::Archetypes;
+use change::Ticks;
+use commands::CommandQueue;
+use entity::Entities;
+use name::NameIndex;
+use resource::Resources;
+use schedule::Schedule;
+use sparse::SparseSets;
+pub(crate) use prefab::merge;
+use crate::core::task_pool::TaskPool;
+
+pub use borrow::{Ref, RefMut};
+pub use bundle::{Bundle, SpriteBundle};
+pub use change::{ComponentTicks, Mut};
+pub use commands::Commands;
+pub use entity::Entity;
+pub use event::{EventReader, EventWriter, Events};
+pub use hierarchy::{propagate_transforms, Children, GlobalTransform, Parent};
+pub use name::Name;
+pub use parallel::{Access, ParallelSystem, SystemWorld};
+pub use prefab::{Prefab, PrefabError, Prefabs};
+pub use query::{Added, Changed, QueryBorrow, QueryData, QueryFilter, QueryIter, With, Without};
+pub use reflect::{FieldInfo, FieldType, Reflect, ReflectError, StructInfo, TypeRegistry, Value};
+pub use scene::{MapEntities, Migration, Scene, SceneEntity, SceneError, SceneRegistry};
+pub use schedule::{Stage, SystemDescriptor};
+pub use wasm_game_engine_derive::{Bundle, Component, Reflect};
+
+// Usually implemented with `#[derive(Component)]`, which also accepts
+// `#[component(storage = "sparse")]`. Components must be `Clone` so a whole
+// world can be deep-cloned, and `Send + Sync` so parallel systems can reach
+// them from worker threads.
+pub trait Component: Clone + Send + Sync + 'static {
+    const STORAGE: StorageType = StorageType::Dense;
+}
+
+// How a component type is stored. Dense components live in archetype tables
+// and iterate fastest; sparse ones live in a per-type sparse set, so adding
+// and removing them is cheap (no archetype move). Use sparse for short-lived
+// markers and state that toggles often.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageType {
+    Dense,
+    Sparse,
+}
+
+impl StorageType {
+    pub fn is_sparse(self) -> bool {
+        self == StorageType::Sparse
+    }
+}
+
+pub trait System {
+    fn update(&mut self, world: &mut World, delta_time: f32);
+}
+
+impl<F: FnMut(&mut World, f32)> System for F {
+    fn update(&mut self, world: &mut World, delta_time: f32) {
+        self(world, delta_time)
+    }
+}
+
+// Transform component for position, rotation, scale
+#[derive(Clone, Copy, Debug, Component, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+}
+
+impl Transform {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+// Sprite component for rendering. The color tints the texture, or fills the
+// sprite if it has none.
+#[derive(Clone, Copy, Debug, Component, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sprite {
+    pub width: f32,
+    pub height: f32,
+    pub color_r: f32,
+    pub color_g: f32,
+    pub color_b: f32,
+    pub color_a: f32,
+    pub texture_id: Option<u32>,
+    // The part of the texture to draw, in pixels from its top left, e.g. a
+    // frame of a sprite sheet. A zero width or height means the whole texture.
+    pub source_x: f32,
+    pub source_y: f32,
+    pub source_width: f32,
+    pub source_height: f32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Sprite {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            color_r: 1.0,
+            color_g: 1.0,
+            color_b: 1.0,
+            color_a: 1.0,
+            texture_id: None,
+            source_x: 0.0,
+            source_y: 0.0,
+            source_width: 0.0,
+            source_height: 0.0,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+    
+    pub fn with_color(mut self, r: f32, g: f32, b: f32, a: f32) -> Self {
+        self.color_r = r;
+        self.color_g = g;
+        self.color_b = b;
+        self.color_a = a;
+        self
+    }
+    
+    pub fn with_texture(mut self, texture_id: u32) -> Self {
+        self.texture_id = Some(texture_id);
+        self
+    }
+    
+    pub fn with_source_rect(mut self, x: f32, y: f32, width: f32, height: f32) -> Self {
+        self.source_x = x;
+        self.source_y = y;
+        self.source_width = width;
+        self.source_height = height;
+        self
+    }
+    
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+    
+    // Texture coordinates of the left, top, right and bottom edges for a
+    // texture of the given size, flips applied
+    pub fn uv_rect(&self, texture_width: u32, texture_height: u32) -> (f32, f32, f32, f32) {
+        let (width, height) = (texture_width.max(1) as f32, texture_height.max(1) as f32);
+        let (mut left, mut top, mut right, mut bottom) = if self.source_width > 0.0 && self.source_height > 0.0 {
+            (
+                self.source_x / width,
+                self.source_y / height,
+                (self.source_x + self.source_width) / width,
+                (self.source_y + self.source_height) / height,
+            )
+        } else {
+            (0.0, 0.0, 1.0, 1.0)
+        };
+        if self.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+        (left, top, right, bottom)
+    }
+}
+
+// A white unit square; scale it with `Transform` or set the size directly
+impl Default for Sprite {
+    fn default() -> Self {
+        Self::new(1.0, 1.0)
+    }
+}
+
+// Velocity component for physics
+#[derive(Clone, Copy, Debug, Default, Component, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Velocity {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+// The main ECS world. Components live in archetype tables: every entity with
+// the same set of component types shares one table, and each component type
+// is a contiguous column inside it.
+pub struct World {
+    entities: Entities,
+    archetypes: Archetypes,
+    sparse_sets: SparseSets,
+    names: NameIndex,
+    resources: Resources,
+    schedule: Schedule,
+    commands: RefCell<CommandQueue>,
+    // Stamped on every component write. Advanced around each system run so
+    // a system's own writes are never older than its next `last_run`.
+    change_tick: u64,
+    // What `Added`/`Changed` filters and `removed` compare against: the
+    // running system's previous run, or the last `clear_trackers` outside
+    // of systems
+    last_change_tick: u64,
+    removed: HashMap<TypeId, Vec<(Entity, u64)>>,
+    removed_cutoff: u64,
+    event_updaters: Vec<fn(&World)>,
+    task_pool: Arc<TaskPool>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            entities: Entities::new(),
+            archetypes: Archetypes::new(),
+            sparse_sets: SparseSets::default(),
+            names: NameIndex::default(),
+            resources: Resources::default(),
+            schedule: Schedule::new(),
+            commands: RefCell::new(CommandQueue::default()),
+            change_tick: 1,
+            last_change_tick: 0,
+            removed: HashMap::new(),
+            removed_cutoff: 0,
+            event_updaters: Vec::new(),
+            task_pool: TaskPool::global(),
+        }
+    }
+    
+    pub fn create_entity(&mut self) -> Entity {
+        let entity = self.entities.alloc();
+        let location = self.archetypes.spawn(entity);
+        self.entities.set_location(entity, location);
+        entity
+    }
+    
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.create_entity();
+        bundle.insert_into(self, entity);
+        entity
+    }
+    
+    // Adds every component in `bundle` to `entity`, replacing existing ones
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        if self.is_alive(entity) {
+            bundle.insert_into(self, entity);
+        }
+    }
+    
+    // Destroys `entity` and drops all of its components. Returns false if the
+    // handle was already stale.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        self.detach_hierarchy(entity);
+        self.unindex_name(entity);
+        
+        let Some(location) = self.entities.free(entity) else {
+            return false;
+        };
+        
+        let tick = self.change_tick;
+        let sparse_types = self.sparse_sets.remove_entity(entity);
+        for &type_id in self.archetypes.get(location.archetype).types().iter().chain(&sparse_types) {
+            self.removed.entry(type_id).or_default().push((entity, tick));
+        }
+        
+        if let Some(moved) = self.archetypes.get_mut(location.archetype).remove(location.row) {
+            self.entities.set_location(moved, location);
+        }
+        true
+    }
+    
+    // Despawns `entity` and, recursively, every entity in its `Children`
+    pub fn despawn_recursive(&mut self, entity: Entity) -> bool {
+        let children = self.remove_component::<Children>(entity);
+        for child in children.into_iter().flat_map(|children| children.0) {
+            self.despawn_recursive(child);
+        }
+        self.despawn(entity)
+    }
+    
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+    
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+    
+    pub fn add_component<T: Component + 'static>(&mut self, entity: Entity, component: T) {
+        let Some(location) = self.entities.location(entity) else {
+            return;
+        };
+        
+        if let Some(name) = name::as_name(&component) {
+            self.unindex_name(entity);
+            self.names.insert(name.as_str(), entity);
+        }
+        
+        let tick = self.change_tick;
+        if T::STORAGE.is_sparse() {
+            self.sparse_sets.get_or_insert::<T>().insert(entity, component, tick);
+            return;
+        }
+        
+        // Already has this component type: overwrite in place
+        let Err(component) = self.archetypes.get_mut(location.archetype).replace(location.row, component, tick) else {
+            return;
+        };
+        
+        let target = self.archetypes.add_target::<T>(location.archetype);
+        let (new_location, moved) = self.archetypes.move_entity(location, target, |column, row| column.swap_remove(row));
+        if let Some(moved) = moved {
+            self.entities.set_location(moved, location);
+        }
+        
+        self.archetypes.get_mut(target).push(component, tick);
+        self.entities.set_location(entity, new_location);
+    }
+    
+    // Detaches `T` from `entity` and hands it back, if it had one
+    pub fn remove_component<T: Component + 'static>(&mut self, entity: Entity) -> Option<T> {
+        let location = self.entities.location(entity)?;
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            self.unindex_name(entity);
+        }
+        if T::STORAGE.is_sparse() {
+            let removed = self.sparse_sets.get_mut::<T>()?.remove(entity)?;
+            self.removed.entry(TypeId::of::<T>()).or_default().push((entity, self.change_tick));
+            return Some(removed);
+        }
+        
+        let target = self.archetypes.remove_target::<T>(location.archetype)?;
+        
+        let mut removed = Vec::<T>::with_capacity(1);
+        let (new_location, moved) = self.archetypes.move_entity(location, target, |column, row| {
+            column.move_row(row, &mut removed)
+        });
+        if let Some(moved) = moved {
+            self.entities.set_location(moved, location);
+        }
+        self.entities.set_location(entity, new_location);
+        
+        self.removed.entry(TypeId::of::<T>()).or_default().push((entity, self.change_tick));
+        removed.pop()
+    }
+    
+    pub fn get_component<T: Component + 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let location = self.entities.location(entity)?;
+        if T::STORAGE.is_sparse() {
+            return self.sparse_sets.get::<T>(entity);
+        }
+        self.archetypes.get(location.archetype).get::<T>(location.row)
+    }
+    
+    // Marks the component as changed whether or not it's actually written
+    pub fn get_component_mut<T: Component + 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        let location = self.entities.location(entity)?;
+        if T::STORAGE.is_sparse() {
+            return self.sparse_sets.get_mut::<T>()?.get_mut(entity, self.change_tick);
+        }
+        self.archetypes.get_mut(location.archetype).get_mut::<T>(location.row, self.change_tick)
+    }
+    
+    // When `entity`'s `T` was added and last changed
+    pub fn component_ticks<T: Component + 'static>(&self, entity: Entity) -> Option<ComponentTicks> {
+        let location = self.entities.location(entity)?;
+        if T::STORAGE.is_sparse() {
+            let _component = self.sparse_sets.get::<T>(entity)?;
+            
+            // Safety: `_component` holds a shared borrow of the set until we're done reading
+            return unsafe { (*self.sparse_sets.set_ptr::<T>()?).ticks(entity) };
+        }
+        
+        let archetype = self.archetypes.get(location.archetype);
+        let _component = archetype.get::<T>(location.row)?;
+        
+        // Safety: `_component` holds a shared borrow of the column until we're done reading
+        let ticks = unsafe { archetype.ticks_ptr::<T>()?.add(location.row).read() };
+        Some(ticks)
+    }
+    
+    pub fn has_component<T: Component + 'static>(&self, entity: Entity) -> bool {
+        let Some(location) = self.entities.location(entity) else {
+            return false;
+        };
+        if T::STORAGE.is_sparse() {
+            // Only reads the entity index, so it needn't take a borrow
+            return unsafe { self.sparse_sets.set_ptr::<T>() }
+                .is_some_and(|set| unsafe { (*set).contains(entity) });
+        }
+        self.archetypes.get(location.archetype).has(TypeId::of::<T>())
+    }
+    
+    // Every entity that has a `T`. Works on whole archetypes (or the sparse
+    // set's entity list) without touching component data, so it's the cheap
+    // way to collect zero-sized tags, e.g. `world.entities_with::<EnemyTag>()`
+    // for a `#[derive(Clone, Component)] struct EnemyTag;`.
+    pub fn entities_with<T: Component + 'static>(&self) -> impl Iterator<Item = Entity> + '_ {
+        let sparse = if T::STORAGE.is_sparse() {
+            // Only reads the entity list, which can't change while `self` is
+            // shared, so it needn't take a borrow
+            unsafe { self.sparse_sets.set_ptr::<T>() }.map(|set| unsafe { (*set).entities() })
+        } else {
+            None
+        };
+        
+        self.archetypes
+            .iter()
+            .filter(|archetype| !T::STORAGE.is_sparse() && archetype.has(TypeId::of::<T>()))
+            .flat_map(|archetype| archetype.entities().iter().copied())
+            .chain(sparse.into_iter().flatten().copied())
+    }
+    
+    // Iterates every entity matching `Q`, e.g. `world.query::<(&mut Transform, &Velocity)>()`.
+    // Only the touched columns are borrowed, so several queries can be alive
+    // at once as long as they don't conflict.
+    pub fn query<Q: QueryData>(&self) -> QueryBorrow<'_, Q> {
+        QueryBorrow::new(self)
+    }
+    
+    // Like `query`, additionally narrowed by filters such as `With<T>` and `Without<T>`
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> QueryBorrow<'_, Q, F> {
+        QueryBorrow::new(self)
+    }
+    
+    // Entities that lost their `T` (through `remove_component` or despawn)
+    // since the running system last ran. Removals are kept for two
+    // `clear_trackers` calls, so systems running every frame see all of them.
+    pub fn removed<T: Component + 'static>(&self) -> impl Iterator<Item = Entity> + '_ {
+        let last_run = self.last_change_tick;
+        self.removed
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .filter(move |&&(_, tick)| tick > last_run)
+            .map(|&(entity, _)| entity)
+    }
+    
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick
+    }
+    
+    // Ends a frame for change detection outside of systems: later queries
+    // and `removed` calls only report what happens after this point. The
+    // engine calls it once per frame after rendering.
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.change_tick;
+        self.change_tick += 1;
+        
+        let cutoff = self.removed_cutoff;
+        for removed in self.removed.values_mut() {
+            removed.retain(|&(_, tick)| tick > cutoff);
+        }
+        self.removed_cutoff = self.last_change_tick;
+    }
+    
+    pub(crate) fn ticks(&self) -> Ticks {
+        Ticks::new(self.last_change_tick, self.change_tick)
+    }
+    
+    // Runs `run` as a system that last ran at `last_run`, updating it to the
+    // tick this run's writes are stamped with
+    pub(crate) fn run_system(&mut self, last_run: &mut u64, run: impl FnOnce(&mut World)) {
+        let outer_last_change_tick = self.last_change_tick;
+        self.change_tick += 1;
+        self.last_change_tick = *last_run;
+        
+        run(self);
+        
+        *last_run = self.change_tick;
+        self.last_change_tick = outer_last_change_tick;
+        self.change_tick += 1;
+    }
+    
+    // Stores a singleton value, replacing any previous one of the same type.
+    // Resources must be `Clone` for `World::clone`; wrap shared services in
+    // an `Rc` so clones of the world share them.
+    pub fn insert_resource<T: Clone + 'static>(&mut self, resource: T) {
+        self.resources.insert(resource);
+    }
+    
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+    
+    pub fn has_resource<T: 'static>(&self) -> bool {
+        self.resources.contains::<T>()
+    }
+    
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        self.resources.get::<T>()
+    }
+    
+    // Only needs `&self`, so systems can update e.g. a `Random` resource while
+    // iterating a query
+    pub fn resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        self.resources.get_mut::<T>()
+    }
+    
+    // Registers a system, e.g. `world.add_system(Stage::Update, movement)` or
+    // with ordering: `SystemDescriptor::new(movement).after("input")`
+    pub fn add_system(&mut self, stage: Stage, system: impl Into<SystemDescriptor>) {
+        self.schedule.add_system(stage, system.into());
+    }
+    
+    pub fn set_fixed_time_step(&mut self, step: f32) {
+        self.schedule.set_fixed_time_step(step);
+    }
+    
+    // Runs every system in `stage`, then applies the commands they recorded
+    pub fn run_stage(&mut self, stage: Stage, delta_time: f32) {
+        // Take the schedule out so each system can borrow the world mutably;
+        // anything registered meanwhile lands in a fresh schedule we merge back
+        let mut schedule = std::mem::replace(&mut self.schedule, Schedule::new());
+        schedule.run_stage(stage, self, delta_time);
+        let added = std::mem::replace(&mut self.schedule, schedule);
+        self.schedule.merge(added);
+        
+        self.apply_commands();
+    }
+    
+    // Hands out a buffer for deferred structural changes. Only needs `&self`,
+    // so it can be used while queries are borrowing the world.
+    pub fn commands(&self) -> Commands<'_> {
+        Commands::new(&self.commands)
+    }
+    
+    pub fn apply_commands(&mut self) {
+        loop {
+            let commands = self.commands.get_mut().take();
+            if commands.is_empty() {
+                break;
+            }
+            for command in commands {
+                command(self);
+            }
+        }
+    }
+    
+    // Runs one frame of every stage except `Render`, after swapping the event
+    // buffers. `delta_time` is in seconds.
+    pub fn update(&mut self, delta_time: f32) {
+        self.update_events();
+        self.run_stage(Stage::PreUpdate, delta_time);
+        
+        let fixed_steps = self.schedule.accumulate(delta_time);
+        let fixed_time_step = self.schedule.fixed_time_step();
+        for _ in 0..fixed_steps {
+            self.run_stage(Stage::FixedUpdate, fixed_time_step);
+        }
+        
+        self.run_stage(Stage::Update, delta_time);
+        self.run_stage(Stage::PostUpdate, delta_time);
+    }
+    
+    // Helper methods for common operations
+    pub fn set_position(&mut self, entity: Entity, x: f32, y: f32) {
+        if let Some(transform) = self.get_component_mut::<Transform>(entity) {
+            transform.x = x;
+            transform.y = y;
+        }
+    }
+    
+    pub fn set_color(&mut self, entity: Entity, r: f32, g: f32, b: f32, a: f32) {
+        if let Some(sprite) = self.get_component_mut::<Sprite>(entity) {
+            sprite.color_r = r;
+            sprite.color_g = g;
+            sprite.color_b = b;
+            sprite.color_a = a;
+        }
+    }
+    
+    pub fn get_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.archetypes
+            .iter()
+            .flat_map(|archetype| archetype.entities().iter().copied())
+    }
+}
+
+// Deep-copies every entity, component and resource, e.g. to snapshot a level.
+// Systems and pending commands aren't copied, so a clone starts with an empty
+// schedule. `clone_from` keeps the destination's systems instead, which makes
+// `world.clone_from(&snapshot)` a level restart.
+impl Clone for World {
+    fn clone(&self) -> Self {
+        Self {
+            entities: self.entities.clone(),
+            archetypes: self.archetypes.clone(),
+            sparse_sets: self.sparse_sets.clone(),
+            names: self.names.clone(),
+            resources: self.resources.clone(),
+            schedule: self.schedule.without_systems(),
+            commands: RefCell::new(CommandQueue::default()),
+            change_tick: self.change_tick,
+            last_change_tick: self.last_change_tick,
+            removed: self.removed.clone(),
+            removed_cutoff: self.removed_cutoff,
+            event_updaters: self.event_updaters.clone(),
+            task_pool: self.task_pool.clone(),
+        }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        let mut schedule = std::mem::replace(&mut self.schedule, Schedule::new());
+        *self = source.clone();
+        
+        // The systems' last runs were ticks of the old contents; restart them
+        // so the restored entities all read as freshly added
+        schedule.reset_change_ticks();
+        self.schedule = schedule;
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,117 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Component, Entity, World};
+
+// A human-readable label for an entity, indexed by the world so
+// `world.find_by_name("player")` is a hash lookup. Several entities may share
+// a name. Rename through `World::set_name` (or `add_component`) so the index
+// stays current.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Component, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Name(String);
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+    
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+// `component` as a `Name`, if that's what it is. Lets the generic component
+// paths keep the index in sync; the check folds away for every other type.
+pub(crate) fn as_name<T: 'static>(component: &T) -> Option<&Name> {
+    (component as &dyn Any).downcast_ref::<Name>()
+}
+
+// Entities by name, in the order they were given it
+#[derive(Clone, Default)]
+pub(crate) struct NameIndex {
+    entities: HashMap<String, Vec<Entity>>,
+}
+
+impl NameIndex {
+    pub fn insert(&mut self, name: &str, entity: Entity) {
+        self.entities.entry(name.to_string()).or_default().push(entity);
+    }
+    
+    pub fn remove(&mut self, name: &str, entity: Entity) {
+        let Some(entities) = self.entities.get_mut(name) else {
+            return;
+        };
+        entities.retain(|&other| other != entity);
+        if entities.is_empty() {
+            self.entities.remove(name);
+        }
+    }
+    
+    pub fn get(&self, name: &str) -> &[Entity] {
+        self.entities.get(name).map_or(&[], |entities| entities.as_slice())
+    }
+}
+
+impl World {
+    // The first entity given `name` that still has it
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.find_all_by_name(name).next()
+    }
+    
+    // Every entity currently called `name`
+    pub fn find_all_by_name<'w>(&'w self, name: &'w str) -> impl Iterator<Item = Entity> + 'w {
+        // Double-check against the component in case it was overwritten
+        // through `get_component_mut`, bypassing the index
+        self.names.get(name).iter().copied().filter(move |&entity| {
+            self.get_component::<Name>(entity).is_some_and(|current| current.as_str() == name)
+        })
+    }
+    
+    pub fn set_name(&mut self, entity: Entity, name: impl Into<Name>) {
+        self.add_component(entity, name.into());
+    }
+    
+    pub fn name(&self, entity: Entity) -> Option<String> {
+        self.get_component::<Name>(entity).map(|name| name.0.clone())
+    }
+    
+    // Drops `entity`'s current name from the index ahead of it being
+    // replaced or removed
+    pub(crate) fn unindex_name(&mut self, entity: Entity) {
+        let Some(name) = self.get_component::<Name>(entity).map(|name| name.0.clone()) else {
+            return;
+        };
+        self.names.remove(&name, entity);
+    }
+}
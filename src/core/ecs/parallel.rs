@@ -0,0 +1,292 @@
+use std::any::{type_name, TypeId};
+use std::sync::Arc;
+
+use super::change::Ticks;
+use super::event::{EventWriter, Events};
+use super::{Component, Entity, QueryBorrow, QueryData, QueryFilter, Ref, RefMut, World};
+use crate::core::task_pool::TaskPool;
+
+// The component types and resources a system reads and writes, e.g.
+// `Access::new().query::<(&mut Transform, &Velocity)>().read_resource::<Gravity>()`.
+// Systems whose accesses don't conflict may run at the same time.
+#[derive(Clone, Debug, Default)]
+pub struct Access {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+    resource_reads: Vec<TypeId>,
+    resource_writes: Vec<TypeId>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Everything `world.query_filtered::<Q, F>()` touches
+    pub fn query<Q: QueryData>(mut self) -> Self {
+        Q::add_access(&mut self);
+        self
+    }
+    
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(mut self) -> Self {
+        Q::add_access(&mut self);
+        F::add_access(&mut self);
+        self
+    }
+    
+    pub fn read<T: Component>(mut self) -> Self {
+        self.add_read(TypeId::of::<T>());
+        self
+    }
+    
+    pub fn write<T: Component>(mut self) -> Self {
+        self.add_write(TypeId::of::<T>());
+        self
+    }
+    
+    pub fn read_resource<R: Send + Sync + 'static>(mut self) -> Self {
+        insert(&mut self.resource_reads, TypeId::of::<R>());
+        self
+    }
+    
+    pub fn write_resource<R: Send + Sync + 'static>(mut self) -> Self {
+        insert(&mut self.resource_writes, TypeId::of::<R>());
+        self
+    }
+    
+    // Reading events counts as reading their queue
+    pub fn read_events<T: Send + Sync + 'static>(self) -> Self {
+        self.read_resource::<Events<T>>()
+    }
+    
+    pub fn write_events<T: Send + Sync + 'static>(self) -> Self {
+        self.write_resource::<Events<T>>()
+    }
+    
+    pub(crate) fn add_read(&mut self, type_id: TypeId) {
+        insert(&mut self.reads, type_id);
+    }
+    
+    pub(crate) fn add_write(&mut self, type_id: TypeId) {
+        insert(&mut self.writes, type_id);
+    }
+    
+    // Whether two systems can run at once: neither writes anything the
+    // other touches
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        let disjoint = |writes: &[TypeId], reads: &[TypeId], other_writes: &[TypeId]| {
+            writes.iter().all(|type_id| !reads.contains(type_id) && !other_writes.contains(type_id))
+        };
+        disjoint(&self.writes, &other.reads, &other.writes)
+            && disjoint(&other.writes, &self.reads, &self.writes)
+            && disjoint(&self.resource_writes, &other.resource_reads, &other.resource_writes)
+            && disjoint(&other.resource_writes, &self.resource_reads, &self.resource_writes)
+    }
+    
+    // Whether everything `other` touches is declared here
+    fn covers(&self, other: &Access) -> bool {
+        other.reads.iter().all(|type_id| self.reads.contains(type_id) || self.writes.contains(type_id))
+            && other.writes.iter().all(|type_id| self.writes.contains(type_id))
+    }
+    
+    fn can_read_resource(&self, type_id: TypeId) -> bool {
+        self.resource_reads.contains(&type_id) || self.resource_writes.contains(&type_id)
+    }
+}
+
+fn insert(types: &mut Vec<TypeId>, type_id: TypeId) {
+    if !types.contains(&type_id) {
+        types.push(type_id);
+    }
+}
+
+// What a parallel system sees of the world: queries, components and
+// resources, limited to those in its declared `Access`. Touching anything
+// else panics, which is what makes running it on another thread safe.
+// Structural changes need an exclusive system.
+pub struct SystemWorld<'w> {
+    world: &'w World,
+    access: &'w Access,
+    ticks: Ticks,
+}
+
+// Safety: a `SystemWorld` only reaches data its `Access` declares, all of
+// which is `Send + Sync` (components by trait bound, resources by the bounds
+// on the accessors), and the scheduler never runs two systems with
+// conflicting accesses at once. The shared structures it walks to get there
+// (archetype and resource tables) are only modified through `&mut World`.
+unsafe impl Send for SystemWorld<'_> {}
+
+impl<'w> SystemWorld<'w> {
+    pub(crate) fn new(world: &'w World, access: &'w Access, ticks: Ticks) -> Self {
+        Self { world, access, ticks }
+    }
+    
+    pub fn query<Q: QueryData>(&self) -> QueryBorrow<'w, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+    
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> QueryBorrow<'w, Q, F> {
+        if !self.access.covers(&Access::new().query_filtered::<Q, F>()) {
+            panic!("system queried `{}` without declaring it in its `Access`", type_name::<(Q, F)>());
+        }
+        QueryBorrow::with_ticks(self.world, self.ticks)
+    }
+    
+    pub fn get_component<T: Component>(&self, entity: Entity) -> Option<Ref<'w, T>> {
+        if !self.access.covers(&Access::new().read::<T>()) {
+            panic!("system read `{}` without declaring it in its `Access`", type_name::<T>());
+        }
+        self.world.get_component::<T>(entity)
+    }
+    
+    pub fn resource<R: Send + Sync + 'static>(&self) -> Option<Ref<'w, R>> {
+        if !self.access.can_read_resource(TypeId::of::<R>()) {
+            panic!("system read resource `{}` without declaring it in its `Access`", type_name::<R>());
+        }
+        self.world.resource::<R>()
+    }
+    
+    pub fn resource_mut<R: Send + Sync + 'static>(&self) -> Option<RefMut<'w, R>> {
+        if !self.access.resource_writes.contains(&TypeId::of::<R>()) {
+            panic!("system wrote resource `{}` without declaring it in its `Access`", type_name::<R>());
+        }
+        self.world.resource_mut::<R>()
+    }
+    
+    // Panics if `T` wasn't registered with `add_event`
+    pub fn events<T: Send + Sync + 'static>(&self) -> Ref<'w, Events<T>> {
+        self.resource::<Events<T>>()
+            .unwrap_or_else(|| panic!("event `{}` is not registered", type_name::<T>()))
+    }
+    
+    // Panics if `T` wasn't registered with `add_event`
+    pub fn event_writer<T: Send + Sync + 'static>(&self) -> EventWriter<'w, T> {
+        let events = self.resource_mut::<Events<T>>()
+            .unwrap_or_else(|| panic!("event `{}` is not registered", type_name::<T>()));
+        EventWriter::new(events)
+    }
+    
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.world.is_alive(entity)
+    }
+    
+    pub fn entity_count(&self) -> usize {
+        self.world.entity_count()
+    }
+}
+
+// A system that may run on a worker thread alongside others, e.g.
+// `SystemDescriptor::parallel(Access::new().query::<(&mut Transform, &Velocity)>(), movement)`
+// with `fn movement(world: &SystemWorld, delta_time: f32)`.
+pub trait ParallelSystem: Send {
+    fn run(&mut self, world: &SystemWorld, delta_time: f32);
+}
+
+impl<F: FnMut(&SystemWorld, f32) + Send> ParallelSystem for F {
+    fn run(&mut self, world: &SystemWorld, delta_time: f32) {
+        self(world, delta_time)
+    }
+}
+
+impl World {
+    // Replaces the thread pool parallel systems run on, e.g. with
+    // `TaskPool::new()` to run everything on the calling thread
+    pub fn set_task_pool(&mut self, task_pool: Arc<TaskPool>) {
+        self.task_pool = task_pool;
+    }
+    
+    pub fn task_pool(&self) -> &Arc<TaskPool> {
+        &self.task_pool
+    }
+    
+    // Runs `systems`, whose accesses must be pairwise compatible, on the task
+    // pool. Like `run_system`, but all of them share one tick.
+    pub(crate) fn run_parallel<'s>(
+        &mut self,
+        systems: impl Iterator<Item = (&'s mut u64, &'s mut (dyn ParallelSystem + 'static), &'s Access)>,
+        delta_time: f32,
+    ) {
+        self.change_tick += 1;
+        let this_run = self.change_tick;
+        
+        let task_pool = self.task_pool.clone();
+        let world = &*self;
+        task_pool.scope(|scope| {
+            for (last_run, system, access) in systems {
+                let system_world = SystemWorld::new(world, access, Ticks::new(*last_run, this_run));
+                *last_run = this_run;
+                scope.spawn(move || system.run(&system_world, delta_time));
+            }
+        });
+        
+        self.change_tick += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::{Stage, SystemDescriptor, Transform, Velocity};
+    
+    fn world_on_threads() -> World {
+        let mut world = World::new();
+        world.set_task_pool(Arc::new(TaskPool::with_threads(2)));
+        for index in 0..100 {
+            let entity = world.create_entity();
+            world.add_component(entity, Transform::new(index as f32, 0.0));
+            world.add_component(entity, Velocity::new(1.0, 2.0));
+        }
+        world
+    }
+    
+    #[test]
+    fn runs_compatible_systems_together() {
+        let mut world = world_on_threads();
+        world.add_system(
+            Stage::Update,
+            SystemDescriptor::parallel(Access::new().query::<(&mut Transform, &Velocity)>(), |world: &SystemWorld, delta_time: f32| {
+                for (_, (mut transform, velocity)) in world.query::<(&mut Transform, &Velocity)>().iter() {
+                    transform.x += velocity.x * delta_time;
+                    transform.y += velocity.y * delta_time;
+                }
+            }),
+        );
+        world.add_system(
+            Stage::Update,
+            SystemDescriptor::parallel(Access::new().query::<&Velocity>(), |world: &SystemWorld, _: f32| {
+                assert_eq!(world.query::<&Velocity>().iter().count(), 100);
+            }),
+        );
+        
+        world.update(0.5);
+        let mut query = world.query::<&Transform>();
+        let mut positions: Vec<(f32, f32)> = query.iter().map(|(_, transform)| (transform.x, transform.y)).collect();
+        positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(positions[0], (0.5, 1.0));
+        assert_eq!(positions[99], (99.5, 1.0));
+    }
+    
+    #[test]
+    #[should_panic(expected = "without declaring it")]
+    fn undeclared_access_panics() {
+        let mut world = world_on_threads();
+        world.add_system(
+            Stage::Update,
+            SystemDescriptor::parallel(Access::new().query::<&Transform>(), |world: &SystemWorld, _: f32| {
+                world.query::<&mut Transform>();
+            }),
+        );
+        world.update(0.0);
+    }
+    
+    #[test]
+    fn access_conflicts_only_on_writes() {
+        let reads = Access::new().read::<Transform>().read_resource::<u32>();
+        assert!(reads.is_compatible(&Access::new().read::<Transform>().read_resource::<u32>()));
+        assert!(!reads.is_compatible(&Access::new().write::<Transform>()));
+        assert!(!reads.is_compatible(&Access::new().write_resource::<u32>()));
+        assert!(!Access::new().write::<Velocity>().is_compatible(&Access::new().write::<Velocity>()));
+        assert!(Access::new().write::<Velocity>().is_compatible(&Access::new().write::<Transform>()));
+    }
+}
@@ -0,0 +1,215 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::reflect::{ComponentData, Plain};
+use super::{Bundle, Component, Entity, TypeRegistry, World};
+
+#[derive(Debug)]
+pub enum PrefabError {
+    UnknownPrefab(String),
+    UnknownComponent(String),
+    // The prefab or override data isn't valid JSON, or isn't an object
+    InvalidJson(String),
+    InvalidComponent { component: String, message: String },
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::UnknownPrefab(name) => write!(f, "unknown prefab `{}`", name),
+            PrefabError::UnknownComponent(name) => write!(f, "unknown component type `{}`", name),
+            PrefabError::InvalidJson(message) => write!(f, "invalid prefab data: {}", message),
+            PrefabError::InvalidComponent { component, message } => {
+                write!(f, "invalid `{}` data: {}", component, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+// An entity template: a set of component values that `World::instantiate`
+// copies onto a new entity. Build one in code with `with`, or from data with
+// `TypeRegistry::parse_prefab`.
+#[derive(Default)]
+pub struct Prefab {
+    components: Vec<Box<dyn ComponentData>>,
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Adds `component`, replacing any existing one of the same type
+    pub fn with<T: Component + Clone + Serialize>(mut self, component: T) -> Self {
+        self.set(Box::new(Plain(component)));
+        self
+    }
+    
+    fn set(&mut self, component: Box<dyn ComponentData>) {
+        let type_id = component.type_id();
+        match self.components.iter_mut().find(|existing| existing.type_id() == type_id) {
+            Some(existing) => *existing = component,
+            None => self.components.push(component),
+        }
+    }
+    
+    fn get(&self, type_id: TypeId) -> Option<&dyn ComponentData> {
+        self.components
+            .iter()
+            .find(|component| component.type_id() == type_id)
+            .map(|component| component.as_ref())
+    }
+    
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+    
+    pub fn spawn(&self, world: &mut World) -> Entity {
+        let entity = world.create_entity();
+        for component in &self.components {
+            component.insert(world, entity);
+        }
+        entity
+    }
+}
+
+impl Clone for Prefab {
+    fn clone(&self) -> Self {
+        Self {
+            components: self.components.iter().map(|component| component.clone_box()).collect(),
+        }
+    }
+}
+
+// Prefabs registered by name. Lives in the world as a resource. Prefab data
+// may name any component in the world's `TypeRegistry`.
+#[derive(Clone, Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, Prefab>,
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn register(&mut self, name: &str, prefab: Prefab) {
+        self.prefabs.insert(name.to_string(), prefab);
+    }
+    
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+    
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+}
+
+impl TypeRegistry {
+    // Builds a prefab from a JSON object mapping component names to their
+    // fields, e.g. `{"Transform": {"x": 10}, "Sprite": {"width": 32, "height": 32}}`.
+    // Fields left out take the component's default.
+    pub fn parse_prefab(&self, json: &str) -> Result<Prefab, PrefabError> {
+        self.apply_overrides(&Prefab::new(), json)
+    }
+    
+    // Copies `prefab` with the components in `json` (same shape as for
+    // `parse_prefab`) merged over it field by field
+    pub fn apply_overrides(&self, prefab: &Prefab, json: &str) -> Result<Prefab, PrefabError> {
+        let mut result = prefab.clone();
+        for (name, fields) in parse_object(json)? {
+            let registration = self
+                .registration(&name)
+                .ok_or_else(|| PrefabError::UnknownComponent(name.clone()))?;
+            
+            let mut value = result.get(registration.type_id).map_or(Value::Object(Map::new()), |base| base.to_json());
+            merge(&mut value, fields);
+            
+            let component = (registration.load)(value).map_err(|error| PrefabError::InvalidComponent {
+                component: name.clone(),
+                message: error.to_string(),
+            })?;
+            result.set(component);
+        }
+        Ok(result)
+    }
+}
+
+fn parse_object(json: &str) -> Result<Map<String, Value>, PrefabError> {
+    match serde_json::from_str(json) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(PrefabError::InvalidJson("expected an object of components".to_string())),
+        Err(error) => Err(PrefabError::InvalidJson(error.to_string())),
+    }
+}
+
+// Overwrites the fields of `base` that `overrides` sets, recursing into
+// nested objects
+pub(crate) fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+impl World {
+    // The prefab registry, created on first use
+    pub fn prefabs_mut(&mut self) -> &mut Prefabs {
+        if !self.has_resource::<Prefabs>() {
+            self.insert_resource(Prefabs::new());
+        }
+        self.resources.get_exclusive::<Prefabs>().expect("prefab registry missing")
+    }
+    
+    pub fn register_prefab(&mut self, name: &str, prefab: Prefab) {
+        self.prefabs_mut().register(name, prefab);
+    }
+    
+    // Registers a prefab described as JSON, see `TypeRegistry::parse_prefab`
+    pub fn register_prefab_json(&mut self, name: &str, json: &str) -> Result<(), PrefabError> {
+        let prefab = self.with_type_registry(|registry| registry.parse_prefab(json))?;
+        self.register_prefab(name, prefab);
+        Ok(())
+    }
+    
+    pub fn instantiate(&mut self, name: &str) -> Result<Entity, PrefabError> {
+        Ok(self.prefab(name)?.spawn(self))
+    }
+    
+    // Spawns the prefab and then adds `overrides`, replacing any of its
+    // components of the same types
+    pub fn instantiate_with<B: Bundle>(&mut self, name: &str, overrides: B) -> Result<Entity, PrefabError> {
+        let entity = self.instantiate(name)?;
+        overrides.insert_into(self, entity);
+        Ok(entity)
+    }
+    
+    // Spawns the prefab with field-level overrides given as JSON, e.g.
+    // `{"Transform": {"x": 100, "y": 50}}`
+    pub fn instantiate_json(&mut self, name: &str, overrides: &str) -> Result<Entity, PrefabError> {
+        let prefab = self.prefab(name)?;
+        let prefab = self.with_type_registry(|registry| registry.apply_overrides(&prefab, overrides))?;
+        Ok(prefab.spawn(self))
+    }
+    
+    fn prefab(&self, name: &str) -> Result<Prefab, PrefabError> {
+        self.resource::<Prefabs>()
+            .and_then(|prefabs| prefabs.get(name).cloned())
+            .ok_or_else(|| PrefabError::UnknownPrefab(name.to_string()))
+    }
+}
@@ -0,0 +1,690 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+use super::archetype::Archetype;
+use super::sparse::SparseSet;
+use super::change::{ComponentTicks, Mut, Ticks};
+use super::parallel::Access;
+use super::{Component, Entity, World};
+
+// Where a fetch finds component `T` for the archetype being iterated: its
+// table column, or the world's sparse set looked up by entity. `set` is null
+// for dense components (and for sparse ones never inserted anywhere).
+pub struct StorageFetch<T> {
+    column: *mut T,
+    ticks: *mut ComponentTicks,
+    set: *const SparseSet<T>,
+}
+
+impl<T: Component> StorageFetch<T> {
+    // Safety: the caller must hold a borrow of `T` matching how the returned
+    // pointers are used, and `archetype` must store `T` if it's dense
+    unsafe fn new(world: &World, archetype: &Archetype) -> Self {
+        if T::STORAGE.is_sparse() {
+            return match world.sparse_sets.set_ptr::<T>() {
+                Some(set) => Self {
+                    column: (*set).dense_ptr(),
+                    ticks: (*set).ticks_ptr(),
+                    set,
+                },
+                None => Self {
+                    column: std::ptr::null_mut(),
+                    ticks: std::ptr::null_mut(),
+                    set: std::ptr::null(),
+                },
+            };
+        }
+        
+        Self {
+            column: archetype.column_ptr::<T>().expect("query fetched from a non-matching archetype"),
+            ticks: archetype.ticks_ptr::<T>().expect("query fetched from a non-matching archetype"),
+            set: std::ptr::null(),
+        }
+    }
+    
+    // Safety: `row` must be in bounds and `entity` the entity stored there
+    #[inline]
+    unsafe fn get(&self, row: usize, entity: Entity) -> Option<(*mut T, *mut ComponentTicks)> {
+        // `STORAGE` is a constant, so dense fetches compile down to plain
+        // column indexing
+        let index = if T::STORAGE.is_sparse() {
+            if self.set.is_null() {
+                return None;
+            }
+            (*self.set).dense_index(entity)?
+        } else {
+            row
+        };
+        Some((self.column.add(index), self.ticks.add(index)))
+    }
+}
+
+// Archetype-level match for `T`: sparse components are checked per entity
+// instead, so any archetype might hold them
+fn storage_matches<T: Component>(archetype: &Archetype) -> bool {
+    T::STORAGE.is_sparse() || archetype.has(TypeId::of::<T>())
+}
+
+// What a query fetches from each matching archetype. Implemented for `&T`,
+// `&mut T`, `Option<Q>` and tuples of those, e.g.
+// `world.query::<(&mut Transform, &Velocity)>()`.
+pub trait QueryData {
+    type Item<'w>;
+    type Fetch<'w>;
+    
+    fn matches(archetype: &Archetype) -> bool;
+    
+    // Records which component types the query reads and writes, for
+    // scheduling systems in parallel
+    fn add_access(access: &mut Access);
+    
+    // Takes the runtime borrows this query needs on `archetype`, panicking if
+    // another live query or component reference conflicts with them
+    fn borrow(archetype: &Archetype);
+    fn release(archetype: &Archetype);
+    
+    // Same, once per query, for sparse-set components that live outside the
+    // archetypes
+    fn borrow_sparse(world: &World);
+    fn release_sparse(world: &World);
+    
+    /// # Safety
+    /// The borrows taken by `borrow` and `borrow_sparse` must be held while
+    /// the fetch is alive.
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w>;
+    
+    /// # Safety
+    /// `row` must be in bounds, `entity` must be the entity stored there, and
+    /// each row is handed out at most once per fetch. Returns `None` if the
+    /// entity lacks a sparse component the query requires.
+    unsafe fn get<'w>(fetch: &Self::Fetch<'w>, row: usize, entity: Entity) -> Option<Self::Item<'w>>;
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'w> = &'w T;
+    type Fetch<'w> = (StorageFetch<T>, PhantomData<&'w T>);
+    
+    fn matches(archetype: &Archetype) -> bool {
+        storage_matches::<T>(archetype)
+    }
+    
+    fn add_access(access: &mut Access) {
+        access.add_read(TypeId::of::<T>());
+    }
+    
+    fn borrow(archetype: &Archetype) {
+        if !T::STORAGE.is_sparse() {
+            archetype.borrow::<T>();
+        }
+    }
+    
+    fn release(archetype: &Archetype) {
+        if !T::STORAGE.is_sparse() {
+            archetype.release::<T>();
+        }
+    }
+    
+    fn borrow_sparse(world: &World) {
+        if T::STORAGE.is_sparse() {
+            world.sparse_sets.borrow::<T>();
+        }
+    }
+    
+    fn release_sparse(world: &World) {
+        if T::STORAGE.is_sparse() {
+            world.sparse_sets.release::<T>();
+        }
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, _ticks: Ticks) -> Self::Fetch<'w> {
+        (StorageFetch::new(world, archetype), PhantomData)
+    }
+    
+    unsafe fn get<'w>(fetch: &Self::Fetch<'w>, row: usize, entity: Entity) -> Option<Self::Item<'w>> {
+        fetch.0.get(row, entity).map(|(value, _)| &*value)
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'w> = Mut<'w, T>;
+    type Fetch<'w> = (StorageFetch<T>, Ticks, PhantomData<&'w mut T>);
+    
+    fn matches(archetype: &Archetype) -> bool {
+        storage_matches::<T>(archetype)
+    }
+    
+    fn add_access(access: &mut Access) {
+        access.add_write(TypeId::of::<T>());
+    }
+    
+    fn borrow(archetype: &Archetype) {
+        if !T::STORAGE.is_sparse() {
+            archetype.borrow_mut::<T>();
+        }
+    }
+    
+    fn release(archetype: &Archetype) {
+        if !T::STORAGE.is_sparse() {
+            archetype.release_mut::<T>();
+        }
+    }
+    
+    fn borrow_sparse(world: &World) {
+        if T::STORAGE.is_sparse() {
+            world.sparse_sets.borrow_mut::<T>();
+        }
+    }
+    
+    fn release_sparse(world: &World) {
+        if T::STORAGE.is_sparse() {
+            world.sparse_sets.release_mut::<T>();
+        }
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        (StorageFetch::new(world, archetype), ticks, PhantomData)
+    }
+    
+    unsafe fn get<'w>(fetch: &Self::Fetch<'w>, row: usize, entity: Entity) -> Option<Self::Item<'w>> {
+        let (value, component_ticks) = fetch.0.get(row, entity)?;
+        Some(Mut::new(&mut *value, &mut *component_ticks, fetch.1))
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+    type Fetch<'w> = Option<Q::Fetch<'w>>;
+    
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    
+    fn add_access(access: &mut Access) {
+        Q::add_access(access);
+    }
+    
+    fn borrow(archetype: &Archetype) {
+        if Q::matches(archetype) {
+            Q::borrow(archetype);
+        }
+    }
+    
+    fn release(archetype: &Archetype) {
+        if Q::matches(archetype) {
+            Q::release(archetype);
+        }
+    }
+    
+    fn borrow_sparse(world: &World) {
+        Q::borrow_sparse(world);
+    }
+    
+    fn release_sparse(world: &World) {
+        Q::release_sparse(world);
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        if Q::matches(archetype) {
+            Some(Q::fetch(world, archetype, ticks))
+        } else {
+            None
+        }
+    }
+    
+    unsafe fn get<'w>(fetch: &Self::Fetch<'w>, row: usize, entity: Entity) -> Option<Self::Item<'w>> {
+        Some(fetch.as_ref().and_then(|fetch| Q::get(fetch, row, entity)))
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Item<'w> = ($($name::Item<'w>,)*);
+            type Fetch<'w> = ($($name::Fetch<'w>,)*);
+            
+            fn matches(archetype: &Archetype) -> bool {
+                $($name::matches(archetype))&&*
+            }
+            
+            fn add_access(access: &mut Access) {
+                $($name::add_access(access);)*
+            }
+            
+            fn borrow(archetype: &Archetype) {
+                $($name::borrow(archetype);)*
+            }
+            
+            fn release(archetype: &Archetype) {
+                $($name::release(archetype);)*
+            }
+            
+            fn borrow_sparse(world: &World) {
+                $($name::borrow_sparse(world);)*
+            }
+            
+            fn release_sparse(world: &World) {
+                $($name::release_sparse(world);)*
+            }
+            
+            unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+                ($($name::fetch(world, archetype, ticks),)*)
+            }
+            
+            unsafe fn get<'w>(fetch: &Self::Fetch<'w>, row: usize, entity: Entity) -> Option<Self::Item<'w>> {
+                let ($($name,)*) = fetch;
+                Some(($($name::get($name, row, entity)?,)*))
+            }
+        }
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+impl_query_data_tuple!(A, B, C, D, E, F, G);
+impl_query_data_tuple!(A, B, C, D, E, F, G, H);
+
+// Narrows a query without fetching anything. `matches` rules out whole
+// archetypes; `filter` can additionally reject single rows, e.g. components
+// that haven't changed since the system last ran.
+pub trait QueryFilter {
+    type Fetch<'w>;
+    
+    fn matches(archetype: &Archetype) -> bool;
+    
+    // Like `QueryData::add_access`; filters that read tick values count as
+    // reading the component
+    fn add_access(_access: &mut Access) {}
+    
+    /// # Safety
+    /// `archetype` must match the filter.
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w>;
+    
+    /// # Safety
+    /// `row` must be in bounds for the fetched archetype and `entity` must be
+    /// the entity stored there.
+    unsafe fn filter(fetch: &Self::Fetch<'_>, row: usize, entity: Entity) -> bool;
+}
+
+pub struct With<T>(PhantomData<T>);
+pub struct Without<T>(PhantomData<T>);
+
+// Rows whose `T` was added since the system last ran
+pub struct Added<T>(PhantomData<T>);
+
+// Rows whose `T` was added or mutably accessed since the system last ran
+pub struct Changed<T>(PhantomData<T>);
+
+impl QueryFilter for () {
+    type Fetch<'w> = ();
+    
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+    
+    unsafe fn fetch<'w>(_world: &'w World, _archetype: &'w Archetype, _ticks: Ticks) -> Self::Fetch<'w> {}
+    
+    unsafe fn filter(_fetch: &Self::Fetch<'_>, _row: usize, _entity: Entity) -> bool {
+        true
+    }
+}
+
+// Filters only read presence and tick values, so they take no borrow and can
+// sit next to a `&mut T` in the same query
+// (`query_filtered::<&mut Transform, Changed<Transform>>()`)
+pub struct FilterFetch<'w, T> {
+    storage: StorageFetch<T>,
+    last_run: u64,
+    _marker: PhantomData<&'w T>,
+}
+
+impl<T: Component> FilterFetch<'_, T> {
+    unsafe fn new(world: &World, archetype: &Archetype, ticks: Ticks) -> Self {
+        Self {
+            storage: StorageFetch::new(world, archetype),
+            last_run: ticks.last_run(),
+            _marker: PhantomData,
+        }
+    }
+    
+    unsafe fn ticks(&self, row: usize, entity: Entity) -> Option<ComponentTicks> {
+        self.storage.get(row, entity).map(|(_, ticks)| ticks.read())
+    }
+    
+    // Whether a sparse `T` is present, without touching its value or ticks
+    unsafe fn contains(&self, entity: Entity) -> bool {
+        !self.storage.set.is_null() && (*self.storage.set).contains(entity)
+    }
+}
+
+// `With`/`Without` on a sparse `T` look entities up in its set, which a
+// system writing `T` reaches mutably, so they count as reading `T`
+fn add_presence_access<T: Component>(access: &mut Access) {
+    if T::STORAGE.is_sparse() {
+        access.add_read(TypeId::of::<T>());
+    }
+}
+
+impl<T: Component> QueryFilter for With<T> {
+    type Fetch<'w> = Option<FilterFetch<'w, T>>;
+    
+    fn matches(archetype: &Archetype) -> bool {
+        storage_matches::<T>(archetype)
+    }
+    
+    fn add_access(access: &mut Access) {
+        add_presence_access::<T>(access);
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        T::STORAGE.is_sparse().then(|| FilterFetch::new(world, archetype, ticks))
+    }
+    
+    unsafe fn filter(fetch: &Self::Fetch<'_>, _row: usize, entity: Entity) -> bool {
+        match fetch {
+            Some(fetch) => fetch.contains(entity),
+            None => true,
+        }
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type Fetch<'w> = Option<FilterFetch<'w, T>>;
+    
+    fn matches(archetype: &Archetype) -> bool {
+        T::STORAGE.is_sparse() || !archetype.has(TypeId::of::<T>())
+    }
+    
+    fn add_access(access: &mut Access) {
+        add_presence_access::<T>(access);
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        T::STORAGE.is_sparse().then(|| FilterFetch::new(world, archetype, ticks))
+    }
+    
+    unsafe fn filter(fetch: &Self::Fetch<'_>, _row: usize, entity: Entity) -> bool {
+        match fetch {
+            Some(fetch) => !fetch.contains(entity),
+            None => true,
+        }
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type Fetch<'w> = FilterFetch<'w, T>;
+    
+    fn matches(archetype: &Archetype) -> bool {
+        storage_matches::<T>(archetype)
+    }
+    
+    fn add_access(access: &mut Access) {
+        access.add_read(TypeId::of::<T>());
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        FilterFetch::new(world, archetype, ticks)
+    }
+    
+    unsafe fn filter(fetch: &Self::Fetch<'_>, row: usize, entity: Entity) -> bool {
+        fetch.ticks(row, entity).is_some_and(|ticks| ticks.is_added(fetch.last_run))
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type Fetch<'w> = FilterFetch<'w, T>;
+    
+    fn matches(archetype: &Archetype) -> bool {
+        storage_matches::<T>(archetype)
+    }
+    
+    fn add_access(access: &mut Access) {
+        access.add_read(TypeId::of::<T>());
+    }
+    
+    unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+        FilterFetch::new(world, archetype, ticks)
+    }
+    
+    unsafe fn filter(fetch: &Self::Fetch<'_>, row: usize, entity: Entity) -> bool {
+        fetch.ticks(row, entity).is_some_and(|ticks| ticks.is_changed(fetch.last_run))
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type Fetch<'w> = ($($name::Fetch<'w>,)*);
+            
+            fn matches(archetype: &Archetype) -> bool {
+                $($name::matches(archetype))&&*
+            }
+            
+            fn add_access(access: &mut Access) {
+                $($name::add_access(access);)*
+            }
+            
+            unsafe fn fetch<'w>(world: &'w World, archetype: &'w Archetype, ticks: Ticks) -> Self::Fetch<'w> {
+                ($($name::fetch(world, archetype, ticks),)*)
+            }
+            
+            unsafe fn filter(fetch: &Self::Fetch<'_>, row: usize, entity: Entity) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::filter($name, row, entity))&&*
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(A);
+impl_query_filter_tuple!(A, B);
+impl_query_filter_tuple!(A, B, C);
+impl_query_filter_tuple!(A, B, C, D);
+
+// A live query over a shared `World`. The component columns it touches stay
+// borrowed until it is dropped, so conflicting queries panic instead of
+// aliasing.
+pub struct QueryBorrow<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: &'w World,
+    ticks: Ticks,
+    _marker: PhantomData<(Q, F)>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> QueryBorrow<'w, Q, F> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self::with_ticks(world, world.ticks())
+    }
+    
+    // A query for a system whose change detection runs on `ticks` rather
+    // than the world's current ones
+    pub(crate) fn with_ticks(world: &'w World, ticks: Ticks) -> Self {
+        Q::borrow_sparse(world);
+        for archetype in world.archetypes.iter() {
+            if Self::matches(archetype) {
+                Q::borrow(archetype);
+            }
+        }
+        
+        Self {
+            world,
+            ticks,
+            _marker: PhantomData,
+        }
+    }
+    
+    fn matches(archetype: &Archetype) -> bool {
+        Q::matches(archetype) && F::matches(archetype)
+    }
+    
+    pub fn iter(&mut self) -> QueryIter<'_, Q, F> {
+        QueryIter {
+            world: self.world,
+            archetypes: self.world.archetypes.iter(),
+            ticks: self.ticks,
+            current: None,
+            row: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Q: QueryData, F: QueryFilter> Drop for QueryBorrow<'_, Q, F> {
+    fn drop(&mut self) {
+        for archetype in self.world.archetypes.iter() {
+            if Self::matches(archetype) {
+                Q::release(archetype);
+            }
+        }
+        Q::release_sparse(self.world);
+    }
+}
+
+impl<'q, Q: QueryData, F: QueryFilter> IntoIterator for &'q mut QueryBorrow<'_, Q, F> {
+    type Item = (Entity, Q::Item<'q>);
+    type IntoIter = QueryIter<'q, Q, F>;
+    
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct QueryIter<'q, Q: QueryData, F: QueryFilter> {
+    world: &'q World,
+    archetypes: std::slice::Iter<'q, Archetype>,
+    ticks: Ticks,
+    current: Option<(&'q [Entity], Q::Fetch<'q>, F::Fetch<'q>)>,
+    row: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<'q, Q: QueryData, F: QueryFilter> Iterator for QueryIter<'q, Q, F> {
+    type Item = (Entity, Q::Item<'q>);
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((entities, fetch, filter)) = &self.current {
+                while let Some(&entity) = entities.get(self.row) {
+                    let row = self.row;
+                    self.row += 1;
+                    
+                    // Safety: the owning `QueryBorrow` holds the borrows for
+                    // this archetype and every row is visited exactly once
+                    if !unsafe { F::filter(filter, row, entity) } {
+                        continue;
+                    }
+                    if let Some(item) = unsafe { Q::get(fetch, row, entity) } {
+                        return Some((entity, item));
+                    }
+                }
+            }
+            
+            let archetype = self.archetypes.next()?;
+            if archetype.entities().is_empty() || !QueryBorrow::<Q, F>::matches(archetype) {
+                continue;
+            }
+            
+            // Safety: as above, the borrows were taken in `QueryBorrow::new`
+            let fetch = unsafe { Q::fetch(self.world, archetype, self.ticks) };
+            let filter = unsafe { F::fetch(self.world, archetype, self.ticks) };
+            self.current = Some((archetype.entities(), fetch, filter));
+            self.row = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::{Sprite, Transform, Velocity};
+    
+    fn world_with_movers() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        let mover = world.create_entity();
+        world.add_component(mover, Transform::new(1.0, 2.0));
+        world.add_component(mover, Velocity::new(3.0, 4.0));
+        let still = world.create_entity();
+        world.add_component(still, Transform::new(5.0, 6.0));
+        (world, mover, still)
+    }
+    
+    #[test]
+    fn iterates_only_matching_entities() {
+        let (world, mover, still) = world_with_movers();
+        
+        let mut query = world.query::<(&mut Transform, &Velocity)>();
+        for (_, (mut transform, velocity)) in query.iter() {
+            transform.x += velocity.x;
+            transform.y += velocity.y;
+        }
+        drop(query);
+        
+        let moved: Vec<Entity> = world.query::<&Transform>().iter().map(|(entity, _)| entity).collect();
+        assert_eq!(moved.len(), 2);
+        assert!(moved.contains(&mover) && moved.contains(&still));
+        assert_eq!(world.get_component::<Transform>(mover).unwrap().x, 4.0);
+        assert_eq!(world.get_component::<Transform>(still).unwrap().x, 5.0);
+        assert_eq!(world.query::<&Sprite>().iter().count(), 0);
+    }
+    
+    #[test]
+    fn optional_components_match_everything() {
+        let (world, mover, _) = world_with_movers();
+        let mut query = world.query::<(&Transform, Option<&Velocity>)>();
+        let velocities: Vec<(Entity, bool)> = query.iter().map(|(entity, (_, velocity))| (entity, velocity.is_some())).collect();
+        assert_eq!(velocities.len(), 2);
+        assert!(velocities.contains(&(mover, true)));
+    }
+    
+    #[test]
+    fn filters_by_presence() {
+        let (world, mover, still) = world_with_movers();
+        let with: Vec<Entity> = world.query_filtered::<&Transform, With<Velocity>>().iter().map(|(entity, _)| entity).collect();
+        let without: Vec<Entity> = world.query_filtered::<&Transform, Without<Velocity>>().iter().map(|(entity, _)| entity).collect();
+        assert_eq!((with, without), (vec![mover], vec![still]));
+    }
+    
+    #[test]
+    fn shared_borrows_coexist() {
+        let (world, mover, _) = world_with_movers();
+        let mut first = world.query::<&Transform>();
+        let mut second = world.query::<(&Transform, &Velocity)>();
+        assert_eq!(first.iter().count() + second.iter().count(), 3);
+        assert!(world.get_component::<Transform>(mover).is_some());
+    }
+    
+    #[test]
+    #[should_panic(expected = "is already borrowed")]
+    fn writing_while_reading_panics() {
+        let (world, _, _) = world_with_movers();
+        let _reading = world.query::<&Transform>();
+        world.query::<&mut Transform>();
+    }
+    
+    #[test]
+    #[should_panic(expected = "is already borrowed mutably")]
+    fn reading_while_writing_panics() {
+        let (world, mover, _) = world_with_movers();
+        let _writing = world.query::<&mut Transform>();
+        world.get_component::<Transform>(mover);
+    }
+    
+    #[test]
+    #[should_panic(expected = "is already borrowed")]
+    fn aliasing_within_a_query_panics() {
+        let (world, _, _) = world_with_movers();
+        world.query::<(&Transform, &mut Transform)>();
+    }
+    
+    #[test]
+    fn dropping_a_query_releases_its_borrows() {
+        let (world, _, _) = world_with_movers();
+        drop(world.query::<&mut Transform>());
+        drop(world.query::<&mut Transform>());
+        let _reading = world.query::<&Transform>();
+    }
+}
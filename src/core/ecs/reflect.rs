@@ -0,0 +1,762 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{Children, Component, Entity, MapEntities, Name, Parent, Sprite, Transform, Velocity, World};
+use crate::graphics::tilemap::Tilemap;
+use crate::physics::collision::Collider;
+use crate::physics::physics_world::RigidBody;
+
+#[derive(Debug)]
+pub enum ReflectError {
+    // Paths are a component name followed by dotted field names
+    InvalidPath(String),
+    UnknownComponent(String),
+    MissingComponent { entity: Entity, component: String },
+    UnknownField(String),
+    TypeMismatch { path: String, expected: &'static str, found: &'static str },
+}
+
+impl ReflectError {
+    // Prefixes the path the error refers to with the field it came through
+    fn within(self, field: &str) -> Self {
+        if field.is_empty() {
+            return self;
+        }
+        let join = |path: String| if path.is_empty() { field.to_string() } else { format!("{}.{}", field, path) };
+        match self {
+            ReflectError::UnknownField(path) => ReflectError::UnknownField(join(path)),
+            ReflectError::TypeMismatch { path, expected, found } => ReflectError::TypeMismatch {
+                path: join(path),
+                expected,
+                found,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectError::InvalidPath(path) => write!(f, "invalid field path `{}`", path),
+            ReflectError::UnknownComponent(name) => write!(f, "unknown component type `{}`", name),
+            ReflectError::MissingComponent { entity, component } => {
+                write!(f, "entity {:?} has no `{}`", entity, component)
+            }
+            ReflectError::UnknownField(path) => write!(f, "unknown field `{}`", path),
+            ReflectError::TypeMismatch { path, expected, found } => {
+                write!(f, "`{}` expects {}, got {}", path, expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+// The shape of a reflected type, for listing fields at runtime
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldType {
+    F32,
+    U32,
+    Bool,
+    Option(Box<FieldType>),
+    Struct(StructInfo),
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::F32 => f.write_str("f32"),
+            FieldType::U32 => f.write_str("u32"),
+            FieldType::Bool => f.write_str("bool"),
+            FieldType::Option(inner) => write!(f, "Option<{}>", inner),
+            FieldType::Struct(info) => f.write_str(info.name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructInfo {
+    pub name: &'static str,
+    pub fields: Vec<FieldInfo>,
+}
+
+impl StructInfo {
+    pub fn new(name: &'static str, fields: Vec<FieldInfo>) -> Self {
+        Self { name, fields }
+    }
+    
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub field_type: FieldType,
+}
+
+impl FieldInfo {
+    pub fn new(name: &'static str, field_type: FieldType) -> Self {
+        Self { name, field_type }
+    }
+}
+
+// A reflected value, detached from the type it was read from. Structs list
+// their fields in declaration order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    F32(f32),
+    U32(u32),
+    Bool(bool),
+    None,
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::F32(_) => "f32",
+            Value::U32(_) => "u32",
+            Value::Bool(_) => "bool",
+            Value::None => "none",
+            Value::Struct(_) => "struct",
+        }
+    }
+    
+    // Integers read as floats too, since paths are often set from JSON or JS
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::F32(value) => Some(value),
+            Value::U32(value) => Some(value as f32),
+            _ => None,
+        }
+    }
+    
+    // Floats only count if they're whole and in range
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Value::U32(value) => Some(value),
+            Value::F32(value) if value.fract() == 0.0 && (0.0..4294967296.0).contains(&value) => {
+                Some(value as u32)
+            }
+            _ => None,
+        }
+    }
+    
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+    
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl Value {
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::F32(value) => serde_json::json!(value),
+            Value::U32(value) => serde_json::json!(value),
+            Value::Bool(value) => serde_json::Value::Bool(*value),
+            Value::None => serde_json::Value::Null,
+            Value::Struct(fields) => {
+                serde_json::Value::Object(fields.iter().map(|(name, value)| (name.clone(), value.to_json())).collect())
+            }
+        }
+    }
+    
+    // Whole non-negative numbers become `U32`, other numbers `F32`. Strings
+    // and arrays have no reflected counterpart.
+    pub fn from_json(json: serde_json::Value) -> Option<Value> {
+        match json {
+            serde_json::Value::Null => Some(Value::None),
+            serde_json::Value::Bool(value) => Some(Value::Bool(value)),
+            serde_json::Value::Number(number) => match number.as_u64().and_then(|value| u32::try_from(value).ok()) {
+                Some(value) => Some(Value::U32(value)),
+                None => number.as_f64().map(|value| Value::F32(value as f32)),
+            },
+            serde_json::Value::Object(fields) => fields
+                .into_iter()
+                .map(|(name, value)| Some((name, Value::from_json(value)?)))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Struct),
+            serde_json::Value::String(_) | serde_json::Value::Array(_) => None,
+        }
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::F32(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::U32(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::None, Into::into)
+    }
+}
+
+// Runtime access to a type's fields by name. Usually implemented with
+// `#[derive(Reflect)]`; fields may be `f32`, `u32`, `bool`, `Option`s of
+// those, or other reflected structs.
+pub trait Reflect: Any + Send + Sync {
+    fn field_type() -> FieldType
+    where
+        Self: Sized;
+    
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+    
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+    
+    fn field_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+    
+    fn value(&self) -> Value {
+        let fields = self.field_names().iter().filter_map(|&name| {
+            self.field(name).map(|field| (name.to_string(), field.value()))
+        });
+        Value::Struct(fields.collect())
+    }
+    
+    // Structs take a `Value::Struct` naming some or all of their fields;
+    // fields left out keep their current value
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        let Value::Struct(fields) = value else {
+            return Err(mismatch("struct", &value));
+        };
+        for (name, value) in fields {
+            let field = self.field_mut(&name).ok_or_else(|| ReflectError::UnknownField(name.clone()))?;
+            field.set_value(value).map_err(|error| error.within(&name))?;
+        }
+        Ok(())
+    }
+}
+
+impl dyn Reflect {
+    // Follows a dotted field path, e.g. `"position.x"`
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect, ReflectError> {
+        let mut current = self;
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            current = current.field(name).ok_or_else(|| ReflectError::UnknownField(path.to_string()))?;
+        }
+        Ok(current)
+    }
+    
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect, ReflectError> {
+        let mut current = self;
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            current = current.field_mut(name).ok_or_else(|| ReflectError::UnknownField(path.to_string()))?;
+        }
+        Ok(current)
+    }
+}
+
+fn mismatch(expected: &'static str, found: &Value) -> ReflectError {
+    ReflectError::TypeMismatch {
+        path: String::new(),
+        expected,
+        found: found.kind(),
+    }
+}
+
+impl Reflect for f32 {
+    fn field_type() -> FieldType {
+        FieldType::F32
+    }
+    
+    fn value(&self) -> Value {
+        Value::F32(*self)
+    }
+    
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        *self = value.as_f32().ok_or_else(|| mismatch("f32", &value))?;
+        Ok(())
+    }
+}
+
+impl Reflect for u32 {
+    fn field_type() -> FieldType {
+        FieldType::U32
+    }
+    
+    fn value(&self) -> Value {
+        Value::U32(*self)
+    }
+    
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        *self = value.as_u32().ok_or_else(|| mismatch("u32", &value))?;
+        Ok(())
+    }
+}
+
+impl Reflect for bool {
+    fn field_type() -> FieldType {
+        FieldType::Bool
+    }
+    
+    fn value(&self) -> Value {
+        Value::Bool(*self)
+    }
+    
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        *self = value.as_bool().ok_or_else(|| mismatch("bool", &value))?;
+        Ok(())
+    }
+}
+
+// `None` reads and writes as `Value::None`. Setting a value on a `None`
+// starts from `T::default()`, so a partial struct fills in the rest.
+impl<T: Reflect + Default> Reflect for Option<T> {
+    fn field_type() -> FieldType {
+        FieldType::Option(Box::new(T::field_type()))
+    }
+    
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        self.as_ref()?.field(name)
+    }
+    
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        self.as_mut()?.field_mut(name)
+    }
+    
+    fn field_names(&self) -> &'static [&'static str] {
+        self.as_ref().map_or(&[], |inner| inner.field_names())
+    }
+    
+    fn value(&self) -> Value {
+        self.as_ref().map_or(Value::None, |inner| inner.value())
+    }
+    
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        if value == Value::None {
+            *self = None;
+            return Ok(());
+        }
+        match self {
+            Some(inner) => inner.set_value(value),
+            None => {
+                let mut inner = T::default();
+                inner.set_value(value)?;
+                *self = Some(inner);
+                Ok(())
+            }
+        }
+    }
+}
+
+// A component value detached from any entity, as loaded from scene or prefab
+// data, ready to be added to one
+pub(crate) trait ComponentData {
+    fn type_id(&self) -> TypeId;
+    fn to_json(&self) -> serde_json::Value;
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity);
+    fn insert(&self, world: &mut World, entity: Entity);
+    fn clone_box(&self) -> Box<dyn ComponentData>;
+}
+
+#[derive(Clone)]
+pub(crate) struct Plain<T>(pub T);
+
+impl<T: Component + Serialize> ComponentData for Plain<T> {
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+    
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.0).expect("component failed to serialize")
+    }
+    
+    fn map_entities(&mut self, _map: &mut dyn FnMut(Entity) -> Entity) {}
+    
+    fn insert(&self, world: &mut World, entity: Entity) {
+        world.add_component(entity, self.0.clone());
+    }
+    
+    fn clone_box(&self) -> Box<dyn ComponentData> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+struct Mapped<T>(T);
+
+impl<T: Component + MapEntities + Serialize> ComponentData for Mapped<T> {
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+    
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.0).expect("component failed to serialize")
+    }
+    
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+        self.0.map_entities(map);
+    }
+    
+    fn insert(&self, world: &mut World, entity: Entity) {
+        world.add_component(entity, self.0.clone());
+    }
+    
+    fn clone_box(&self) -> Box<dyn ComponentData> {
+        Box::new(self.clone())
+    }
+}
+
+// Both return `Ok(None)` when the entity lacks the component
+type ReadPath = fn(&World, Entity, &str) -> Result<Option<Value>, ReflectError>;
+type WritePath = fn(&mut World, Entity, &str, Value) -> Result<Option<()>, ReflectError>;
+// Serializes the entity's component, if it has one, passing any entity
+// handles in it through the map
+type SaveComponent = fn(&World, Entity, &mut dyn FnMut(Entity) -> Entity) -> Option<serde_json::Value>;
+type LoadComponent = fn(serde_json::Value) -> Result<Box<dyn ComponentData>, serde_json::Error>;
+
+#[derive(Clone)]
+struct Fields {
+    info: StructInfo,
+    read: ReadPath,
+    write: WritePath,
+}
+
+#[derive(Clone)]
+pub(crate) struct Registration {
+    pub(crate) type_id: TypeId,
+    pub(crate) save: SaveComponent,
+    pub(crate) load: LoadComponent,
+    has: fn(&World, Entity) -> bool,
+    // Only for types registered with `register`
+    fields: Option<Fields>,
+}
+
+// The component types the engine knows by name: for reading and writing
+// fields generically, e.g. `world.reflect_set(entity, "Transform.x", 10.0)`,
+// and for the component data in scenes and prefabs. Lives in the world as a
+// resource. `Transform`, `Sprite`, `Velocity`, `RigidBody` and `Collider` are
+// known out of the box with their fields, and `Tilemap`, `Name`, `Parent` and
+// `Children` as data only.
+#[derive(Clone)]
+pub struct TypeRegistry {
+    components: HashMap<String, Registration>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            components: HashMap::new(),
+        };
+        registry.register::<Transform>();
+        registry.register::<Sprite>();
+        registry.register::<Velocity>();
+        registry.register::<RigidBody>();
+        registry.register::<Collider>();
+        registry.register_component::<Tilemap>("Tilemap");
+        registry.register_component::<Name>("Name");
+        registry.register_mapped_component::<Parent>("Parent");
+        registry.register_mapped_component::<Children>("Children");
+        registry
+    }
+    
+    // Makes `T` reachable by its type name in field paths, scenes and prefab
+    // data
+    pub fn register<T>(&mut self)
+    where
+        T: Component + Reflect + Serialize + DeserializeOwned,
+    {
+        fn read<T: Component + Reflect>(world: &World, entity: Entity, path: &str) -> Result<Option<Value>, ReflectError> {
+            let Some(component) = world.get_component::<T>(entity) else {
+                return Ok(None);
+            };
+            let component: &dyn Reflect = &*component;
+            Ok(Some(component.path(path)?.value()))
+        }
+        
+        fn write<T: Component + Reflect>(
+            world: &mut World,
+            entity: Entity,
+            path: &str,
+            value: Value,
+        ) -> Result<Option<()>, ReflectError> {
+            let Some(component) = world.get_component_mut::<T>(entity) else {
+                return Ok(None);
+            };
+            let component: &mut dyn Reflect = component;
+            component.path_mut(path)?.set_value(value).map_err(|error| error.within(path))?;
+            Ok(Some(()))
+        }
+        
+        let FieldType::Struct(info) = T::field_type() else {
+            panic!("only structs can be registered as reflected components");
+        };
+        let name = info.name;
+        self.insert::<T>(name, save::<T>, load::<T>, Some(Fields {
+            info,
+            read: read::<T>,
+            write: write::<T>,
+        }));
+    }
+    
+    // Makes `T` usable in scenes and prefab data under `name`, without access
+    // to its fields
+    pub fn register_component<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.insert::<T>(name, save::<T>, load::<T>, None);
+    }
+    
+    // Like `register_component`, for components holding entity handles
+    pub fn register_mapped_component<T>(&mut self, name: &str)
+    where
+        T: Component + MapEntities + Serialize + DeserializeOwned,
+    {
+        fn save<T: Component + MapEntities + Serialize>(
+            world: &World,
+            entity: Entity,
+            map: &mut dyn FnMut(Entity) -> Entity,
+        ) -> Option<serde_json::Value> {
+            let mut component = world.get_component::<T>(entity)?.clone();
+            component.map_entities(map);
+            Some(serde_json::to_value(&component).expect("component failed to serialize"))
+        }
+        
+        fn load<T: Component + MapEntities + Serialize + DeserializeOwned>(
+            value: serde_json::Value,
+        ) -> Result<Box<dyn ComponentData>, serde_json::Error> {
+            Ok(Box::new(Mapped(serde_json::from_value::<T>(value)?)))
+        }
+        
+        self.insert::<T>(name, save::<T>, load::<T>, None);
+    }
+    
+    fn insert<T: Component>(&mut self, name: &str, save: SaveComponent, load: LoadComponent, fields: Option<Fields>) {
+        fn has<T: Component>(world: &World, entity: Entity) -> bool {
+            world.get_component::<T>(entity).is_some()
+        }
+        
+        self.components.insert(name.to_string(), Registration {
+            type_id: TypeId::of::<T>(),
+            save,
+            load,
+            has: has::<T>,
+            fields,
+        });
+    }
+    
+    // The fields of the reflected component `name`
+    pub fn get(&self, name: &str) -> Option<&StructInfo> {
+        self.get_fields(name).map(|fields| &fields.info)
+    }
+    
+    pub fn get_by_type<T: 'static>(&self) -> Option<&StructInfo> {
+        self.components
+            .values()
+            .find(|registration| registration.type_id == TypeId::of::<T>())?
+            .fields
+            .as_ref()
+            .map(|fields| &fields.info)
+    }
+    
+    // Every reflected component type, sorted by name
+    pub fn components(&self) -> Vec<&StructInfo> {
+        let mut components: Vec<_> = self.components.values().filter_map(|registration| {
+            registration.fields.as_ref().map(|fields| &fields.info)
+        }).collect();
+        components.sort_by_key(|info| info.name);
+        components
+    }
+    
+    fn get_fields(&self, name: &str) -> Option<&Fields> {
+        self.components.get(name)?.fields.as_ref()
+    }
+    
+    // Whether scenes and prefab data may name `name`, reflected or not
+    pub fn contains(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+    
+    pub(crate) fn registration(&self, name: &str) -> Option<&Registration> {
+        self.components.get(name)
+    }
+    
+    pub(crate) fn registrations(&self) -> impl Iterator<Item = (&str, &Registration)> {
+        self.components.iter().map(|(name, registration)| (name.as_str(), registration))
+    }
+}
+
+fn save<T: Component + Serialize>(
+    world: &World,
+    entity: Entity,
+    _map: &mut dyn FnMut(Entity) -> Entity,
+) -> Option<serde_json::Value> {
+    let component = world.get_component::<T>(entity)?;
+    Some(serde_json::to_value(&*component).expect("component failed to serialize"))
+}
+
+fn load<T: Component + Serialize + DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<Box<dyn ComponentData>, serde_json::Error> {
+    Ok(Box::new(Plain(serde_json::from_value::<T>(value)?)))
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Splits `"Transform.x"` into the component name and the field path within it
+fn split_path(path: &str) -> Result<(&str, &str), ReflectError> {
+    let (component, fields) = path.split_once('.').unwrap_or((path, ""));
+    let empty_field = path.contains('.') && fields.split('.').any(str::is_empty);
+    if component.is_empty() || empty_field {
+        return Err(ReflectError::InvalidPath(path.to_string()));
+    }
+    Ok((component, fields))
+}
+
+impl World {
+    // The type registry, created on first use
+    pub fn type_registry_mut(&mut self) -> &mut TypeRegistry {
+        if !self.has_resource::<TypeRegistry>() {
+            self.insert_resource(TypeRegistry::new());
+        }
+        self.resources.get_exclusive::<TypeRegistry>().expect("type registry missing")
+    }
+    
+    pub fn register_reflect<T>(&mut self)
+    where
+        T: Component + Reflect + Serialize + DeserializeOwned,
+    {
+        self.type_registry_mut().register::<T>();
+    }
+    
+    // Every reflected component type and its fields
+    pub fn reflected_types(&self) -> Vec<StructInfo> {
+        self.with_type_registry(|registry| registry.components().into_iter().cloned().collect())
+    }
+    
+    // Names of the reflected components `entity` has
+    pub fn reflected_components(&self, entity: Entity) -> Vec<&'static str> {
+        let registrations = self.with_type_registry(|registry| {
+            registry.components.values().filter_map(|registration| {
+                registration.fields.as_ref().map(|fields| (fields.info.name, registration.has))
+            }).collect::<Vec<_>>()
+        });
+        let mut names: Vec<_> = registrations
+            .into_iter()
+            .filter(|(_, has)| has(self, entity))
+            .map(|(name, _)| name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+    
+    // Reads a component or one of its fields by path, e.g. `"Transform"` or
+    // `"Transform.x"`
+    pub fn reflect_get(&self, entity: Entity, path: &str) -> Result<Value, ReflectError> {
+        let (component, fields) = split_path(path)?;
+        let read = self.with_type_registry(|registry| {
+            registry.get_fields(component).map(|fields| fields.read)
+        });
+        let read = read.ok_or_else(|| ReflectError::UnknownComponent(component.to_string()))?;
+        read(self, entity, fields)
+            .map_err(|error| error.within(component))?
+            .ok_or_else(|| ReflectError::MissingComponent { entity, component: component.to_string() })
+    }
+    
+    // Writes a component or one of its fields by path, marking the component
+    // changed. Structs may be given only some of their fields.
+    pub fn reflect_set(&mut self, entity: Entity, path: &str, value: impl Into<Value>) -> Result<(), ReflectError> {
+        let (component, fields) = split_path(path)?;
+        let write = self.with_type_registry(|registry| {
+            registry.get_fields(component).map(|fields| fields.write)
+        });
+        let write = write.ok_or_else(|| ReflectError::UnknownComponent(component.to_string()))?;
+        write(self, entity, fields, value.into())
+            .map_err(|error| error.within(component))?
+            .ok_or_else(|| ReflectError::MissingComponent { entity, component: component.to_string() })
+    }
+    
+    // Falls back to the built-in registrations when no registry has been
+    // created yet
+    pub(crate) fn with_type_registry<R>(&self, f: impl FnOnce(&TypeRegistry) -> R) -> R {
+        match self.resource::<TypeRegistry>() {
+            Some(registry) => f(&registry),
+            None => f(&TypeRegistry::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::PrefabError;
+    use serde::Deserialize;
+    
+    #[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+    struct Health(u32);
+    
+    #[test]
+    fn whole_floats_read_as_u32_up_to_its_max() {
+        assert_eq!(Value::F32(4294967040.0).as_u32(), Some(4294967040));
+        assert_eq!(Value::F32(4294967296.0).as_u32(), None);
+        assert_eq!(Value::F32(-1.0).as_u32(), None);
+        assert_eq!(Value::F32(1.5).as_u32(), None);
+        assert_eq!(Value::F32(f32::NAN).as_u32(), None);
+    }
+    
+    #[test]
+    fn paths_read_and_write_fields() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        world.add_component(entity, Transform::new(1.0, 2.0));
+        
+        world.reflect_set(entity, "Transform.x", 10u32).unwrap();
+        assert_eq!(world.reflect_get(entity, "Transform.x").unwrap(), Value::F32(10.0));
+        assert!(matches!(world.reflect_get(entity, "Transform.z"), Err(ReflectError::UnknownField(_))));
+        assert!(matches!(world.reflect_get(entity, "Sprite"), Err(ReflectError::MissingComponent { .. })));
+        assert!(matches!(world.reflect_get(entity, "Transform..x"), Err(ReflectError::InvalidPath(_))));
+        // Data-only registrations have no fields to reach
+        assert!(matches!(world.reflect_get(entity, "Name"), Err(ReflectError::UnknownComponent(_))));
+    }
+    
+    #[test]
+    fn registered_components_are_usable_in_prefab_data() {
+        let mut world = World::new();
+        assert!(matches!(world.register_prefab_json("medic", r#"{"Health": 5}"#), Err(PrefabError::UnknownComponent(_))));
+        
+        world.type_registry_mut().register_component::<Health>("Health");
+        world.register_prefab_json("medic", r#"{"Health": 5}"#).unwrap();
+        let entity = world.instantiate("medic").unwrap();
+        assert_eq!(*world.get_component::<Health>(entity).unwrap(), Health(5));
+    }
+}
@@ -0,0 +1,113 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+use super::borrow::{BorrowFlag, Ref, RefMut};
+
+// A resource with its type erased, still cloneable for `World::clone`
+trait ResourceData {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn clone_box(&self) -> Box<dyn ResourceData>;
+}
+
+impl<T: Clone + 'static> ResourceData for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+    
+    fn clone_box(&self) -> Box<dyn ResourceData> {
+        Box::new(self.clone())
+    }
+}
+
+struct ResourceCell {
+    data: UnsafeCell<Box<dyn ResourceData>>,
+    borrow: BorrowFlag,
+}
+
+impl Clone for ResourceCell {
+    fn clone(&self) -> Self {
+        if !self.borrow.borrow() {
+            panic!("cannot clone the world while a resource is borrowed mutably");
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        let data = unsafe { (**self.data.get()).clone_box() };
+        self.borrow.release();
+        
+        Self {
+            data: UnsafeCell::new(data),
+            borrow: BorrowFlag::new(),
+        }
+    }
+}
+
+// Singleton values stored in the world by type, e.g. the `TimeManager`,
+// `InputManager` or a score counter. Borrows are checked at runtime like
+// component columns, so systems can hold them alongside live queries.
+#[derive(Clone, Default)]
+pub(crate) struct Resources {
+    cells: HashMap<TypeId, ResourceCell>,
+}
+
+impl Resources {
+    pub fn insert<T: Clone + 'static>(&mut self, value: T) {
+        self.cells.insert(TypeId::of::<T>(), ResourceCell {
+            data: UnsafeCell::new(Box::new(value)),
+            borrow: BorrowFlag::new(),
+        });
+    }
+    
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        let cell = self.cells.remove(&TypeId::of::<T>())?;
+        cell.data.into_inner().into_any().downcast::<T>().ok().map(|value| *value)
+    }
+    
+    // Plain `&mut` access: holding `&mut self` already rules out any
+    // outstanding `Ref`/`RefMut`
+    pub fn get_exclusive<T: 'static>(&mut self) -> Option<&mut T> {
+        self.cells.get_mut(&TypeId::of::<T>())?.data.get_mut().as_mut().as_any_mut().downcast_mut::<T>()
+    }
+    
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.cells.contains_key(&TypeId::of::<T>())
+    }
+    
+    pub fn get<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        let cell = self.cells.get(&TypeId::of::<T>())?;
+        if !cell.borrow.borrow() {
+            panic!("resource `{}` is already borrowed mutably", type_name::<T>());
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        let value = unsafe { &**cell.data.get() }
+            .as_any()
+            .downcast_ref::<T>()
+            .expect("resource stored under the wrong type");
+        Some(unsafe { Ref::new(&cell.borrow, value) })
+    }
+    
+    pub fn get_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        let cell = self.cells.get(&TypeId::of::<T>())?;
+        if !cell.borrow.borrow_mut() {
+            panic!("resource `{}` is already borrowed", type_name::<T>());
+        }
+        
+        // Safety: the unique borrow taken above excludes any other access
+        let value = unsafe { &mut **cell.data.get() }
+            .as_any_mut()
+            .downcast_mut::<T>()
+            .expect("resource stored under the wrong type");
+        Some(unsafe { RefMut::new(&cell.borrow, value) })
+    }
+}
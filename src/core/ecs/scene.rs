@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{binary, Entity, TypeRegistry, World};
+
+#[derive(Debug)]
+pub enum SceneError {
+    InvalidJson(String),
+    InvalidBinary(String),
+    // Written by a newer version of the engine
+    UnsupportedVersion(u32),
+    // Written by a newer version of the game, with a later schema
+    UnsupportedSchema { version: u32, supported: u32 },
+    MigrationFailed { component: String, from: u32, message: String },
+    UnknownComponent(String),
+    UnknownResource(String),
+    DuplicateEntity(u64),
+    // A component refers to an entity id the scene doesn't contain
+    UnknownEntity(u64),
+    InvalidComponent { component: String, message: String },
+    InvalidResource { resource: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::InvalidJson(message) => write!(f, "invalid scene JSON: {}", message),
+            SceneError::InvalidBinary(message) => write!(f, "invalid binary scene: {}", message),
+            SceneError::UnsupportedVersion(version) => {
+                write!(f, "scene version {} is newer than the supported {}", version, Scene::VERSION)
+            }
+            SceneError::UnsupportedSchema { version, supported } => {
+                write!(f, "save schema version {} is newer than the supported {}", version, supported)
+            }
+            SceneError::MigrationFailed { component, from, message } => {
+                write!(f, "failed to migrate `{}` from schema version {}: {}", component, from, message)
+            }
+            SceneError::UnknownComponent(name) => write!(f, "unknown component type `{}`", name),
+            SceneError::UnknownResource(name) => write!(f, "unknown resource type `{}`", name),
+            SceneError::DuplicateEntity(id) => write!(f, "entity id {} appears more than once", id),
+            SceneError::UnknownEntity(id) => write!(f, "reference to entity {} outside the scene", id),
+            SceneError::InvalidComponent { component, message } => {
+                write!(f, "invalid `{}` data: {}", component, message)
+            }
+            SceneError::InvalidResource { resource, message } => {
+                write!(f, "invalid `{}` resource data: {}", resource, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Components that hold entity handles, so scenes can rewrite them when saving
+// and loading
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity);
+}
+
+// A snapshot of a world's entities and opted-in resources, detached from any
+// world. Entities get scene-local ids, which components referring to other
+// entities (`Parent`, `Children`) use too. Save with `World::save_scene`,
+// then write it out as JSON for level files or binary for savegames.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    // The format version, which the engine owns
+    pub version: u32,
+    // The version of the game's component layouts, which the game owns; see
+    // `SceneRegistry::register_migration`. Version 1 scenes predate it and
+    // count as schema 0.
+    #[serde(default)]
+    pub schema_version: u32,
+    pub entities: Vec<SceneEntity>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resources: BTreeMap<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneEntity {
+    pub id: u64,
+    // Component fields by component name
+    pub components: BTreeMap<String, Value>,
+}
+
+impl Scene {
+    pub const VERSION: u32 = 2;
+    
+    // Starts binary scenes, followed by the version as a little-endian u32
+    const MAGIC: &'static [u8; 4] = b"WGES";
+    
+    pub fn new() -> Self {
+        Self {
+            version: Self::VERSION,
+            schema_version: 0,
+            entities: Vec::new(),
+            resources: BTreeMap::new(),
+        }
+    }
+    
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene failed to serialize")
+    }
+    
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        let scene: Scene = serde_json::from_str(json).map_err(|error| SceneError::InvalidJson(error.to_string()))?;
+        if scene.version > Self::VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+        Ok(scene)
+    }
+    
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        
+        let value = serde_json::to_value(self).expect("scene failed to serialize");
+        binary::encode(&value, &mut bytes);
+        bytes
+    }
+    
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, SceneError> {
+        let invalid = |message: &str| SceneError::InvalidBinary(message.to_string());
+        let header = bytes.get(..8).ok_or_else(|| invalid("too short for a header"))?;
+        if &header[..4] != Self::MAGIC {
+            return Err(invalid("not a scene file"));
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into().expect("took 4 bytes"));
+        if version > Self::VERSION {
+            return Err(SceneError::UnsupportedVersion(version));
+        }
+        
+        let value = binary::decode(&bytes[8..]).map_err(SceneError::InvalidBinary)?;
+        let scene: Scene = serde_json::from_value(value).map_err(|error| invalid(&error.to_string()))?;
+        if scene.version != version {
+            return Err(invalid("header and body disagree on the version"));
+        }
+        Ok(scene)
+    }
+}
+
+// Inserts a deserialized resource
+type ResourceInsert = Box<dyn FnOnce(&mut World)>;
+type SaveResource = fn(&World) -> Option<Value>;
+// Upgrades a component's saved fields by one schema version, returning the
+// name it's saved under from then on, which may be new, or `None` to drop it
+pub type Migration = fn(Value) -> Result<Option<(String, Value)>, String>;
+type LoadResource = fn(Value) -> Result<ResourceInsert, serde_json::Error>;
+
+#[derive(Clone)]
+struct ResourceEntry {
+    save: SaveResource,
+    load: LoadResource,
+}
+
+// The resource types scenes may contain, by name, and the game's schema
+// version. Lives in the world as a resource. Components are looked up in the
+// world's `TypeRegistry`, and those of unregistered types are left out when
+// saving; resources are only saved once registered here.
+//
+// Scenes are stamped with the registry's schema version. When a game changes
+// a component's layout it bumps the version and registers a migration from
+// the old one, so older saves are brought up to date as they load.
+#[derive(Clone)]
+pub struct SceneRegistry {
+    resources: BTreeMap<String, ResourceEntry>,
+    schema_version: u32,
+    // By component name, then by the schema version they upgrade from
+    migrations: HashMap<String, BTreeMap<u32, Migration>>,
+}
+
+impl SceneRegistry {
+    pub fn new() -> Self {
+        Self {
+            resources: BTreeMap::new(),
+            schema_version: 0,
+            migrations: HashMap::new(),
+        }
+    }
+    
+    // Opts the resource `R` into scenes under `name`
+    pub fn register_resource<R>(&mut self, name: &str)
+    where
+        R: Clone + Serialize + DeserializeOwned + 'static,
+    {
+        fn save<R: Serialize + 'static>(world: &World) -> Option<Value> {
+            let resource = world.resource::<R>()?;
+            Some(serde_json::to_value(&*resource).expect("resource failed to serialize"))
+        }
+        
+        fn load<R: Clone + DeserializeOwned + 'static>(
+            value: Value,
+        ) -> Result<ResourceInsert, serde_json::Error> {
+            let resource = serde_json::from_value::<R>(value)?;
+            Ok(Box::new(move |world: &mut World| world.insert_resource(resource)))
+        }
+        
+        self.resources.insert(name.to_string(), ResourceEntry {
+            save: save::<R>,
+            load: load::<R>,
+        });
+    }
+    
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+    
+    pub fn set_schema_version(&mut self, version: u32) {
+        self.schema_version = version;
+    }
+    
+    // Registers how to turn the component saved as `name` at schema version
+    // `from` into what version `from + 1` saves. Loading a scene runs every
+    // migration between its version and the current one in order, following
+    // the component through renames, so `name` is its name as of `from` and
+    // needn't be registered any more. Components without a migration for a
+    // version are taken as is.
+    pub fn register_migration(&mut self, name: &str, from: u32, migration: Migration) {
+        self.migrations.entry(name.to_string()).or_default().insert(from, migration);
+    }
+    
+    // Brings a component saved at `version` up to the current schema, giving
+    // its current name, or `None` if a migration dropped it
+    fn migrate(&self, name: &str, version: u32, mut value: Value) -> Result<Option<(String, Value)>, SceneError> {
+        let mut name = name.to_string();
+        for from in version..self.schema_version {
+            let Some(migration) = self.migrations.get(&name).and_then(|migrations| migrations.get(&from)) else {
+                continue;
+            };
+            match migration(value).map_err(|message| SceneError::MigrationFailed {
+                component: name.clone(),
+                from,
+                message,
+            })? {
+                Some((renamed, migrated)) => {
+                    name = renamed;
+                    value = migrated;
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((name, value)))
+    }
+}
+
+impl Default for SceneRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    // The scene registry, created on first use
+    pub fn scene_registry_mut(&mut self) -> &mut SceneRegistry {
+        if !self.has_resource::<SceneRegistry>() {
+            self.insert_resource(SceneRegistry::new());
+        }
+        self.resources.get_exclusive::<SceneRegistry>().expect("scene registry missing")
+    }
+    
+    // Snapshots every entity with its registered components, plus the
+    // registered resources. Entity ids are renumbered from 0 in index order.
+    pub fn save_scene(&self) -> Scene {
+        let registry = self.resource::<SceneRegistry>().map_or_else(SceneRegistry::new, |registry| registry.clone());
+        let types = self.resource::<TypeRegistry>().map_or_else(TypeRegistry::new, |types| types.clone());
+        
+        let mut entities: Vec<Entity> = self.get_entities().collect();
+        entities.sort_unstable();
+        let ids: HashMap<Entity, u64> = entities.iter().enumerate().map(|(id, &entity)| (entity, id as u64)).collect();
+        // References to dead entities can't be restored, so they stay dead
+        let mut map = |entity: Entity| ids.get(&entity).map_or(Entity::from_bits(u64::MAX), |&id| Entity::from_bits(id));
+        
+        let mut scene = Scene::new();
+        scene.schema_version = registry.schema_version;
+        for (id, &entity) in entities.iter().enumerate() {
+            let components = types
+                .registrations()
+                .filter_map(|(name, registration)| Some((name.to_string(), (registration.save)(self, entity, &mut map)?)))
+                .collect();
+            scene.entities.push(SceneEntity { id: id as u64, components });
+        }
+        scene.resources = registry
+            .resources
+            .iter()
+            .filter_map(|(name, entry)| Some((name.clone(), (entry.save)(self)?)))
+            .collect();
+        scene
+    }
+    
+    // Spawns the scene's entities alongside any already in the world and
+    // inserts its resources, replacing existing ones. Returns the entity each
+    // scene id became. Nothing is changed if any of the data is invalid.
+    pub fn load_scene(&mut self, scene: &Scene) -> Result<HashMap<u64, Entity>, SceneError> {
+        if scene.version > Scene::VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+        let registry = self.resource::<SceneRegistry>().map_or_else(SceneRegistry::new, |registry| registry.clone());
+        let types = self.resource::<TypeRegistry>().map_or_else(TypeRegistry::new, |types| types.clone());
+        if scene.schema_version > registry.schema_version {
+            return Err(SceneError::UnsupportedSchema {
+                version: scene.schema_version,
+                supported: registry.schema_version,
+            });
+        }
+        
+        // Parse everything before touching the world
+        let mut entities = Vec::with_capacity(scene.entities.len());
+        for scene_entity in &scene.entities {
+            let mut components = Vec::with_capacity(scene_entity.components.len());
+            for (name, value) in &scene_entity.components {
+                // Migrate first, as the component may have been renamed since
+                let Some((name, value)) = registry.migrate(name, scene.schema_version, value.clone())? else {
+                    continue;
+                };
+                let registration = types
+                    .registration(&name)
+                    .ok_or_else(|| SceneError::UnknownComponent(name.clone()))?;
+                let component = (registration.load)(value).map_err(|error| SceneError::InvalidComponent {
+                    component: name.clone(),
+                    message: error.to_string(),
+                })?;
+                components.push(component);
+            }
+            entities.push((scene_entity.id, components));
+        }
+        
+        let mut resources = Vec::with_capacity(scene.resources.len());
+        for (name, value) in &scene.resources {
+            let entry = registry.resources
+                .get(name)
+                .ok_or_else(|| SceneError::UnknownResource(name.clone()))?;
+            let insert = (entry.load)(value.clone()).map_err(|error| SceneError::InvalidResource {
+                resource: name.clone(),
+                message: error.to_string(),
+            })?;
+            resources.push(insert);
+        }
+        
+        // Check references before spawning anything
+        let mut ids = HashSet::with_capacity(scene.entities.len());
+        for scene_entity in &scene.entities {
+            if !ids.insert(scene_entity.id) {
+                return Err(SceneError::DuplicateEntity(scene_entity.id));
+            }
+        }
+        let dead = Entity::from_bits(u64::MAX);
+        let mut unknown = None;
+        for (_, components) in &mut entities {
+            for component in components {
+                component.map_entities(&mut |entity| {
+                    if entity != dead && !ids.contains(&entity.to_bits()) {
+                        unknown.get_or_insert(entity.to_bits());
+                    }
+                    entity
+                });
+            }
+        }
+        if let Some(id) = unknown {
+            return Err(SceneError::UnknownEntity(id));
+        }
+        
+        let spawned: HashMap<u64, Entity> = entities.iter().map(|&(id, _)| (id, self.create_entity())).collect();
+        for (id, components) in entities {
+            let entity = spawned[&id];
+            for mut component in components {
+                component.map_entities(&mut |entity| spawned.get(&entity.to_bits()).copied().unwrap_or(dead));
+                component.insert(self, entity);
+            }
+        }
+        for insert in resources {
+            insert(self);
+        }
+        Ok(spawned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::{Children, Component, Name, Parent, Transform};
+    
+    #[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+    struct Health(u32);
+    
+    #[test]
+    fn scenes_round_trip_with_remapped_hierarchy() {
+        let mut world = World::new();
+        let gap = world.create_entity();
+        let parent = world.create_entity();
+        world.add_component(parent, Transform::new(10.0, 20.0));
+        let child = world.create_entity();
+        world.add_component(child, Name::new("shadow"));
+        world.set_parent(child, parent);
+        world.despawn(gap);
+        
+        let scene = Scene::from_binary(&world.save_scene().to_binary()).unwrap();
+        let mut copy = World::new();
+        let spawned = copy.load_scene(&scene).unwrap();
+        let (parent, child) = (spawned[&0], spawned[&1]);
+        assert_eq!(copy.get_component::<Parent>(child).unwrap().0, parent);
+        assert_eq!(copy.get_component::<Children>(parent).unwrap().0, vec![child]);
+        assert_eq!(copy.get_component::<Transform>(parent).unwrap().x, 10.0);
+        assert_eq!(copy.get_component::<Name>(child).unwrap().as_str(), "shadow");
+    }
+    
+    #[test]
+    fn saves_only_registered_components() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        world.add_component(entity, Health(5));
+        assert!(world.save_scene().entities[0].components.is_empty());
+        
+        world.type_registry_mut().register_component::<Health>("Health");
+        let scene = world.save_scene();
+        assert_eq!(scene.entities[0].components["Health"], serde_json::json!(5));
+        
+        let mut copy = World::new();
+        assert!(matches!(copy.load_scene(&scene), Err(SceneError::UnknownComponent(_))));
+        copy.type_registry_mut().register_component::<Health>("Health");
+        let spawned = copy.load_scene(&scene).unwrap();
+        assert_eq!(*copy.get_component::<Health>(spawned[&0]).unwrap(), Health(5));
+    }
+    
+    #[test]
+    fn failed_loads_leave_the_world_untouched() {
+        let mut scene = Scene::new();
+        scene.entities.push(SceneEntity {
+            id: 0,
+            components: BTreeMap::from([("Parent".to_string(), serde_json::json!(7))]),
+        });
+        
+        let mut world = World::new();
+        assert!(matches!(world.load_scene(&scene), Err(SceneError::UnknownEntity(7))));
+        assert_eq!(world.entity_count(), 0);
+        assert!(!world.has_resource::<SceneRegistry>());
+        assert!(!world.has_resource::<TypeRegistry>());
+    }
+}
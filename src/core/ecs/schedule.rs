@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use super::{Access, ParallelSystem, System, World};
+
+// Stages run in declaration order every frame. `FixedUpdate` runs zero or
+// more times per frame at the schedule's fixed time step; `Render` is driven
+// separately by the renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    PreUpdate,
+    FixedUpdate,
+    Update,
+    PostUpdate,
+    Render,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::PreUpdate,
+        Stage::FixedUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::Render,
+    ];
+}
+
+type RunCondition = Box<dyn FnMut(&World) -> bool>;
+
+enum SystemKind {
+    // Gets `&mut World` and always runs alone
+    Exclusive(Box<dyn System>),
+    Parallel(Box<dyn ParallelSystem>, Access),
+}
+
+// A system together with its scheduling metadata, e.g.
+// `SystemDescriptor::new(movement).label("movement").after("input")`
+pub struct SystemDescriptor {
+    system: SystemKind,
+    labels: Vec<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    run_conditions: Vec<RunCondition>,
+    // World tick of the previous run, for change detection
+    last_run: u64,
+}
+
+impl SystemDescriptor {
+    pub fn new<S: System + 'static>(system: S) -> Self {
+        Self::with_kind(SystemKind::Exclusive(Box::new(system)))
+    }
+    
+    // A system limited to `access`, which the scheduler may run on a worker
+    // thread at the same time as other parallel systems in its stage whose
+    // accesses don't conflict and that aren't ordered against it
+    pub fn parallel<S: ParallelSystem + 'static>(access: Access, system: S) -> Self {
+        Self::with_kind(SystemKind::Parallel(Box::new(system), access))
+    }
+    
+    fn with_kind(system: SystemKind) -> Self {
+        Self {
+            system,
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            run_conditions: Vec::new(),
+            last_run: 0,
+        }
+    }
+    
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.labels.push(label);
+        self
+    }
+    
+    // Run before every system carrying `label` in the same stage
+    pub fn before(mut self, label: &'static str) -> Self {
+        self.before.push(label);
+        self
+    }
+    
+    // Run after every system carrying `label` in the same stage
+    pub fn after(mut self, label: &'static str) -> Self {
+        self.after.push(label);
+        self
+    }
+    
+    // Skip the system on frames where `condition` returns false. Multiple
+    // conditions must all pass.
+    pub fn run_if(mut self, condition: impl FnMut(&World) -> bool + 'static) -> Self {
+        self.run_conditions.push(Box::new(condition));
+        self
+    }
+    
+    fn should_run(&mut self, world: &World) -> bool {
+        self.run_conditions.iter_mut().all(|condition| condition(world))
+    }
+    
+    fn access(&self) -> Option<&Access> {
+        match &self.system {
+            SystemKind::Exclusive(_) => None,
+            SystemKind::Parallel(_, access) => Some(access),
+        }
+    }
+    
+    // Whether a before/after constraint links the two systems
+    fn is_ordered_with(&self, other: &SystemDescriptor) -> bool {
+        let constrains = |a: &SystemDescriptor, b: &SystemDescriptor| {
+            a.before.iter().chain(&a.after).any(|label| b.labels.contains(label))
+        };
+        constrains(self, other) || constrains(other, self)
+    }
+    
+    // Whether this system can run alongside all of `batch`
+    fn can_join(&self, batch: &[SystemDescriptor]) -> bool {
+        let Some(access) = self.access() else {
+            return false;
+        };
+        batch.iter().all(|other| {
+            other.access().is_some_and(|other_access| access.is_compatible(other_access))
+                && !self.is_ordered_with(other)
+        })
+    }
+    
+    fn run(&mut self, world: &mut World, delta_time: f32) {
+        match &mut self.system {
+            SystemKind::Exclusive(system) => {
+                world.run_system(&mut self.last_run, |world| system.update(world, delta_time));
+            }
+            SystemKind::Parallel(system, access) => {
+                world.run_parallel(std::iter::once((&mut self.last_run, system.as_mut(), &*access)), delta_time);
+            }
+        }
+    }
+}
+
+impl<S: System + 'static> From<S> for SystemDescriptor {
+    fn from(system: S) -> Self {
+        SystemDescriptor::new(system)
+    }
+}
+
+#[derive(Default)]
+struct StageSystems {
+    systems: Vec<SystemDescriptor>,
+    dirty: bool,
+}
+
+impl StageSystems {
+    // Orders systems so every before/after constraint holds, keeping
+    // registration order wherever the constraints leave a choice
+    fn sort(&mut self, stage: Stage) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        
+        let count = self.systems.len();
+        let mut by_label: HashMap<&'static str, Vec<usize>> = HashMap::new();
+        for (index, descriptor) in self.systems.iter().enumerate() {
+            for label in &descriptor.labels {
+                by_label.entry(label).or_default().push(index);
+            }
+        }
+        
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut in_degree = vec![0usize; count];
+        for (index, descriptor) in self.systems.iter().enumerate() {
+            for label in &descriptor.before {
+                for &other in by_label.get(label).into_iter().flatten() {
+                    successors[index].push(other);
+                    in_degree[other] += 1;
+                }
+            }
+            for label in &descriptor.after {
+                for &other in by_label.get(label).into_iter().flatten() {
+                    successors[other].push(index);
+                    in_degree[index] += 1;
+                }
+            }
+        }
+        
+        let mut order = Vec::with_capacity(count);
+        let mut placed = vec![false; count];
+        while order.len() < count {
+            let next = (0..count).find(|&index| !placed[index] && in_degree[index] == 0);
+            let Some(next) = next else {
+                panic!("system ordering constraints in stage {:?} form a cycle", stage);
+            };
+            
+            placed[next] = true;
+            order.push(next);
+            for &successor in &successors[next] {
+                in_degree[successor] -= 1;
+            }
+        }
+        
+        let mut slots: Vec<Option<SystemDescriptor>> = self.systems.drain(..).map(Some).collect();
+        self.systems = order
+            .into_iter()
+            .map(|index| slots[index].take().expect("system placed twice"))
+            .collect();
+    }
+}
+
+pub(crate) struct Schedule {
+    stages: HashMap<Stage, StageSystems>,
+    fixed_time_step: f32,
+    accumulator: f32,
+}
+
+impl Schedule {
+    // Caps catch-up work after a long frame (e.g. a backgrounded tab)
+    const MAX_FIXED_STEPS: u32 = 8;
+    
+    pub fn new() -> Self {
+        Self {
+            stages: HashMap::new(),
+            fixed_time_step: 1.0 / 60.0,
+            accumulator: 0.0,
+        }
+    }
+    
+    // Same timing settings, no systems: those are arbitrary closures and
+    // can't be copied
+    pub fn without_systems(&self) -> Self {
+        Self {
+            stages: HashMap::new(),
+            fixed_time_step: self.fixed_time_step,
+            accumulator: self.accumulator,
+        }
+    }
+    
+    // Makes every system treat all components as added and changed on its
+    // next run
+    pub fn reset_change_ticks(&mut self) {
+        for descriptor in self.stages.values_mut().flat_map(|stage_systems| &mut stage_systems.systems) {
+            descriptor.last_run = 0;
+        }
+    }
+    
+    pub fn add_system(&mut self, stage: Stage, descriptor: SystemDescriptor) {
+        let stage_systems = self.stages.entry(stage).or_default();
+        stage_systems.systems.push(descriptor);
+        stage_systems.dirty = true;
+    }
+    
+    pub fn fixed_time_step(&self) -> f32 {
+        self.fixed_time_step
+    }
+    
+    pub fn set_fixed_time_step(&mut self, step: f32) {
+        assert!(step > 0.0, "fixed time step must be positive");
+        self.fixed_time_step = step;
+    }
+    
+    // Adds a frame's worth of time and returns how many fixed steps are due.
+    // NaN, infinite and negative deltas add nothing, as they would stall or
+    // poison the accumulator.
+    pub fn accumulate(&mut self, delta_time: f32) -> u32 {
+        if delta_time.is_finite() && delta_time > 0.0 {
+            self.accumulator += delta_time;
+        }
+        
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_time_step && steps < Self::MAX_FIXED_STEPS {
+            self.accumulator -= self.fixed_time_step;
+            steps += 1;
+        }
+        
+        // Time the cap left over is dropped rather than carried into the
+        // next frame, which would then hit the cap too
+        if self.accumulator >= self.fixed_time_step {
+            self.accumulator = 0.0;
+        }
+        steps
+    }
+    
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World, delta_time: f32) {
+        let Some(stage_systems) = self.stages.get_mut(&stage) else {
+            return;
+        };
+        stage_systems.sort(stage);
+        
+        // Walk the sorted systems in batches: a run of consecutive parallel
+        // systems that can all run at once, or a single system otherwise
+        let systems = &mut stage_systems.systems;
+        let mut start = 0;
+        while start < systems.len() {
+            let mut end = start + 1;
+            while end < systems.len() && systems[end].can_join(&systems[start..end]) {
+                end += 1;
+            }
+            
+            // Run conditions for the whole batch are checked before any of it runs
+            let mut batch: Vec<&mut SystemDescriptor> = systems[start..end]
+                .iter_mut()
+                .filter_map(|descriptor| descriptor.should_run(world).then_some(descriptor))
+                .collect();
+            
+            if batch.len() == 1 {
+                batch[0].run(world, delta_time);
+            } else if !batch.is_empty() {
+                let parallel = batch.iter_mut().filter_map(|descriptor| match &mut descriptor.system {
+                    SystemKind::Parallel(system, access) => Some((&mut descriptor.last_run, system.as_mut(), &*access)),
+                    SystemKind::Exclusive(_) => None,
+                });
+                world.run_parallel(parallel, delta_time);
+            }
+            start = end;
+        }
+    }
+    
+    // Folds in systems that were registered while this schedule was taken
+    // out of the world to run
+    pub fn merge(&mut self, other: Schedule) {
+        for (stage, stage_systems) in other.stages {
+            for descriptor in stage_systems.systems {
+                self.add_system(stage, descriptor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    
+    use super::*;
+    use crate::core::ecs::{Component, SystemWorld, Transform, Velocity, With};
+    
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+    
+    fn logger(log: &Log, name: &'static str) -> impl FnMut(&mut World, f32) + 'static {
+        let log = log.clone();
+        move |_: &mut World, _: f32| log.borrow_mut().push(name)
+    }
+    
+    #[test]
+    fn runs_stages_in_order() {
+        let log = Log::default();
+        let mut world = World::new();
+        world.add_system(Stage::PostUpdate, logger(&log, "post"));
+        world.add_system(Stage::Update, logger(&log, "update"));
+        world.add_system(Stage::FixedUpdate, logger(&log, "fixed"));
+        world.add_system(Stage::PreUpdate, logger(&log, "pre"));
+        world.add_system(Stage::Render, logger(&log, "render"));
+        
+        world.set_fixed_time_step(0.5);
+        world.update(0.5);
+        assert_eq!(*log.borrow(), ["pre", "fixed", "update", "post"]);
+    }
+    
+    #[test]
+    fn orders_systems_by_label() {
+        let log = Log::default();
+        let mut world = World::new();
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "render")).after("physics"));
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "physics")).label("physics").after("input"));
+        world.add_system(Stage::Update, logger(&log, "unordered"));
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "input")).label("input"));
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "camera")).before("physics"));
+        
+        world.update(0.0);
+        assert_eq!(*log.borrow(), ["unordered", "input", "camera", "physics", "render"]);
+    }
+    
+    #[test]
+    #[should_panic(expected = "form a cycle")]
+    fn cycles_panic() {
+        let log = Log::default();
+        let mut world = World::new();
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "a")).label("a").after("b"));
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "b")).label("b").after("a"));
+        world.update(0.0);
+    }
+    
+    #[test]
+    fn skips_systems_whose_run_condition_fails() {
+        let log = Log::default();
+        let mut world = World::new();
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "never")).run_if(|_| false));
+        world.add_system(Stage::Update, SystemDescriptor::new(logger(&log, "always")).run_if(|_| true));
+        world.update(0.0);
+        assert_eq!(*log.borrow(), ["always"]);
+    }
+    
+    #[test]
+    fn accumulates_fixed_steps() {
+        let mut schedule = Schedule::new();
+        schedule.set_fixed_time_step(0.25);
+        assert_eq!(schedule.accumulate(0.1), 0);
+        assert_eq!(schedule.accumulate(0.2), 1);
+        assert_eq!(schedule.accumulate(0.45), 2);
+    }
+    
+    #[test]
+    fn caps_fixed_steps_and_drops_the_rest() {
+        let mut schedule = Schedule::new();
+        schedule.set_fixed_time_step(0.25);
+        assert_eq!(schedule.accumulate(1000.0), Schedule::MAX_FIXED_STEPS);
+        assert_eq!(schedule.accumulate(0.0), 0);
+    }
+    
+    #[test]
+    fn ignores_deltas_that_arent_finite_or_positive() {
+        let mut schedule = Schedule::new();
+        schedule.set_fixed_time_step(0.25);
+        assert_eq!(schedule.accumulate(f32::INFINITY), 0);
+        assert_eq!(schedule.accumulate(f32::NAN), 0);
+        assert_eq!(schedule.accumulate(-1.0), 0);
+        assert_eq!(schedule.accumulate(0.25), 1);
+    }
+    
+    #[derive(Clone, Component)]
+    #[component(storage = "sparse")]
+    struct Stunned;
+    
+    fn parallel(access: Access) -> SystemDescriptor {
+        SystemDescriptor::parallel(access, |_: &SystemWorld, _: f32| {})
+    }
+    
+    #[test]
+    fn batches_only_compatible_systems() {
+        let movement = parallel(Access::new().query::<(&mut Transform, &Velocity)>());
+        let readers = [parallel(Access::new().query::<&Transform>()), parallel(Access::new().query::<&Velocity>())];
+        assert!(readers[1].can_join(&readers[..1]));
+        assert!(!movement.can_join(&readers));
+        assert!(!SystemDescriptor::new(|_: &mut World, _: f32| {}).can_join(&readers));
+        
+        let ordered = parallel(Access::new().query::<&Velocity>()).after("reader");
+        let labeled = [parallel(Access::new().query::<&Transform>()).label("reader")];
+        assert!(!ordered.can_join(&labeled));
+    }
+    
+    // `With` on a sparse component reads its set, so it can't share a batch
+    // with a system writing it
+    #[test]
+    fn sparse_filters_conflict_with_writers() {
+        let filtered = [parallel(Access::new().query_filtered::<&Transform, With<Stunned>>())];
+        assert!(!parallel(Access::new().query::<&mut Stunned>()).can_join(&filtered));
+        assert!(parallel(Access::new().query::<&Stunned>()).can_join(&filtered));
+        
+        // Dense filters only rule out archetypes
+        let filtered = [parallel(Access::new().query_filtered::<&Transform, With<Velocity>>())];
+        assert!(parallel(Access::new().query::<&mut Velocity>()).can_join(&filtered));
+    }
+}
@@ -0,0 +1,356 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+use super::borrow::{BorrowFlag, Ref};
+use super::change::ComponentTicks;
+use super::{Component, Entity};
+
+// Storage for components declared with `#[component(storage = "sparse")]`.
+// Values sit in a dense array indexed through a sparse one keyed by entity
+// index, so adding or removing them never moves the entity between
+// archetypes. Iterating is slower than a table column, which is the tradeoff.
+#[derive(Clone)]
+pub(crate) struct SparseSet<T> {
+    dense: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    entities: Vec<Entity>,
+    sparse: Vec<Option<u32>>,
+}
+
+impl<T> SparseSet<T> {
+    fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            ticks: Vec::new(),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+    
+    pub fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.index() as usize)?)? as usize;
+        (self.entities[index] == entity).then_some(index)
+    }
+    
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+    
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|index| &self.dense[index])
+    }
+    
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+    
+    pub fn ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.dense_index(entity).map(|index| self.ticks[index])
+    }
+    
+    pub fn dense_ptr(&mut self) -> *mut T {
+        self.dense.as_mut_ptr()
+    }
+    
+    pub fn ticks_ptr(&mut self) -> *mut ComponentTicks {
+        self.ticks.as_mut_ptr()
+    }
+    
+    pub fn get_mut(&mut self, entity: Entity, tick: u64) -> Option<&mut T> {
+        let index = self.dense_index(entity)?;
+        self.ticks[index].set_changed(tick);
+        Some(&mut self.dense[index])
+    }
+    
+    pub fn insert(&mut self, entity: Entity, component: T, tick: u64) {
+        if let Some(index) = self.dense_index(entity) {
+            self.dense[index] = component;
+            self.ticks[index].set_changed(tick);
+            return;
+        }
+        
+        let slot = entity.index() as usize;
+        if slot >= self.sparse.len() {
+            self.sparse.resize(slot + 1, None);
+        }
+        self.sparse[slot] = Some(self.dense.len() as u32);
+        self.dense.push(component);
+        self.ticks.push(ComponentTicks::new(tick));
+        self.entities.push(entity);
+    }
+    
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.dense_index(entity)?;
+        self.sparse[entity.index() as usize] = None;
+        
+        let component = self.dense.swap_remove(index);
+        self.ticks.swap_remove(index);
+        self.entities.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[moved.index() as usize] = Some(index as u32);
+        }
+        Some(component)
+    }
+}
+
+// Type-erased view of a `SparseSet<T>`, for despawning entities without
+// knowing which sparse component types they have
+trait SparseStorage {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn remove_entity(&mut self, entity: Entity) -> bool;
+    fn clone_box(&self) -> Box<dyn SparseStorage>;
+}
+
+impl<T: Component> SparseStorage for SparseSet<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    
+    fn remove_entity(&mut self, entity: Entity) -> bool {
+        self.remove(entity).is_some()
+    }
+    
+    fn clone_box(&self) -> Box<dyn SparseStorage> {
+        Box::new(self.clone())
+    }
+}
+
+struct SparseCell {
+    data: UnsafeCell<Box<dyn SparseStorage>>,
+    borrow: BorrowFlag,
+}
+
+impl Clone for SparseCell {
+    fn clone(&self) -> Self {
+        if !self.borrow.borrow() {
+            panic!("cannot clone the world while a sparse component set is borrowed mutably");
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        let data = unsafe { (*self.data.get()).clone_box() };
+        self.borrow.release();
+        
+        Self {
+            data: UnsafeCell::new(data),
+            borrow: BorrowFlag::new(),
+        }
+    }
+}
+
+// Every sparse set in a world, with the same runtime borrow rules as
+// archetype columns
+#[derive(Clone, Default)]
+pub(crate) struct SparseSets {
+    sets: HashMap<TypeId, SparseCell>,
+}
+
+impl SparseSets {
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.sets
+            .get_mut(&TypeId::of::<T>())?
+            .data
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+    }
+    
+    pub fn get_or_insert<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.sets
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| SparseCell {
+                data: UnsafeCell::new(Box::new(SparseSet::<T>::new())),
+                borrow: BorrowFlag::new(),
+            })
+            .data
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("sparse set stored under the wrong type")
+    }
+    
+    // Drops every sparse component of `entity`, returning the types it had
+    pub fn remove_entity(&mut self, entity: Entity) -> Vec<TypeId> {
+        self.sets
+            .iter_mut()
+            .filter_map(|(&type_id, cell)| cell.data.get_mut().remove_entity(entity).then_some(type_id))
+            .collect()
+    }
+    
+    // Borrows the whole `T` set, panicking on a conflicting borrow. Does
+    // nothing if no `T` was ever inserted.
+    pub fn borrow<T: Component>(&self) {
+        if let Some(cell) = self.sets.get(&TypeId::of::<T>()) {
+            if !cell.borrow.borrow() {
+                panic!("`{}` is already borrowed mutably", type_name::<T>());
+            }
+        }
+    }
+    
+    pub fn borrow_mut<T: Component>(&self) {
+        if let Some(cell) = self.sets.get(&TypeId::of::<T>()) {
+            if !cell.borrow.borrow_mut() {
+                panic!("`{}` is already borrowed", type_name::<T>());
+            }
+        }
+    }
+    
+    pub fn release<T: Component>(&self) {
+        if let Some(cell) = self.sets.get(&TypeId::of::<T>()) {
+            cell.borrow.release();
+        }
+    }
+    
+    pub fn release_mut<T: Component>(&self) {
+        if let Some(cell) = self.sets.get(&TypeId::of::<T>()) {
+            cell.borrow.release_mut();
+        }
+    }
+    
+    // Safety: as for `Archetype::column_ptr`, the caller must hold the
+    // borrow matching how it accesses the values behind the pointer
+    pub unsafe fn set_ptr<T: Component>(&self) -> Option<*mut SparseSet<T>> {
+        let cell = self.sets.get(&TypeId::of::<T>())?;
+        (*cell.data.get())
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .map(|set| set as *mut SparseSet<T>)
+    }
+    
+    // Borrows a single component, like `Archetype::get`
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let cell = self.sets.get(&TypeId::of::<T>())?;
+        if !cell.borrow.borrow() {
+            panic!("`{}` is already borrowed mutably", type_name::<T>());
+        }
+        
+        // Safety: the shared borrow taken above excludes any `&mut` access
+        match unsafe { Self::downcast::<T>(cell) }.get(entity) {
+            Some(value) => Some(unsafe { Ref::new(&cell.borrow, value) }),
+            None => {
+                cell.borrow.release();
+                None
+            }
+        }
+    }
+    
+    unsafe fn downcast<T: Component>(cell: &SparseCell) -> &SparseSet<T> {
+        (*cell.data.get())
+            .as_any()
+            .downcast_ref::<SparseSet<T>>()
+            .expect("sparse set stored under the wrong type")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::archetype::EntityLocation;
+    use crate::core::ecs::entity::Entities;
+    use crate::core::ecs::{Transform, With, Without, World};
+    
+    #[derive(Clone, Debug, PartialEq, Component)]
+    #[component(storage = "sparse")]
+    struct Stunned(u32);
+    
+    fn entities(count: usize) -> Vec<Entity> {
+        let mut entities = Entities::new();
+        (0..count).map(|_| entities.alloc()).collect()
+    }
+    
+    #[test]
+    fn removing_moves_the_last_row_into_the_gap() {
+        let entity = entities(4);
+        let mut set = SparseSet::new();
+        for (index, &entity) in entity.iter().enumerate() {
+            set.insert(entity, index * 10, 1);
+        }
+        
+        assert_eq!(set.remove(entity[1]), Some(10));
+        assert_eq!(set.entities, [entity[0], entity[3], entity[2]]);
+        assert_eq!(set.dense_index(entity[3]), Some(1));
+        assert_eq!(set.get(entity[3]), Some(&30));
+        assert!(!set.contains(entity[1]));
+        assert_eq!(set.remove(entity[1]), None);
+        
+        // The last row has nothing to move
+        assert_eq!(set.remove(entity[2]), Some(20));
+        assert_eq!(set.entities, [entity[0], entity[3]]);
+        assert_eq!(set.get(entity[0]), Some(&0));
+    }
+    
+    #[test]
+    fn inserting_again_replaces_in_place() {
+        let entity = entities(6);
+        let mut set = SparseSet::new();
+        set.insert(entity[5], "old", 1);
+        set.insert(entity[2], "other", 1);
+        set.insert(entity[5], "new", 3);
+        
+        assert_eq!(set.entities, [entity[5], entity[2]]);
+        assert_eq!(set.get(entity[5]), Some(&"new"));
+        let ticks = set.ticks(entity[5]).unwrap();
+        assert_eq!((ticks.added(), ticks.changed()), (1, 3));
+    }
+    
+    #[test]
+    fn stale_generations_miss() {
+        let mut entities = Entities::new();
+        let old = entities.alloc();
+        entities.set_location(old, EntityLocation { archetype: 0, row: 0 });
+        entities.free(old);
+        let new = entities.alloc();
+        assert_eq!(new.index(), old.index());
+        
+        let mut set = SparseSet::new();
+        set.insert(old, 1, 1);
+        set.remove(old);
+        set.insert(new, 2, 2);
+        assert_eq!(set.get(old), None);
+        assert_eq!(set.remove(old), None);
+        assert_eq!(set.get(new), Some(&2));
+    }
+    
+    #[test]
+    fn worlds_keep_sparse_components_per_entity() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..3)
+            .map(|index| {
+                let entity = world.create_entity();
+                world.add_component(entity, Transform::new(index as f32, 0.0));
+                world.add_component(entity, Stunned(index));
+                entity
+            })
+            .collect();
+        
+        assert_eq!(world.remove_component::<Stunned>(entities[0]), Some(Stunned(0)));
+        assert_eq!(*world.get_component::<Stunned>(entities[2]).unwrap(), Stunned(2));
+        world.despawn(entities[1]);
+        
+        let stunned: Vec<Entity> = world.query_filtered::<&Transform, With<Stunned>>().iter().map(|(entity, _)| entity).collect();
+        let free: Vec<Entity> = world.query_filtered::<&Transform, Without<Stunned>>().iter().map(|(entity, _)| entity).collect();
+        assert_eq!((stunned, free), (vec![entities[2]], vec![entities[0]]));
+        
+        let mut query = world.query::<(&Transform, &mut Stunned)>();
+        for (_, (transform, mut stunned)) in query.iter() {
+            stunned.0 += transform.x as u32;
+        }
+        drop(query);
+        assert_eq!(*world.get_component::<Stunned>(entities[2]).unwrap(), Stunned(4));
+    }
+    
+    #[test]
+    #[should_panic(expected = "is already borrowed")]
+    fn conflicting_sparse_borrows_panic() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        world.add_component(entity, Stunned(0));
+        let _reading = world.query::<&Stunned>();
+        world.query::<&mut Stunned>();
+    }
+}
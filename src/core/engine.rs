@@ -1,29 +1,54 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{HtmlCanvasElement, Document, Window};
-use std::collections::HashMap;
+use web_sys::{AudioBuffer, HtmlCanvasElement, WebGl2RenderingContext};
+use std::collections::{HashMap, HashSet};
 
+use crate::assets::{
+    Archive, ArchiveIo, AssetServer, AudioLoader, BytesLoader, FetchIo, Handle, ImageLoader, JsonLoader, LoadState, SceneLoader, ShaderLoader,
+    TextLoader, TextureLoader,
+};
+use crate::graphics::atlas::{AtlasBuilder, AtlasLayout};
 use crate::graphics::renderer::Renderer;
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::sprite_batch::RenderStats;
+use crate::graphics::texture::Texture;
 use crate::input::input_manager::InputManager;
+use crate::physics::collision::CollisionEvent;
 use crate::physics::physics_world::PhysicsWorld;
 use crate::audio::audio_manager::AudioManager;
-use crate::core::ecs::{World, Entity};
+use crate::core::ecs::{propagate_transforms, World, Entity, GlobalTransform, RefMut, SpriteBundle, Scene, Stage, Sprite, Value};
 use crate::core::time::TimeManager;
+use crate::tiled::{TiledError, TiledMap};
+use crate::utils::Random;
 
 pub struct GameEngine {
     canvas: HtmlCanvasElement,
     renderer: Renderer,
-    input_manager: InputManager,
     physics_world: PhysicsWorld,
     audio_manager: AudioManager,
+    assets: AssetServer,
+    // Textures loaded from JavaScript, kept loaded until released
+    textures: HashMap<u32, Handle<Texture>>,
+    // The regions of the textures that are atlases
+    atlases: HashMap<u32, AtlasLayout>,
+    // Sprite shaders loaded from JavaScript, kept loaded until released
+    shaders: HashMap<u32, Handle<ShaderProgram>>,
+    // Sounds loaded from JavaScript, kept loaded until released
+    sounds: HashMap<u32, Handle<AudioBuffer>>,
+    // The world being updated and rendered; the others are kept alive under
+    // their names until switched to
     world: World,
-    time_manager: TimeManager,
-    sprites: HashMap<u32, Entity>,
+    active_world: String,
+    worlds: HashMap<String, World>,
+    // Sprite ids stay bound to the world they were created in
+    sprites: HashMap<u32, (String, Entity)>,
     next_sprite_id: u32,
     canvas_width: u32,
     canvas_height: u32,
 }
 
 impl GameEngine {
+    pub const DEFAULT_WORLD: &'static str = "main";
+    
     pub fn new(canvas_id: &str) -> Result<Self, JsValue> {
         let window = web_sys::window().unwrap();
         let document = window.document().unwrap();
@@ -37,20 +62,35 @@ impl GameEngine {
         
         // Initialize all systems
         let renderer = Renderer::new(&canvas)?;
-        let input_manager = InputManager::new();
         let physics_world = PhysicsWorld::new();
         let audio_manager = AudioManager::new()?;
-        let world = World::new();
-        let time_manager = TimeManager::new();
+        
+        // Assets are fetched relative to the page
+        let mut assets = AssetServer::with_io(FetchIo::new(""));
+        assets.register_loader(TextureLoader::new(renderer.gl()));
+        assets.register_loader(ShaderLoader::new(renderer.gl()));
+        assets.register_loader(SceneLoader);
+        assets.register_loader(ImageLoader);
+        assets.register_loader(JsonLoader);
+        assets.register_loader(TextLoader);
+        assets.register_loader(BytesLoader);
+        if let Some(context) = audio_manager.audio_context() {
+            assets.register_loader(AudioLoader::new(context));
+        }
         
         Ok(GameEngine {
             canvas,
             renderer,
-            input_manager,
             physics_world,
             audio_manager,
-            world,
-            time_manager,
+            assets,
+            textures: HashMap::new(),
+            atlases: HashMap::new(),
+            shaders: HashMap::new(),
+            sounds: HashMap::new(),
+            world: Self::new_world(),
+            active_world: Self::DEFAULT_WORLD.to_string(),
+            worlds: HashMap::new(),
             sprites: HashMap::new(),
             next_sprite_id: 1,
             canvas_width,
@@ -59,27 +99,46 @@ impl GameEngine {
     }
     
     pub fn update(&mut self, delta_time: f64) {
-        self.time_manager.update(delta_time);
+        self.time_manager_mut().update(delta_time);
         
         // Update physics
         self.physics_world.update(delta_time as f32);
         
-        // Update ECS world
-        self.world.update(delta_time as f32);
+        // Run the ECS stages (PreUpdate, FixedUpdate, Update, PostUpdate)
+        let frame_time = self.frame_time();
+        self.world.update(frame_time);
         
         // Process input
-        self.input_manager.update();
+        self.input_manager_mut().update();
+        
+        // Take in finished loads and release assets nothing holds a handle to
+        // anymore
+        self.assets.update();
     }
     
     pub fn render(&mut self) {
+        let frame_time = self.frame_time();
+        self.world.run_stage(Stage::Render, frame_time);
+        propagate_transforms(&mut self.world);
         self.renderer.clear();
         
-        // Render all sprites
-        for (_, entity) in &self.sprites {
-            self.renderer.render_sprite(*entity, &self.world);
+        // Render all sprites; ones whose texture isn't loaded (yet) are drawn
+        // untextured, and with the built-in shader until theirs is loaded.
+        // Both are looked up every frame so reloaded ones show straight away.
+        let shader_id = self.renderer.sprite_shader();
+        for (_, (transform, sprite)) in &mut self.world.query::<(&GlobalTransform, &Sprite)>() {
+            let texture = sprite.texture_id.and_then(|id| self.assets.get_by_id::<Texture>(id));
+            let shader = shader_id.and_then(|id| self.assets.get_by_id::<ShaderProgram>(id));
+            self.renderer.render_sprite(transform, sprite, texture, shader);
         }
         
         self.renderer.present();
+        self.world.clear_trackers();
+    }
+    
+    // Draw calls and sprites in the last frame rendered
+    pub fn render_stats(&self) -> RenderStats {
+        self.renderer.stats()
     }
     
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -90,48 +149,503 @@ impl GameEngine {
         self.renderer.resize(width, height);
     }
     
+    // Time, input and RNG live in the world so systems can reach them
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TimeManager::new());
+        world.insert_resource(InputManager::new());
+        world.insert_resource(Random::new());
+        world.add_event::<CollisionEvent>();
+        world
+    }
+    
+    // Adds an empty world, e.g. "menu" or "loading". Returns false if the
+    // name is taken.
+    pub fn create_world(&mut self, name: &str) -> bool {
+        if self.has_world(name) {
+            return false;
+        }
+        self.worlds.insert(name.to_string(), Self::new_world());
+        true
+    }
+    
+    pub fn has_world(&self, name: &str) -> bool {
+        name == self.active_world || self.worlds.contains_key(name)
+    }
+    
+    pub fn active_world(&self) -> &str {
+        &self.active_world
+    }
+    
+    // Makes `name` the world that's updated and rendered. Time and input
+    // state move along with it, so held keys carry over the switch.
+    pub fn set_active_world(&mut self, name: &str) -> bool {
+        if name == self.active_world {
+            return true;
+        }
+        let Some(mut world) = self.worlds.remove(name) else {
+            return false;
+        };
+        
+        if let Some(time) = self.world.remove_resource::<TimeManager>() {
+            world.insert_resource(time);
+        }
+        if let Some(input) = self.world.remove_resource::<InputManager>() {
+            world.insert_resource(input);
+        }
+        
+        let previous = std::mem::replace(&mut self.world, world);
+        let previous_name = std::mem::replace(&mut self.active_world, name.to_string());
+        self.worlds.insert(previous_name, previous);
+        true
+    }
+    
+    // Deep-copies `source` into `target`, e.g. to snapshot a level or to
+    // restart one from its snapshot. An existing `target` keeps its systems
+    // and is otherwise replaced; a new one starts without systems.
+    pub fn clone_world(&mut self, source: &str, target: &str) -> bool {
+        if source == target {
+            return self.has_world(source);
+        }
+        let Some(copy) = self.world_named(source).cloned() else {
+            return false;
+        };
+        
+        if target == self.active_world {
+            // Keep the live time and input state rather than the snapshot's
+            let time = self.world.remove_resource::<TimeManager>();
+            let input = self.world.remove_resource::<InputManager>();
+            self.world.clone_from(&copy);
+            if let Some(time) = time {
+                self.world.insert_resource(time);
+            }
+            if let Some(input) = input {
+                self.world.insert_resource(input);
+            }
+            return true;
+        }
+        
+        match self.worlds.get_mut(target) {
+            Some(existing) => existing.clone_from(&copy),
+            None => {
+                self.worlds.insert(target.to_string(), copy);
+            }
+        }
+        true
+    }
+    
+    // Drops an inactive world along with its sprites. The active world
+    // can't be removed.
+    pub fn remove_world(&mut self, name: &str) -> Option<World> {
+        let world = self.worlds.remove(name)?;
+        self.sprites.retain(|_, (world_name, _)| world_name != name);
+        Some(world)
+    }
+    
+    pub fn world_named(&self, name: &str) -> Option<&World> {
+        if name == self.active_world {
+            return Some(&self.world);
+        }
+        self.worlds.get(name)
+    }
+    
+    pub fn world_named_mut(&mut self, name: &str) -> Option<&mut World> {
+        if name == self.active_world {
+            return Some(&mut self.world);
+        }
+        self.worlds.get_mut(name)
+    }
+    
     pub fn create_sprite(&mut self, x: f32, y: f32, width: f32, height: f32) -> u32 {
-        let entity = self.world.create_sprite_entity(x, y, width, height);
+        let entity = self.world.spawn(SpriteBundle::new(x, y, width, height));
+        self.track_sprite(entity)
+    }
+    
+    pub fn register_prefab(&mut self, name: &str, json: &str) -> Result<(), JsValue> {
+        self.world
+            .register_prefab_json(name, json)
+            .map_err(|error| JsValue::from_str(&error.to_string()))
+    }
+    
+    // Spawns a registered prefab with JSON overrides and returns its sprite id
+    pub fn instantiate_prefab(&mut self, name: &str, overrides: &str) -> Result<u32, JsValue> {
+        let entity = self.world
+            .instantiate_json(name, overrides)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        Ok(self.track_sprite(entity))
+    }
+    
+    // The active world as a JSON scene
+    pub fn save_scene(&self) -> String {
+        self.world.save_scene().to_json()
+    }
+    
+    // The active world as a binary scene, for savegames
+    pub fn save_game(&self) -> Vec<u8> {
+        self.world.save_scene().to_binary()
+    }
+    
+    // Replaces the active world's entities with those of a JSON scene and
+    // returns the sprite ids of the ones with a `Sprite`
+    pub fn load_scene(&mut self, json: &str) -> Result<Vec<u32>, JsValue> {
+        let scene = Scene::from_json(json).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        self.replace_entities(&scene)
+    }
+    
+    pub fn load_game(&mut self, bytes: &[u8]) -> Result<Vec<u32>, JsValue> {
+        let scene = Scene::from_binary(bytes).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        self.replace_entities(&scene)
+    }
+    
+    // Spawns a Tiled map (.tmj or .tmx contents) next to the active world's
+    // entities and returns the sprite ids of its tile objects. `tilesets` is
+    // a JSON object from the paths of the external tilesets the map refers
+    // to onto their contents.
+    pub fn load_tiled_map(&mut self, contents: &str, tilesets: &str) -> Result<Vec<u32>, JsValue> {
+        let tilesets: HashMap<String, String> = serde_json::from_str(tilesets)
+            .map_err(|error| JsValue::from_str(&format!("invalid tilesets: {}", error)))?;
+        let map = TiledMap::parse(contents, &mut |source| {
+            tilesets.get(source).cloned().ok_or_else(|| TiledError::ExternalTileset(source.to_string()))
+        })
+        .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        
+        let old: HashSet<Entity> = self.world.get_entities().collect();
+        map.spawn(&mut self.world).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        
+        let mut sprites: Vec<Entity> = self.world
+            .get_entities()
+            .filter(|entity| !old.contains(entity) && self.world.has_component::<Sprite>(*entity))
+            .collect();
+        sprites.sort_unstable_by_key(|entity| entity.to_bits());
+        Ok(sprites.into_iter().map(|entity| self.track_sprite(entity)).collect())
+    }
+    
+    fn replace_entities(&mut self, scene: &Scene) -> Result<Vec<u32>, JsValue> {
+        let old: Vec<Entity> = self.world.get_entities().collect();
+        let loaded = self.world.load_scene(scene).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        
+        // Only clear out the old entities once the new ones are in
+        for entity in old {
+            self.world.despawn(entity);
+        }
+        let active_world = &self.active_world;
+        self.sprites.retain(|_, (world, _)| world != active_world);
+        
+        let mut loaded: Vec<(u64, Entity)> = loaded.into_iter().collect();
+        loaded.sort_unstable();
+        let sprites = loaded
+            .into_iter()
+            .filter(|&(_, entity)| self.world.has_component::<Sprite>(entity))
+            .map(|(_, entity)| entity)
+            .collect::<Vec<_>>();
+        Ok(sprites.into_iter().map(|entity| self.track_sprite(entity)).collect())
+    }
+    
+    fn track_sprite(&mut self, entity: Entity) -> u32 {
         let sprite_id = self.next_sprite_id;
-        self.sprites.insert(sprite_id, entity);
+        self.sprites.insert(sprite_id, (self.active_world.clone(), entity));
         self.next_sprite_id += 1;
         sprite_id
     }
     
+    pub fn remove_sprite(&mut self, sprite_id: u32) -> bool {
+        let Some((world, entity)) = self.sprites.remove(&sprite_id) else {
+            return false;
+        };
+        self.world_named_mut(&world).is_some_and(|world| world.despawn(entity))
+    }
+    
     pub fn set_sprite_position(&mut self, sprite_id: u32, x: f32, y: f32) {
-        if let Some(&entity) = self.sprites.get(&sprite_id) {
-            self.world.set_position(entity, x, y);
+        if let Some((world, entity)) = self.sprite_entity_mut(sprite_id) {
+            world.set_position(entity, x, y);
         }
     }
     
     pub fn set_sprite_color(&mut self, sprite_id: u32, r: f32, g: f32, b: f32, a: f32) {
-        if let Some(&entity) = self.sprites.get(&sprite_id) {
-            self.world.set_color(entity, r, g, b, a);
+        if let Some((world, entity)) = self.sprite_entity_mut(sprite_id) {
+            world.set_color(entity, r, g, b, a);
         }
     }
     
+    // Reads a reflected component or field of a sprite by path, e.g.
+    // "Transform.x", as JSON
+    pub fn get_sprite_property(&self, sprite_id: u32, path: &str) -> Result<String, JsValue> {
+        let (world, entity) = self.sprites.get(&sprite_id).ok_or_else(|| unknown_sprite(sprite_id))?;
+        let world = self.world_named(world).ok_or_else(|| unknown_sprite(sprite_id))?;
+        let value = world.reflect_get(*entity, path).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        Ok(value.to_json().to_string())
+    }
+    
+    // Writes a reflected component or field of a sprite by path from JSON,
+    // e.g. ("Transform.x", "10") or ("Sprite", "{\"width\": 32}")
+    pub fn set_sprite_property(&mut self, sprite_id: u32, path: &str, json: &str) -> Result<(), JsValue> {
+        let value = serde_json::from_str(json)
+            .ok()
+            .and_then(Value::from_json)
+            .ok_or_else(|| JsValue::from_str(&format!("invalid value for `{}`: {}", path, json)))?;
+        let (world, entity) = self.sprite_entity_mut(sprite_id).ok_or_else(|| unknown_sprite(sprite_id))?;
+        world.reflect_set(entity, path, value).map_err(|error| JsValue::from_str(&error.to_string()))
+    }
+    
+    // Every reflected component type as JSON, mapping field names to types,
+    // e.g. {"Velocity": {"x": "f32", "y": "f32"}}
+    pub fn component_types(&self) -> String {
+        let types: serde_json::Map<String, serde_json::Value> = self.world
+            .reflected_types()
+            .into_iter()
+            .map(|info| {
+                let fields = info.fields
+                    .iter()
+                    .map(|field| (field.name.to_string(), field.field_type.to_string().into()))
+                    .collect();
+                (info.name.to_string(), serde_json::Value::Object(fields))
+            })
+            .collect();
+        serde_json::Value::Object(types).to_string()
+    }
+    
+    fn sprite_entity_mut(&mut self, sprite_id: u32) -> Option<(&mut World, Entity)> {
+        let (world, entity) = self.sprites.get(&sprite_id)?.clone();
+        Some((self.world_named_mut(&world)?, entity))
+    }
+    
     // Input handling methods
     pub fn handle_key_down(&mut self, key_code: u32) {
-        self.input_manager.handle_key_down(key_code);
+        self.input_manager_mut().handle_key_down(key_code);
     }
     
     pub fn handle_key_up(&mut self, key_code: u32) {
-        self.input_manager.handle_key_up(key_code);
+        self.input_manager_mut().handle_key_up(key_code);
     }
     
     pub fn handle_mouse_move(&mut self, x: f32, y: f32) {
-        self.input_manager.handle_mouse_move(x, y);
+        self.input_manager_mut().handle_mouse_move(x, y);
     }
     
     pub fn handle_mouse_down(&mut self, button: u32, x: f32, y: f32) {
-        self.input_manager.handle_mouse_down(button, x, y);
+        self.input_manager_mut().handle_mouse_down(button, x, y);
     }
     
     pub fn handle_mouse_up(&mut self, button: u32, x: f32, y: f32) {
-        self.input_manager.handle_mouse_up(button, x, y);
+        self.input_manager_mut().handle_mouse_up(button, x, y);
+    }
+    
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+    
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+    
+    fn time_manager_mut(&self) -> RefMut<'_, TimeManager> {
+        self.world.resource_mut::<TimeManager>().expect("TimeManager resource missing")
+    }
+    
+    fn input_manager_mut(&self) -> RefMut<'_, InputManager> {
+        self.world.resource_mut::<InputManager>().expect("InputManager resource missing")
+    }
+    
+    // Seconds since the previous frame, as seen by systems
+    fn frame_time(&self) -> f32 {
+        self.world
+            .resource::<TimeManager>()
+            .map_or(0.0, |time| time.get_delta_time() as f32)
+    }
+    
+    pub fn audio_manager(&mut self) -> &mut AudioManager {
+        &mut self.audio_manager
+    }
+    
+    pub fn assets(&mut self) -> &mut AssetServer {
+        &mut self.assets
+    }
+    
+    // Starts loading a texture, or finds the one already loaded from `path`,
+    // and returns its id for `Sprite::texture_id`
+    pub fn load_texture(&mut self, path: &str) -> u32 {
+        let handle = self.assets.load::<Texture>(path);
+        let id = handle.id();
+        self.textures.insert(id, handle);
+        id
+    }
+    
+    // Lets a texture be freed once no sprite needs it
+    pub fn release_texture(&mut self, texture_id: u32) -> bool {
+        self.atlases.remove(&texture_id);
+        self.textures.remove(&texture_id).is_some()
+    }
+    
+    // Starts loading a sprite shader (see `ShaderLoader` for the file format),
+    // or finds the one already loaded from `path`, and returns its id
+    pub fn load_shader(&mut self, path: &str) -> u32 {
+        let handle = self.assets.load::<ShaderProgram>(path);
+        let id = handle.id();
+        self.shaders.insert(id, handle);
+        id
+    }
+    
+    pub fn release_shader(&mut self, shader_id: u32) -> bool {
+        if self.renderer.sprite_shader() == Some(shader_id) {
+            self.renderer.set_sprite_shader(None);
+        }
+        self.shaders.remove(&shader_id).is_some()
+    }
+    
+    // Draws sprites with a shader from `load_shader`, or the built-in one for
+    // `None`. Returns false for unknown ids.
+    pub fn set_sprite_shader(&mut self, shader_id: Option<u32>) -> bool {
+        if shader_id.is_some_and(|id| !self.shaders.contains_key(&id)) {
+            return false;
+        }
+        self.renderer.set_sprite_shader(shader_id);
+        true
+    }
+    
+    // Starts loading a sound, or finds the one already loaded from `path`,
+    // and returns its id for `play_sound`
+    pub fn load_sound(&mut self, path: &str) -> u32 {
+        let handle = self.assets.load::<AudioBuffer>(path);
+        let id = handle.id();
+        self.sounds.insert(id, handle);
+        id
+    }
+    
+    pub fn release_sound(&mut self, sound_id: u32) -> bool {
+        self.sounds.remove(&sound_id).is_some()
+    }
+    
+    // Returns false for unknown ids; sounds still loading play nothing
+    pub fn play_sound(&mut self, sound_id: u32, volume: f32) -> Result<bool, JsValue> {
+        let Some(handle) = self.sounds.get(&sound_id) else {
+            return Ok(false);
+        };
+        self.audio_manager.play_sound_with_volume(&self.assets, handle, volume)?;
+        Ok(true)
+    }
+    
+    pub fn play_music(&mut self, sound_id: u32) -> Result<bool, JsValue> {
+        let Some(handle) = self.sounds.get(&sound_id) else {
+            return Ok(false);
+        };
+        self.audio_manager.play_music(&self.assets, handle)?;
+        Ok(true)
+    }
+    
+    // Packs images into an atlas texture and returns its id, as a texture id.
+    // `sizes` holds each image's width and height in turn, and `pixels` their
+    // RGBA pixels one after another.
+    pub fn build_atlas(
+        &mut self,
+        names: &[String],
+        sizes: &[u32],
+        pixels: &[u8],
+        padding: u32,
+        extrusion: u32,
+    ) -> Result<u32, JsValue> {
+        if sizes.len() != names.len() * 2 {
+            return Err(JsValue::from_str("expected a width and height per image"));
+        }
+        let max_size = self.renderer.gl()
+            .get_parameter(WebGl2RenderingContext::MAX_TEXTURE_SIZE)?
+            .as_f64()
+            .map_or(4096, |size| size as u32);
+        let mut builder = AtlasBuilder::new()
+            .with_padding(padding)
+            .with_extrusion(extrusion)
+            .with_max_size(max_size);
+        
+        let mut offset = 0usize;
+        for (name, size) in names.iter().zip(sizes.chunks_exact(2)) {
+            let missing = || JsValue::from_str(&format!("pixels for `{}` are missing", name));
+            let end = (size[0] as usize)
+                .checked_mul(size[1] as usize)
+                .and_then(|area| area.checked_mul(4))
+                .and_then(|len| offset.checked_add(len))
+                .ok_or_else(missing)?;
+            let image = pixels.get(offset..end).ok_or_else(missing)?;
+            builder.add(name, image.to_vec(), size[0], size[1]).map_err(|error| JsValue::from_str(&error.to_string()))?;
+            offset = end;
+        }
+        
+        let atlas = builder.build().map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let handle = self.assets.add(atlas.to_texture(self.renderer.gl())?);
+        let id = handle.id();
+        self.textures.insert(id, handle);
+        self.atlases.insert(id, atlas.layout);
+        Ok(id)
+    }
+    
+    // Loads an atlas made ahead of time, e.g. by the `pack-atlas` tool, from
+    // its image and layout JSON. Returns its id, as a texture id.
+    pub fn load_atlas(&mut self, image_path: &str, layout: &str) -> Result<u32, JsValue> {
+        let layout = AtlasLayout::from_json(layout).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let id = self.load_texture(image_path);
+        self.atlases.insert(id, layout);
+        Ok(id)
+    }
+    
+    pub fn atlas_regions(&self, atlas_id: u32) -> Vec<String> {
+        self.atlases
+            .get(&atlas_id)
+            .map(|layout| layout.regions.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+    
+    // Draws the sprite with a named region of an atlas
+    pub fn set_sprite_region(&mut self, sprite_id: u32, atlas_id: u32, region: &str) -> Result<(), JsValue> {
+        let region = *self.atlases
+            .get(&atlas_id)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown atlas {}", atlas_id)))?
+            .region(region)
+            .ok_or_else(|| JsValue::from_str(&format!("atlas {} has no region `{}`", atlas_id, region)))?;
+        let (world, entity) = self.sprite_entity_mut(sprite_id).ok_or_else(|| unknown_sprite(sprite_id))?;
+        let sprite = world
+            .get_component_mut::<Sprite>(entity)
+            .ok_or_else(|| unknown_sprite(sprite_id))?;
+        region.apply_to(sprite, atlas_id);
+        Ok(())
+    }
+    
+    // "loading", "loaded" or "failed: <reason>"
+    pub fn texture_state(&self, texture_id: u32) -> Option<String> {
+        let handle = self.textures.get(&texture_id)?;
+        Some(match self.assets.load_state(handle) {
+            LoadState::Loading => "loading".to_string(),
+            LoadState::Loaded => "loaded".to_string(),
+            LoadState::Failed(error) => format!("failed: {}", error),
+        })
+    }
+    
+    // From 0 to 1 over the assets currently loading
+    pub fn load_progress(&self) -> f32 {
+        self.assets.progress().fraction()
+    }
+    
+    // Reads the assets loaded from `path` again and swaps them in place, for
+    // hot reloading. Returns how many reloads were started.
+    pub fn reload_asset(&mut self, path: &str) -> usize {
+        self.assets.reload(path)
+    }
+    
+    pub fn asset_paths(&self) -> Vec<String> {
+        self.assets.paths()
+    }
+    
+    // Serves assets from a packed archive from now on, fetching any it
+    // doesn't hold. Returns how many it holds.
+    pub fn use_asset_bundle(&mut self, bytes: Vec<u8>) -> Result<usize, JsValue> {
+        let archive = Archive::from_bytes(bytes).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let count = archive.len();
+        self.assets.set_io(ArchiveIo::new(archive).with_fallback(FetchIo::new("")));
+        Ok(count)
     }
     
     pub fn get_canvas_size(&self) -> (u32, u32) {
         (self.canvas_width, self.canvas_height)
     }
 }
+
+fn unknown_sprite(sprite_id: u32) -> JsValue {
+    JsValue::from_str(&format!("unknown sprite {}", sprite_id))
+}
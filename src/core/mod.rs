@@ -1,7 +1,9 @@
 pub mod engine;
 pub mod ecs;
+pub mod task_pool;
 pub mod time;
 
 pub use engine::GameEngine;
-pub use ecs::{Entity, Component, System, World};
+pub use ecs::{Entity, Component, Ref, RefMut, Stage, System, SystemDescriptor, World};
+pub use task_pool::TaskPool;
 pub use time::TimeManager;
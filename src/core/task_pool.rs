@@ -0,0 +1,314 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct Shared {
+    queue: Mutex<VecDeque<Job>>,
+    job_ready: Condvar,
+    workers: AtomicUsize,
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Job>> {
+        // Jobs never run while the lock is held, so it can't be poisoned by them
+        self.queue.lock().unwrap_or_else(|error| error.into_inner())
+    }
+    
+    // `push` and `try_pop` never block: the caller may be a browser's main
+    // thread, where waiting on a lock traps. Workers only hold the lock for
+    // a moment, so spinning is cheap.
+    fn push(&self, job: Job) {
+        loop {
+            if let Ok(mut queue) = self.queue.try_lock() {
+                queue.push_back(job);
+                break;
+            }
+            std::hint::spin_loop();
+        }
+        self.job_ready.notify_one();
+    }
+    
+    fn try_pop(&self) -> Option<Job> {
+        self.queue.try_lock().ok()?.pop_front()
+    }
+    
+    fn next_job(&self) -> Option<Job> {
+        let mut queue = self.lock();
+        loop {
+            if self.shutdown.load(Ordering::Acquire) {
+                return None;
+            }
+            if let Some(job) = queue.pop_front() {
+                return Some(job);
+            }
+            queue = self.job_ready.wait(queue).unwrap_or_else(|error| error.into_inner());
+        }
+    }
+}
+
+// Worker threads for running systems in parallel. Natively the pool spawns
+// its own threads. On wasm, threads have to be web workers sharing the
+// module's memory (built with the `atomics` target feature); each one joins
+// by calling `run_worker`. With no workers, every job runs inline on the
+// calling thread, so everything degrades to serial execution.
+pub struct TaskPool {
+    shared: Arc<Shared>,
+    threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl TaskPool {
+    // A pool without workers of its own
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::new()),
+                job_ready: Condvar::new(),
+                workers: AtomicUsize::new(0),
+                shutdown: AtomicBool::new(false),
+            }),
+            threads: Vec::new(),
+        }
+    }
+    
+    // A pool with `count` worker threads besides the ones calling `scope`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_threads(count: usize) -> Self {
+        let mut pool = Self::new();
+        
+        // Counted up front so jobs spawned before the threads get going are
+        // still queued for them rather than run inline
+        pool.shared.workers.store(count, Ordering::Release);
+        for index in 0..count {
+            let shared = pool.shared.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("task-pool-{}", index))
+                .spawn(move || Self::work(&shared))
+                .expect("failed to spawn task pool thread");
+            pool.threads.push(thread);
+        }
+        pool
+    }
+    
+    // The pool worlds use unless given another one. Natively it keeps one
+    // thread per core besides the main thread; on wasm it starts empty.
+    pub fn global() -> Arc<TaskPool> {
+        static GLOBAL: OnceLock<Arc<TaskPool>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                #[cfg(not(target_arch = "wasm32"))]
+                let pool = {
+                    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+                    TaskPool::with_threads(cores - 1)
+                };
+                #[cfg(target_arch = "wasm32")]
+                let pool = TaskPool::new();
+                Arc::new(pool)
+            })
+            .clone()
+    }
+    
+    // Number of threads currently taking jobs
+    pub fn worker_count(&self) -> usize {
+        self.shared.workers.load(Ordering::Acquire)
+    }
+    
+    // Turns the calling thread into a worker until the pool is dropped. It
+    // blocks while idle, so never call it from a browser's main thread.
+    pub fn run_worker(&self) {
+        self.shared.workers.fetch_add(1, Ordering::AcqRel);
+        Self::work(&self.shared);
+    }
+    
+    // Runs jobs until shutdown, then signs off as a worker
+    fn work(shared: &Shared) {
+        while let Some(job) = shared.next_job() {
+            job();
+        }
+        shared.workers.fetch_sub(1, Ordering::AcqRel);
+    }
+    
+    // Runs `f`, which may spawn jobs borrowing anything that outlives this
+    // call, and returns once every job has finished. The calling thread runs
+    // queued jobs itself while it waits. A panicking job is re-raised here.
+    pub fn scope<'scope, R>(&self, f: impl FnOnce(&Scope<'scope>) -> R) -> R {
+        let scope = Scope {
+            shared: self.shared.clone(),
+            state: Arc::new(ScopeState::default()),
+            _marker: PhantomData,
+        };
+        
+        // Waits even if `f` panics, since spawned jobs may still be using
+        // borrows that the unwinding would invalidate
+        let result = {
+            let _wait = WaitGuard(&scope);
+            f(&scope)
+        };
+        
+        let panic = scope.state.panic.lock().unwrap_or_else(|error| error.into_inner()).take();
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+}
+
+impl Default for TaskPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TaskPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        {
+            // Taking the lock orders the flag before any worker's next wait
+            let _queue = self.shared.lock();
+            self.shared.job_ready.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScopeState {
+    pending: AtomicUsize,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+// Handed to the closure given to `TaskPool::scope` for spawning jobs
+pub struct Scope<'scope> {
+    shared: Arc<Shared>,
+    state: Arc<ScopeState>,
+    // Invariant, so jobs can't be given a shorter lifetime than the scope's
+    _marker: PhantomData<&'scope mut &'scope ()>,
+}
+
+impl<'scope> Scope<'scope> {
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'scope) {
+        if self.shared.workers.load(Ordering::Acquire) == 0 {
+            job();
+            return;
+        }
+        
+        let state = self.state.clone();
+        state.pending.fetch_add(1, Ordering::AcqRel);
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(job)) {
+                state.panic.lock().unwrap_or_else(|error| error.into_inner()).get_or_insert(payload);
+            }
+            state.pending.fetch_sub(1, Ordering::AcqRel);
+        });
+        
+        // Safety: `TaskPool::scope` doesn't return (or unwind) before the job
+        // has run, so nothing it borrows for 'scope is gone by then
+        let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        self.shared.push(job);
+    }
+    
+    fn wait(&self) {
+        while self.state.pending.load(Ordering::Acquire) > 0 {
+            match self.shared.try_pop() {
+                Some(job) => job(),
+                None => {
+                    std::hint::spin_loop();
+                    std::thread::yield_now();
+                }
+            }
+        }
+    }
+}
+
+struct WaitGuard<'a, 'scope>(&'a Scope<'scope>);
+
+impl Drop for WaitGuard<'_, '_> {
+    fn drop(&mut self) {
+        self.0.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn runs_every_job_before_returning() {
+        let pool = TaskPool::with_threads(3);
+        let mut slots = vec![0usize; 64];
+        let total = AtomicUsize::new(0);
+        pool.scope(|scope| {
+            for (index, slot) in slots.iter_mut().enumerate() {
+                let total = &total;
+                scope.spawn(move || {
+                    *slot = index * 2;
+                    total.fetch_add(index, Ordering::Relaxed);
+                });
+            }
+        });
+        
+        assert!(slots.iter().enumerate().all(|(index, &slot)| slot == index * 2));
+        assert_eq!(total.load(Ordering::Relaxed), (0..64).sum::<usize>());
+    }
+    
+    #[test]
+    fn runs_jobs_inline_without_workers() {
+        let pool = TaskPool::new();
+        assert_eq!(pool.worker_count(), 0);
+        let caller = std::thread::current().id();
+        let mut ran_on = None;
+        pool.scope(|scope| scope.spawn(|| ran_on = Some(std::thread::current().id())));
+        assert_eq!(ran_on, Some(caller));
+    }
+    
+    #[test]
+    fn returns_the_closures_result() {
+        let pool = TaskPool::with_threads(1);
+        assert_eq!(pool.scope(|_| 42), 42);
+    }
+    
+    #[test]
+    #[should_panic(expected = "job failed")]
+    fn reraises_job_panics() {
+        let pool = TaskPool::with_threads(2);
+        pool.scope(|scope| {
+            scope.spawn(|| panic!("job failed"));
+            scope.spawn(|| {});
+        });
+    }
+    
+    #[test]
+    fn waits_for_jobs_when_the_closure_panics() {
+        let pool = TaskPool::with_threads(2);
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                for _ in 0..8 {
+                    scope.spawn(|| {
+                        std::thread::sleep(std::time::Duration::from_millis(5));
+                        finished.fetch_add(1, Ordering::Relaxed);
+                    });
+                }
+                panic!("scope failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(finished.load(Ordering::Relaxed), 8);
+    }
+    
+    #[test]
+    fn dropping_the_pool_stops_its_threads() {
+        let pool = TaskPool::with_threads(2);
+        pool.scope(|scope| scope.spawn(|| {}));
+        let shared = pool.shared.clone();
+        drop(pool);
+        assert_eq!(shared.workers.load(Ordering::Acquire), 0);
+    }
+}
@@ -1,8 +1,8 @@
 use web_sys::Performance;
 
+#[derive(Clone)]
 pub struct TimeManager {
     performance: Option<Performance>,
-    last_time: f64,
     delta_time: f64,
     fps: f64,
     frame_count: u32,
@@ -14,14 +14,8 @@ impl TimeManager {
         let performance = web_sys::window()
             .and_then(|window| window.performance());
         
-        let current_time = performance
-            .as_ref()
-            .map(|p| p.now())
-            .unwrap_or(0.0);
-        
         Self {
             performance,
-            last_time: current_time,
             delta_time: 0.0,
             fps: 60.0,
             frame_count: 0,
@@ -58,3 +52,9 @@ impl TimeManager {
             .unwrap_or(0.0)
     }
 }
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGl2RenderingContext;
+
+use crate::core::ecs::Sprite;
+use crate::graphics::png::encode_png;
+use crate::graphics::texture::Texture;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AtlasError {
+    DuplicateName(String),
+    // The pixels given don't match the image's size
+    ImageSize { name: String, expected: usize, actual: usize },
+    TooLarge { max_size: u32 },
+    InvalidLayout(String),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::DuplicateName(name) => write!(f, "more than one image is named `{}`", name),
+            AtlasError::ImageSize { name, expected, actual } => {
+                write!(f, "image `{}` should have {} bytes of pixels, not {}", name, expected, actual)
+            }
+            AtlasError::TooLarge { max_size } => {
+                write!(f, "the images don't fit in a {}x{} atlas", max_size, max_size)
+            }
+            AtlasError::InvalidLayout(message) => write!(f, "invalid atlas layout: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+// Where an image ended up in its atlas, in pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRegion {
+    // Points `sprite` at this region of the atlas texture `texture_id`,
+    // keeping its size
+    pub fn apply_to(&self, sprite: &mut Sprite, texture_id: u32) {
+        *sprite = sprite.with_texture(texture_id).with_source_rect(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+        );
+    }
+}
+
+// An atlas's size and named regions, saved as JSON next to its image:
+//
+//     {"image": "sprites.png", "width": 256, "height": 128,
+//      "regions": {"player/idle": {"x": 1, "y": 1, "width": 32, "height": 32}}}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtlasLayout {
+    // The image file, relative to the layout
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub image: String,
+    pub width: u32,
+    pub height: u32,
+    pub regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl AtlasLayout {
+    pub fn from_json(json: &str) -> Result<Self, AtlasError> {
+        let layout: Self = serde_json::from_str(json).map_err(|error| AtlasError::InvalidLayout(error.to_string()))?;
+        // Layouts come from files, so a region's far edge may not fit a u32
+        if let Some((name, _)) = layout.regions.iter().find(|(_, region)| {
+            region.x.checked_add(region.width).is_none_or(|right| right > layout.width)
+                || region.y.checked_add(region.height).is_none_or(|bottom| bottom > layout.height)
+        }) {
+            return Err(AtlasError::InvalidLayout(format!("region `{}` is outside the atlas", name)));
+        }
+        Ok(layout)
+    }
+    
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("layouts always serialize")
+    }
+    
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+}
+
+// A packed atlas: RGBA pixels, rows top to bottom, and where each image is
+pub struct Atlas {
+    pub layout: AtlasLayout,
+    pub pixels: Vec<u8>,
+}
+
+impl Atlas {
+    pub fn to_texture(&self, gl: &WebGl2RenderingContext) -> Result<Texture, JsValue> {
+        Texture::from_data(gl, &self.pixels, self.layout.width, self.layout.height)
+    }
+    
+    pub fn encode_png(&self) -> Vec<u8> {
+        encode_png(self.layout.width, self.layout.height, &self.pixels).expect("atlas pixels match its size")
+    }
+}
+
+struct AtlasImage {
+    name: String,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+// Packs images into one texture so sprites using them can be drawn in one
+// batch. Padding leaves transparent pixels between images; extrusion repeats
+// each image's edge pixels around it. Both keep neighbors from bleeding into
+// a sprite when the atlas is filtered or scaled.
+pub struct AtlasBuilder {
+    images: Vec<AtlasImage>,
+    padding: u32,
+    extrusion: u32,
+    max_size: u32,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            padding: 1,
+            extrusion: 0,
+            max_size: 4096,
+        }
+    }
+    
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+    
+    pub fn with_extrusion(mut self, extrusion: u32) -> Self {
+        self.extrusion = extrusion;
+        self
+    }
+    
+    // The largest width and height to try, e.g. the GPU's maximum texture
+    // size
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    
+    // `pixels` are RGBA, rows top to bottom, as for `Texture::from_data`
+    pub fn add(&mut self, name: &str, pixels: Vec<u8>, width: u32, height: u32) -> Result<(), AtlasError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(AtlasError::ImageSize {
+                name: name.to_string(),
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        if self.images.iter().any(|image| image.name == name) {
+            return Err(AtlasError::DuplicateName(name.to_string()));
+        }
+        self.images.push(AtlasImage {
+            name: name.to_string(),
+            width,
+            height,
+            pixels,
+        });
+        Ok(())
+    }
+    
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+    
+    pub fn build(&self) -> Result<Atlas, AtlasError> {
+        // Each image takes a cell of itself, its extrusion on every side and
+        // the padding to its right and below; the atlas's top and left edges
+        // get their padding as a margin
+        let too_large = AtlasError::TooLarge { max_size: self.max_size };
+        let border = self.extrusion
+            .checked_mul(2)
+            .and_then(|extrusion| extrusion.checked_add(self.padding))
+            .ok_or_else(|| too_large.clone())?;
+        let cells: Vec<(u32, u32)> = self
+            .images
+            .iter()
+            .map(|image| Some((image.width.checked_add(border)?, image.height.checked_add(border)?)))
+            .collect::<Option<_>>()
+            .ok_or_else(|| too_large.clone())?;
+        let RectPacking { width, height, positions } =
+            pack_rects(&cells, self.padding, self.max_size).ok_or(too_large)?;
+        
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let mut regions = BTreeMap::new();
+        for (image, &(cell_x, cell_y)) in self.images.iter().zip(&positions) {
+            let extruded_width = image.width + self.extrusion * 2;
+            let extruded_height = image.height + self.extrusion * 2;
+            // Every pixel of the extruded image takes the nearest image pixel
+            let rows = if image.width == 0 || image.height == 0 { 0 } else { extruded_height };
+            for row in 0..rows {
+                let source_y = row.saturating_sub(self.extrusion).min(image.height - 1);
+                for column in 0..extruded_width {
+                    let source_x = column.saturating_sub(self.extrusion).min(image.width - 1);
+                    let source = (source_y as usize * image.width as usize + source_x as usize) * 4;
+                    let target = ((cell_y + row) as usize * width as usize + (cell_x + column) as usize) * 4;
+                    pixels[target..target + 4].copy_from_slice(&image.pixels[source..source + 4]);
+                }
+            }
+            regions.insert(
+                image.name.clone(),
+                AtlasRegion {
+                    x: cell_x + self.extrusion,
+                    y: cell_y + self.extrusion,
+                    width: image.width,
+                    height: image.height,
+                },
+            );
+        }
+        
+        Ok(Atlas {
+            layout: AtlasLayout {
+                image: String::new(),
+                width,
+                height,
+                regions,
+            },
+            pixels,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RectPacking {
+    pub width: u32,
+    pub height: u32,
+    // Each rectangle's top-left corner, in the order given
+    pub positions: Vec<(u32, u32)>,
+}
+
+// Packs rectangles of the given sizes without overlap into the smallest
+// power-of-two area it finds, no side longer than `max_size`, keeping them
+// `margin` away from the top and left edges
+pub fn pack_rects(sizes: &[(u32, u32)], margin: u32, max_size: u32) -> Option<RectPacking> {
+    let widest = sizes.iter().map(|&(width, _)| width).max().unwrap_or(1).max(1);
+    let tallest = sizes.iter().map(|&(_, height)| height).max().unwrap_or(1).max(1);
+    let area: u64 = sizes.iter().map(|&(width, height)| width as u64 * height as u64).sum();
+    
+    // Start from the smallest square-ish size that could hold everything and
+    // grow the shorter side until it all fits, giving up past `max_size`
+    let mut width = widest.checked_add(margin)?.checked_next_power_of_two()?;
+    let mut height = tallest.checked_add(margin)?.checked_next_power_of_two()?;
+    while ((width - margin) as u64 * (height - margin) as u64) < area {
+        (width, height) = grow(width, height, max_size)?;
+    }
+    while width <= max_size && height <= max_size {
+        if let Some(positions) = pack_skyline(sizes, width - margin, height - margin) {
+            let positions = positions.into_iter().map(|(x, y)| (x + margin, y + margin)).collect();
+            return Some(RectPacking { width, height, positions });
+        }
+        (width, height) = grow(width, height, max_size)?;
+    }
+    None
+}
+
+// Doubles the shorter side, unless that passes `max_size`
+fn grow(width: u32, height: u32, max_size: u32) -> Option<(u32, u32)> {
+    let (width, height) = if width <= height {
+        (width.checked_mul(2)?, height)
+    } else {
+        (width, height.checked_mul(2)?)
+    };
+    (width <= max_size && height <= max_size).then_some((width, height))
+}
+
+// Bottom-left skyline packing, tallest rectangles first. The skyline is the
+// top edge of what's been placed so far, as (x, y, width) segments left to
+// right.
+fn pack_skyline(sizes: &[(u32, u32)], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse((sizes[index].1, sizes[index].0)));
+    
+    let mut skyline = vec![(0u32, 0u32, width)];
+    let mut positions = vec![(0, 0); sizes.len()];
+    for index in order {
+        let (rect_width, rect_height) = sizes[index];
+        if rect_width == 0 || rect_height == 0 {
+            continue;
+        }
+        
+        // Lowest top edge wins, then the leftmost
+        let mut best: Option<(usize, u32, u32)> = None;
+        for start in 0..skyline.len() {
+            let x = skyline[start].0;
+            if x as u64 + rect_width as u64 > width as u64 {
+                break;
+            }
+            let mut y = 0;
+            let mut covered = 0;
+            for &(_, segment_y, segment_width) in &skyline[start..] {
+                y = y.max(segment_y);
+                covered += segment_width;
+                if covered >= rect_width {
+                    break;
+                }
+            }
+            if y as u64 + rect_height as u64 <= height as u64 && best.is_none_or(|(_, _, best_y)| y < best_y) {
+                best = Some((start, x, y));
+            }
+        }
+        let (start, x, y) = best?;
+        positions[index] = (x, y);
+        
+        // Raise the skyline under the new rectangle
+        let right = x + rect_width;
+        let mut end = start;
+        while end < skyline.len() && skyline[end].0 + skyline[end].2 <= right {
+            end += 1;
+        }
+        if end < skyline.len() && skyline[end].0 < right {
+            let segment = &mut skyline[end];
+            segment.2 -= right - segment.0;
+            segment.0 = right;
+        }
+        skyline.splice(start..end, [(x, y + rect_height, rect_width)]);
+        
+        // Merge neighbors at the same height
+        let mut merged: Vec<(u32, u32, u32)> = Vec::with_capacity(skyline.len());
+        for segment in skyline.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.1 == segment.1 => last.2 += segment.2,
+                _ => merged.push(segment),
+            }
+        }
+        skyline = merged;
+    }
+    Some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn layouts_round_trip_through_json() {
+        let json = r#"{"image": "sprites.png", "width": 64, "height": 32,
+            "regions": {"player/idle": {"x": 1, "y": 1, "width": 32, "height": 30}}}"#;
+        let layout = AtlasLayout::from_json(json).unwrap();
+        assert_eq!(layout.image, "sprites.png");
+        assert_eq!(layout.region("player/idle"), Some(&AtlasRegion { x: 1, y: 1, width: 32, height: 30 }));
+        assert_eq!(AtlasLayout::from_json(&layout.to_json()).unwrap(), layout);
+    }
+    
+    #[test]
+    fn layouts_with_regions_outside_are_invalid() {
+        for region in [
+            r#"{"x": 40, "y": 0, "width": 32, "height": 32}"#,
+            r#"{"x": 0, "y": 1, "width": 64, "height": 32}"#,
+            // Far edges past u32::MAX
+            r#"{"x": 4294967295, "y": 0, "width": 2, "height": 1}"#,
+            r#"{"x": 0, "y": 4294967290, "width": 1, "height": 4294967290}"#,
+        ] {
+            let json = format!(r#"{{"width": 64, "height": 32, "regions": {{"bad": {}}}}}"#, region);
+            assert_eq!(
+                AtlasLayout::from_json(&json),
+                Err(AtlasError::InvalidLayout("region `bad` is outside the atlas".to_string())),
+                "{}",
+                region
+            );
+        }
+    }
+    
+    // Deterministic sizes from 1 to `max` on each side
+    fn sizes(count: usize, max: u32, seed: u32) -> Vec<(u32, u32)> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % max + 1
+        };
+        (0..count).map(|_| (next(), next())).collect()
+    }
+    
+    fn assert_packed(sizes: &[(u32, u32)], margin: u32, packing: &RectPacking) {
+        assert!(packing.width.is_power_of_two() && packing.height.is_power_of_two());
+        let rects: Vec<(u32, u32, u32, u32)> = sizes
+            .iter()
+            .zip(&packing.positions)
+            .map(|(&(width, height), &(x, y))| (x, y, width, height))
+            .collect();
+        for (index, &(x, y, width, height)) in rects.iter().enumerate() {
+            assert!(x >= margin && y >= margin, "rect {} is inside the margin", index);
+            assert!(x + width <= packing.width && y + height <= packing.height, "rect {} is out of bounds", index);
+            for &(other_x, other_y, other_width, other_height) in &rects[index + 1..] {
+                let apart = x + width <= other_x || other_x + other_width <= x
+                    || y + height <= other_y || other_y + other_height <= y;
+                assert!(apart, "rect {} overlaps another", index);
+            }
+        }
+    }
+    
+    #[test]
+    fn packs_without_overlap() {
+        for seed in 1..50 {
+            for (count, max) in [(1, 64), (20, 16), (60, 40), (150, 8)] {
+                let sizes = sizes(count, max, seed);
+                for margin in [0, 1, 3] {
+                    let packing = pack_rects(&sizes, margin, 4096).unwrap();
+                    assert_packed(&sizes, margin, &packing);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn packs_tightly_enough() {
+        // 64 16x16 squares fill 128x128 exactly
+        let packing = pack_rects(&[(16, 16); 64], 0, 4096).unwrap();
+        assert_eq!((packing.width, packing.height), (128, 128));
+        
+        let packing = pack_rects(&[], 1, 4096).unwrap();
+        assert!(packing.positions.is_empty());
+    }
+    
+    #[test]
+    fn gives_up_past_the_max_size() {
+        assert!(pack_rects(&[(300, 10)], 0, 256).is_none());
+        assert!(pack_rects(&[(200, 200); 2], 0, 256).is_none());
+        assert!(pack_rects(&[(256, 256)], 1, 256).is_none());
+        assert!(pack_rects(&[(256, 256)], 0, 256).is_some());
+        // No overflow when the sizes themselves are huge
+        assert!(pack_rects(&[(u32::MAX, 1)], 1, u32::MAX).is_none());
+        assert!(pack_rects(&[(1 << 31, 1 << 31); 2], 0, u32::MAX).is_none());
+    }
+    
+    fn solid(width: u32, height: u32, value: u8) -> Vec<u8> {
+        let mut pixels = vec![value; width as usize * height as usize * 4];
+        // Mark the top-left pixel so extrusion can be told from the image
+        if !pixels.is_empty() {
+            pixels[..4].copy_from_slice(&[value, 0, 0, 255]);
+        }
+        pixels
+    }
+    
+    fn pixel(atlas: &Atlas, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * atlas.layout.width as usize + x as usize) * 4;
+        atlas.pixels[index..index + 4].try_into().unwrap()
+    }
+    
+    #[test]
+    fn builds_padded_and_extruded_regions() {
+        for (padding, extrusion) in [(0, 0), (1, 0), (2, 1), (0, 3)] {
+            let mut builder = AtlasBuilder::new().with_padding(padding).with_extrusion(extrusion);
+            for (index, &(width, height)) in sizes(30, 20, 7).iter().enumerate() {
+                builder.add(&format!("image{}", index), solid(width, height, index as u8 + 1), width, height).unwrap();
+            }
+            let atlas = builder.build().unwrap();
+            assert_eq!(atlas.pixels.len(), atlas.layout.width as usize * atlas.layout.height as usize * 4);
+            
+            // Regions plus their extrusion and padding never overlap
+            let cells: Vec<(u32, u32, u32, u32)> = atlas.layout.regions.values().map(|region| {
+                let border = extrusion * 2 + padding;
+                (region.x - extrusion, region.y - extrusion, region.width + border, region.height + border)
+            }).collect();
+            let packing = RectPacking {
+                width: atlas.layout.width,
+                height: atlas.layout.height,
+                positions: cells.iter().map(|&(x, y, _, _)| (x, y)).collect(),
+            };
+            let cell_sizes: Vec<(u32, u32)> = cells.iter().map(|&(_, _, width, height)| (width, height)).collect();
+            assert_packed(&cell_sizes, padding, &packing);
+            
+            for (name, region) in &atlas.layout.regions {
+                let value = name.trim_start_matches("image").parse::<u8>().unwrap() + 1;
+                assert_eq!(pixel(&atlas, region.x, region.y), [value, 0, 0, 255]);
+                assert_eq!(pixel(&atlas, region.x + region.width - 1, region.y + region.height - 1), [value; 4]);
+                if extrusion > 0 {
+                    // The corner extrudes diagonally, the edges straight out
+                    assert_eq!(pixel(&atlas, region.x - extrusion, region.y - extrusion), [value, 0, 0, 255]);
+                    let right = region.x + region.width - 1 + extrusion;
+                    assert_eq!(pixel(&atlas, right, region.y + region.height - 1), [value; 4]);
+                }
+                if padding > 0 {
+                    // Padding to the right and below stays transparent
+                    let right = region.x + region.width + extrusion;
+                    let bottom = region.y + region.height + extrusion;
+                    assert_eq!(pixel(&atlas, right, region.y)[3], 0);
+                    assert_eq!(pixel(&atlas, region.x, bottom)[3], 0);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn builder_rejects_bad_images() {
+        let mut builder = AtlasBuilder::new();
+        assert_eq!(
+            builder.add("short", vec![0; 15], 2, 2),
+            Err(AtlasError::ImageSize { name: "short".to_string(), expected: 16, actual: 15 })
+        );
+        builder.add("a", solid(2, 2, 1), 2, 2).unwrap();
+        assert_eq!(builder.add("a", solid(1, 1, 1), 1, 1), Err(AtlasError::DuplicateName("a".to_string())));
+        // Empty images get an empty region
+        builder.add("empty", Vec::new(), 0, 0).unwrap();
+        let atlas = builder.build().unwrap();
+        assert_eq!(atlas.layout.region("empty").map(|region| (region.width, region.height)), Some((0, 0)));
+    }
+    
+    #[test]
+    fn builder_errors_when_the_images_dont_fit() {
+        let mut builder = AtlasBuilder::new().with_max_size(64);
+        for index in 0..5 {
+            builder.add(&index.to_string(), solid(30, 30, 1), 30, 30).unwrap();
+        }
+        assert_eq!(builder.build().err(), Some(AtlasError::TooLarge { max_size: 64 }));
+        
+        let mut builder = AtlasBuilder::new().with_extrusion(u32::MAX);
+        builder.add("a", solid(1, 1, 1), 1, 1).unwrap();
+        assert_eq!(builder.build().err(), Some(AtlasError::TooLarge { max_size: 4096 }));
+    }
+    
+    #[test]
+    fn atlases_encode_as_png() {
+        let mut builder = AtlasBuilder::new().with_extrusion(1);
+        builder.add("a", solid(5, 3, 9), 5, 3).unwrap();
+        builder.add("b", solid(2, 7, 4), 2, 7).unwrap();
+        let atlas = builder.build().unwrap();
+        let (width, height, pixels) = crate::graphics::png::decode_png(&atlas.encode_png()).unwrap();
+        assert_eq!((width, height), (atlas.layout.width, atlas.layout.height));
+        assert_eq!(pixels, atlas.pixels);
+    }
+}
@@ -1,9 +1,17 @@
+pub mod atlas;
+pub mod png;
 pub mod renderer;
 pub mod shader;
+pub mod sprite_batch;
 pub mod texture;
 pub mod camera;
+pub mod tilemap;
 
+pub use atlas::{pack_rects, Atlas, AtlasBuilder, AtlasError, AtlasLayout, AtlasRegion, RectPacking};
+pub use png::{decode_png, encode_png};
 pub use renderer::Renderer;
 pub use shader::{Shader, ShaderProgram};
+pub use sprite_batch::{BlendMode, RenderStats, SpriteBatch};
 pub use texture::Texture;
 pub use camera::Camera;
+pub use tilemap::{Tilemap, Tileset};
@@ -0,0 +1,138 @@
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+// PNG images as 8-bit RGBA pixels, for building atlases without a browser.
+// Any PNG decodes: palettes and transparency keys expand to RGBA, and 16-bit
+// samples are cut down to 8 bits.
+
+// Returns the width, height and RGBA pixels, rows top to bottom
+pub fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut decoder = Decoder::new(bytes);
+    decoder.set_transformations(Transformations::normalize_to_color8() | Transformations::ALPHA);
+    let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut data).map_err(|error| error.to_string())?;
+    data.truncate(frame.buffer_size());
+    
+    let pixels = match frame.color_type {
+        ColorType::Rgba => data,
+        ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+        other => return Err(format!("unexpected {:?} pixels after expanding", other)),
+    };
+    Ok((frame.width, frame.height, pixels))
+}
+
+// `pixels` are 8-bit RGBA, rows top to bottom
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Result<Vec<u8>, String> {
+    let expected = (width as usize).checked_mul(height as usize).and_then(|area| area.checked_mul(4));
+    if expected != Some(pixels.len()) {
+        return Err(format!("expected {}x{} RGBA pixels, got {} bytes", width, height, pixels.len()));
+    }
+    
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|error| error.to_string())?;
+    writer.write_image_data(pixels).map_err(|error| error.to_string())?;
+    writer.finish().map_err(|error| error.to_string())?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [(x * 7) as u8, (y * 13) as u8, (x ^ y) as u8, (x + y) as u8]))
+            .collect()
+    }
+    
+    // A PNG in any format, `data` being its packed samples
+    fn png(width: u32, height: u32, depth: BitDepth, color: ColorType, palette: &[u8], transparency: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        if !palette.is_empty() {
+            encoder.set_palette(palette.to_vec());
+        }
+        if !transparency.is_empty() {
+            encoder.set_trns(transparency.to_vec());
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+    
+    #[test]
+    fn round_trips() {
+        for (width, height) in [(1, 1), (1, 9), (9, 1), (3, 5), (64, 64), (257, 3)] {
+            let pixels = gradient(width, height);
+            let encoded = encode_png(width, height, &pixels).unwrap();
+            assert_eq!(decode_png(&encoded).unwrap(), (width, height, pixels), "{}x{}", width, height);
+        }
+    }
+    
+    #[test]
+    fn encoding_the_wrong_number_of_pixels_is_an_error() {
+        assert!(encode_png(2, 2, &[0; 15]).is_err());
+        assert!(encode_png(2, 2, &[0; 17]).is_err());
+        assert!(encode_png(u32::MAX, u32::MAX, &[]).is_err());
+    }
+    
+    #[test]
+    fn decodes_other_formats_to_rgba() {
+        // 1-bit gray, one row of 10 pixels
+        let image = png(10, 1, BitDepth::One, ColorType::Grayscale, &[], &[], &[0b1010_0000, 0b0100_0000]);
+        let grays: Vec<u8> = decode_png(&image).unwrap().2.chunks_exact(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(grays, [255, 0, 255, 0, 0, 0, 0, 0, 0, 255]);
+        
+        // 2-bit palette with a transparent first entry
+        let palette = [10, 20, 30, 40, 50, 60, 70, 80, 90];
+        let image = png(3, 1, BitDepth::Two, ColorType::Indexed, &palette, &[0], &[0b0001_1000]);
+        assert_eq!(decode_png(&image).unwrap().2, [10, 20, 30, 0, 40, 50, 60, 255, 70, 80, 90, 255]);
+        
+        // 16-bit RGB with a transparent color key
+        let data = [0xff, 0xff, 0, 0, 0x80, 0x80, 0, 0, 0, 0, 0, 0];
+        let image = png(2, 1, BitDepth::Sixteen, ColorType::Rgb, &[], &[0xff, 0xff, 0, 0, 0x80, 0x80], &data);
+        assert_eq!(decode_png(&image).unwrap().2, [255, 0, 128, 0, 0, 0, 0, 255]);
+        
+        // Gray and alpha
+        let image = png(1, 2, BitDepth::Eight, ColorType::GrayscaleAlpha, &[], &[], &[100, 200, 110, 220]);
+        assert_eq!(decode_png(&image).unwrap().2, [100, 100, 100, 200, 110, 110, 110, 220]);
+    }
+    
+    #[test]
+    fn bad_signatures_are_an_error() {
+        let mut encoded = encode_png(1, 1, &[1, 2, 3, 4]).unwrap();
+        encoded[1] = b'J';
+        assert!(decode_png(&encoded).is_err());
+        assert!(decode_png(&[]).is_err());
+    }
+    
+    #[test]
+    fn corrupt_chunks_are_an_error() {
+        let mut encoded = encode_png(4, 4, &gradient(4, 4)).unwrap();
+        // A byte in the IDAT chunk, after the 33 bytes of signature and header
+        encoded[45] ^= 1;
+        assert!(decode_png(&encoded).is_err());
+    }
+    
+    #[test]
+    fn truncated_or_corrupt_input_never_panics() {
+        let encoded = encode_png(6, 5, &gradient(6, 5)).unwrap();
+        // Only the 12-byte IEND chunk may go missing
+        for len in 0..encoded.len() - 12 {
+            assert!(decode_png(&encoded[..len]).is_err(), "truncated to {}", len);
+        }
+        for index in 8..encoded.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut corrupt = encoded.clone();
+                corrupt[index] ^= flip;
+                let _ = decode_png(&corrupt);
+            }
+        }
+    }
+}
@@ -68,6 +68,10 @@ impl ShaderProgram {
         }
     }
     
+    pub fn program(&self) -> &WebGlProgram {
+        &self.program
+    }
+    
     pub fn use_program(&self, gl: &WebGl2RenderingContext) {
         gl.use_program(Some(&self.program));
     }
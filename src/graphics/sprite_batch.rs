@@ -0,0 +1,288 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlTexture, WebGlVertexArrayObject};
+
+use crate::core::ecs::{GlobalTransform, Sprite};
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::texture::Texture;
+use crate::math::mat4::Mat4;
+use crate::math::Vec2;
+
+// Sprites per draw call; 4 vertices each keeps indices within u16
+const MAX_SPRITES: usize = 4096;
+// Position (2), texture coordinates (2), color (4)
+const VERTEX_FLOATS: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Alpha,
+    Additive,
+    Multiply,
+}
+
+impl BlendMode {
+    fn apply(self, gl: &WebGl2RenderingContext) {
+        let (source, destination) = match self {
+            BlendMode::Alpha => (WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE),
+            BlendMode::Multiply => (WebGl2RenderingContext::DST_COLOR, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA),
+        };
+        gl.blend_func(source, destination);
+    }
+}
+
+// Counted since the frame was cleared
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub sprites: u32,
+}
+
+// What a run of sprites has to share to be drawn together
+#[derive(Clone, PartialEq)]
+struct BatchState {
+    program: WebGlProgram,
+    texture: WebGlTexture,
+    blend: BlendMode,
+}
+
+// Collects sprites into one vertex buffer and draws them together, flushing
+// when the texture, shader or blend mode changes or the buffer fills up.
+// Sprites are drawn in the order they're added.
+//
+// Custom shaders are `ShaderProgram` assets, picked by asset id with
+// `set_shader` and passed to `draw` by whoever holds the `AssetServer`, so a
+// reloaded shader is used from the next sprite on. They take the same inputs
+// as the built-in one: `aPosition`, `aTexCoord` and `aColor` at locations 0
+// to 2, and the `u_projection` and `u_texture` uniforms.
+pub struct SpriteBatch {
+    gl: WebGl2RenderingContext,
+    vao: WebGlVertexArrayObject,
+    vbo: WebGlBuffer,
+    ebo: WebGlBuffer,
+    default_shader: ShaderProgram,
+    // Stands in for a texture on untextured sprites
+    white_pixel: Texture,
+    vertices: Vec<f32>,
+    state: Option<BatchState>,
+    shader: Option<u32>,
+    blend: BlendMode,
+    projection: Mat4,
+    stats: RenderStats,
+}
+
+impl SpriteBatch {
+    pub fn new(gl: &WebGl2RenderingContext) -> Result<Self, JsValue> {
+        let default_shader = ShaderProgram::new(gl, VERTEX_SHADER_SOURCE, FRAGMENT_SHADER_SOURCE)?;
+        let white_pixel = Texture::create_white_pixel(gl)?;
+        
+        let vao = gl.create_vertex_array().ok_or("Failed to create VAO")?;
+        gl.bind_vertex_array(Some(&vao));
+        
+        // Sized once and refilled on every flush
+        let vbo = gl.create_buffer().ok_or("Failed to create VBO")?;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vbo));
+        gl.buffer_data_with_i32(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            (MAX_SPRITES * 4 * VERTEX_FLOATS * 4) as i32,
+            WebGl2RenderingContext::DYNAMIC_DRAW,
+        );
+        
+        // Every quad is two triangles over its 4 vertices, so the indices
+        // never change
+        let indices: Vec<u16> = (0..MAX_SPRITES as u16)
+            .flat_map(|sprite| {
+                let first = sprite * 4;
+                [first, first + 1, first + 2, first + 2, first + 3, first]
+            })
+            .collect();
+        let ebo = gl.create_buffer().ok_or("Failed to create EBO")?;
+        gl.bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, Some(&ebo));
+        unsafe {
+            let index_array = js_sys::Uint16Array::view(&indices);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                &index_array,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+        
+        let stride = (VERTEX_FLOATS * 4) as i32;
+        for (location, size, offset) in [(0, 2, 0), (1, 2, 2), (2, 4, 4)] {
+            gl.vertex_attrib_pointer_with_i32(location, size, WebGl2RenderingContext::FLOAT, false, stride, offset * 4);
+            gl.enable_vertex_attrib_array(location);
+        }
+        
+        gl.bind_vertex_array(None);
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+        gl.bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, None);
+        
+        Ok(Self {
+            gl: gl.clone(),
+            vao,
+            vbo,
+            ebo,
+            default_shader,
+            white_pixel,
+            vertices: Vec::with_capacity(MAX_SPRITES * 4 * VERTEX_FLOATS),
+            state: None,
+            shader: None,
+            blend: BlendMode::Alpha,
+            projection: Mat4::identity(),
+            stats: RenderStats::default(),
+        })
+    }
+    
+    // Starts a frame drawn with `projection`, resetting the stats
+    pub fn begin(&mut self, projection: Mat4) {
+        self.vertices.clear();
+        self.state = None;
+        self.projection = projection;
+        self.stats = RenderStats::default();
+    }
+    
+    // The asset id of the `ShaderProgram` for the sprites added after this,
+    // or `None` for the built-in shader
+    pub fn set_shader(&mut self, shader: Option<u32>) {
+        self.shader = shader;
+    }
+    
+    pub fn shader(&self) -> Option<u32> {
+        self.shader
+    }
+    
+    // For the sprites added after this
+    pub fn set_blend_mode(&mut self, blend: BlendMode) {
+        self.blend = blend;
+    }
+    
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend
+    }
+    
+    // `shader` is the one `set_shader` picked, or `None` for the built-in
+    // shader, e.g. while it's loading
+    pub fn draw(
+        &mut self,
+        transform: &GlobalTransform,
+        sprite: &Sprite,
+        texture: Option<&Texture>,
+        shader: Option<&ShaderProgram>,
+    ) {
+        let program = shader.unwrap_or(&self.default_shader).program();
+        let unchanged = self.state.as_ref().is_some_and(|state| {
+            state.program == *program
+                && state.blend == self.blend
+                && state.texture == *texture.unwrap_or(&self.white_pixel).id()
+        });
+        if !unchanged {
+            let program = program.clone();
+            self.flush();
+            self.state = Some(BatchState {
+                program,
+                texture: texture.unwrap_or(&self.white_pixel).id().clone(),
+                blend: self.blend,
+            });
+        } else if self.vertices.len() == MAX_SPRITES * 4 * VERTEX_FLOATS {
+            self.flush();
+        }
+        
+        // The unit quad stretched to the sprite's size, then placed in the
+        // world; y runs down the screen, as do texture coordinates here
+        let (left, top, right, bottom) = match texture {
+            Some(texture) => sprite.uv_rect(texture.width(), texture.height()),
+            None => (0.0, 0.0, 1.0, 1.0),
+        };
+        let color = [sprite.color_r, sprite.color_g, sprite.color_b, sprite.color_a];
+        for (x, y) in [(0.0, 1.0), (1.0, 1.0), (1.0, 0.0), (0.0, 0.0)] {
+            let position = transform.transform_point(Vec2::new(x * sprite.width, y * sprite.height));
+            let u = if x == 0.0 { left } else { right };
+            let v = if y == 0.0 { top } else { bottom };
+            self.vertices.extend_from_slice(&[position.x, position.y, u, v]);
+            self.vertices.extend_from_slice(&color);
+        }
+        self.stats.sprites += 1;
+    }
+    
+    // Draws the sprites added since the last flush
+    pub fn flush(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        if self.vertices.is_empty() {
+            return;
+        }
+        
+        let gl = &self.gl;
+        gl.use_program(Some(&state.program));
+        let projection = gl.get_uniform_location(&state.program, "u_projection");
+        gl.uniform_matrix4fv_with_f32_array(projection.as_ref(), false, self.projection.as_slice());
+        let texture = gl.get_uniform_location(&state.program, "u_texture");
+        gl.uniform1i(texture.as_ref(), 0);
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&state.texture));
+        state.blend.apply(gl);
+        
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.vbo));
+        unsafe {
+            let vertex_array = js_sys::Float32Array::view(&self.vertices);
+            gl.buffer_sub_data_with_i32_and_array_buffer_view(WebGl2RenderingContext::ARRAY_BUFFER, 0, &vertex_array);
+        }
+        let sprites = self.vertices.len() / (4 * VERTEX_FLOATS);
+        gl.draw_elements_with_i32(
+            WebGl2RenderingContext::TRIANGLES,
+            (sprites * 6) as i32,
+            WebGl2RenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+        gl.bind_vertex_array(None);
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+        
+        self.vertices.clear();
+        self.stats.draw_calls += 1;
+    }
+    
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        self.gl.delete_vertex_array(Some(&self.vao));
+        self.gl.delete_buffer(Some(&self.vbo));
+        self.gl.delete_buffer(Some(&self.ebo));
+    }
+}
+
+const VERTEX_SHADER_SOURCE: &str = r#"#version 300 es
+layout (location = 0) in vec2 aPosition;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
+
+uniform mat4 u_projection;
+
+out vec2 vTexCoord;
+out vec4 vColor;
+
+void main() {
+    gl_Position = u_projection * vec4(aPosition, 0.0, 1.0);
+    vTexCoord = aTexCoord;
+    vColor = aColor;
+}
+"#;
+
+const FRAGMENT_SHADER_SOURCE: &str = r#"#version 300 es
+precision mediump float;
+
+in vec2 vTexCoord;
+in vec4 vColor;
+uniform sampler2D u_texture;
+
+out vec4 fragColor;
+
+void main() {
+    fragColor = texture(u_texture, vTexCoord) * vColor;
+}
+"#;
@@ -1,7 +1,9 @@
 use wasm_bindgen::prelude::*;
 use web_sys::{WebGl2RenderingContext, WebGlTexture, HtmlImageElement};
 
+// Deleted from the GPU when dropped
 pub struct Texture {
+    gl: WebGl2RenderingContext,
     id: WebGlTexture,
     width: u32,
     height: u32,
@@ -13,6 +15,7 @@ impl Texture {
             .ok_or("Failed to create texture")?;
         
         Ok(Self {
+            gl: gl.clone(),
             id: texture,
             width: 0,
             height: 0,
@@ -22,7 +25,7 @@ impl Texture {
     pub fn from_image(gl: &WebGl2RenderingContext, image: &HtmlImageElement) -> Result<Self, JsValue> {
         let texture = gl.create_texture()
             .ok_or("Failed to create texture")?;
-            
+        
         gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
         
         // Set texture parameters
@@ -63,6 +66,7 @@ impl Texture {
         gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, None);
         
         Ok(Self {
+            gl: gl.clone(),
             id: texture,
             width,
             height,
@@ -72,7 +76,7 @@ impl Texture {
     pub fn from_data(gl: &WebGl2RenderingContext, data: &[u8], width: u32, height: u32) -> Result<Self, JsValue> {
         let texture = gl.create_texture()
             .ok_or("Failed to create texture")?;
-            
+        
         gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
         
         // Set texture parameters
@@ -115,6 +119,7 @@ impl Texture {
         gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, None);
         
         Ok(Self {
+            gl: gl.clone(),
             id: texture,
             width,
             height,
@@ -147,3 +152,9 @@ impl Texture {
         &self.id
     }
 }
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.gl.delete_texture(Some(&self.id));
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::ecs::Component;
+
+// A grid of tiles with its top-left corner at the entity's `Transform`.
+// Tiles are global tile ids as in Tiled: 0 is an empty cell, any other id
+// falls in the tileset with the highest `first_gid` not above it, and the top
+// bits flag flips.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tilemap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    // Row by row, starting at the top
+    pub tiles: Vec<u32>,
+    pub tilesets: Vec<Tileset>,
+    pub opacity: f32,
+    pub visible: bool,
+}
+
+impl Default for Tilemap {
+    fn default() -> Self {
+        Self::new(0, 0, 0.0, 0.0)
+    }
+}
+
+// Where the tiles of a tilemap come from
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub name: String,
+    // Path of the tileset's image, relative to the map file
+    pub image: Option<String>,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub margin: u32,
+    pub spacing: u32,
+}
+
+impl Tilemap {
+    pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+    pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+    pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+    const FLAGS: u32 = 0xf000_0000;
+    
+    pub fn new(width: u32, height: u32, tile_width: f32, tile_height: f32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            tiles: vec![0; Self::cell_count(width, height)],
+            tilesets: Vec::new(),
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+    
+    // Panics if the grid has more cells than memory could hold
+    fn cell_count(width: u32, height: u32) -> usize {
+        (width as usize)
+            .checked_mul(height as usize)
+            .unwrap_or_else(|| panic!("a {}x{} tilemap is too large", width, height))
+    }
+    
+    // The raw tile at a cell, flip flags included
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles.get(y as usize * self.width as usize + x as usize).copied()
+    }
+    
+    pub fn set(&mut self, x: u32, y: u32, tile: u32) {
+        if x < self.width && y < self.height {
+            if let Some(cell) = self.tiles.get_mut(y as usize * self.width as usize + x as usize) {
+                *cell = tile;
+            }
+        }
+    }
+    
+    // A tile without its flip flags
+    pub fn tile_id(tile: u32) -> u32 {
+        tile & !Self::FLAGS
+    }
+    
+    // The tileset a tile comes from and the tile's index within it
+    pub fn tileset_for(&self, tile: u32) -> Option<(&Tileset, u32)> {
+        let gid = Self::tile_id(tile);
+        if gid == 0 {
+            return None;
+        }
+        let tileset = self.tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid)
+            .max_by_key(|tileset| tileset.first_gid)?;
+        Some((tileset, gid - tileset.first_gid))
+    }
+    
+    // The cell containing a point given relative to the tilemap's corner
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(u32, u32)> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (column, row) = ((x / self.tile_width) as u32, (y / self.tile_height) as u32);
+        (column < self.width && row < self.height).then_some((column, row))
+    }
+}
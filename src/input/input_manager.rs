@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+#[derive(Clone)]
 pub struct InputManager {
     keys_pressed: HashSet<u32>,
     keys_just_pressed: HashSet<u32>,
@@ -94,6 +95,12 @@ impl InputManager {
     }
 }
 
+impl Default for InputManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Common key codes
 pub const KEY_W: u32 = 87;
 pub const KEY_A: u32 = 65;
@@ -1,5 +1,8 @@
 use wasm_bindgen::prelude::*;
 
+// Lets `#[derive(Component)]` refer to `::wasm_game_engine` from inside this crate too
+extern crate self as wasm_game_engine;
+
 // Import the `console.log` function from the `console` global object
 #[wasm_bindgen]
 extern "C" {
@@ -9,20 +12,23 @@ extern "C" {
 
 // Define a macro for easier console logging
 macro_rules! console_log {
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+    ($($t:tt)*) => ($crate::log(&format_args!($($t)*).to_string()))
 }
 
 // Use `wee_alloc` as the global allocator for smaller binary size
+#[cfg(target_arch = "wasm32")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 // Core modules
 pub mod core;
+pub mod assets;
 pub mod graphics;
 pub mod physics;
 pub mod audio;
 pub mod input;
 pub mod math;
+pub mod tiled;
 pub mod utils;
 
 use core::engine::GameEngine;
@@ -57,6 +63,17 @@ impl WasmGameEngine {
         self.engine.render();
     }
     
+    // For profiling: what the last frame rendered took
+    #[wasm_bindgen]
+    pub fn draw_calls(&self) -> u32 {
+        self.engine.render_stats().draw_calls
+    }
+    
+    #[wasm_bindgen]
+    pub fn sprites_drawn(&self) -> u32 {
+        self.engine.render_stats().sprites
+    }
+    
     #[wasm_bindgen]
     pub fn resize(&mut self, width: u32, height: u32) {
         self.engine.resize(width, height);
@@ -67,6 +84,53 @@ impl WasmGameEngine {
         self.engine.create_sprite(x, y, width, height)
     }
     
+    // `json` maps component names to fields, e.g.
+    // `{"Sprite": {"width": 32, "height": 32, "color_r": 1, "color_g": 0, "color_b": 0}}`
+    #[wasm_bindgen]
+    pub fn register_prefab(&mut self, name: &str, json: &str) -> Result<(), JsValue> {
+        self.engine.register_prefab(name, json)
+    }
+    
+    // Returns a sprite id; `overrides` has the same shape as the prefab JSON
+    // (pass "{}" for none)
+    #[wasm_bindgen]
+    pub fn instantiate_prefab(&mut self, name: &str, overrides: &str) -> Result<u32, JsValue> {
+        self.engine.instantiate_prefab(name, overrides)
+    }
+    
+    // Worlds: only the active one is updated and rendered; sprites stay in
+    // the world that was active when they were created
+    #[wasm_bindgen]
+    pub fn create_world(&mut self, name: &str) -> bool {
+        self.engine.create_world(name)
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_active_world(&mut self, name: &str) -> bool {
+        self.engine.set_active_world(name)
+    }
+    
+    #[wasm_bindgen]
+    pub fn active_world(&self) -> String {
+        self.engine.active_world().to_string()
+    }
+    
+    // Snapshots `source` into `target`, or restores `target` from a snapshot
+    #[wasm_bindgen]
+    pub fn clone_world(&mut self, source: &str, target: &str) -> bool {
+        self.engine.clone_world(source, target)
+    }
+    
+    #[wasm_bindgen]
+    pub fn remove_world(&mut self, name: &str) -> bool {
+        self.engine.remove_world(name).is_some()
+    }
+    
+    #[wasm_bindgen]
+    pub fn remove_sprite(&mut self, sprite_id: u32) -> bool {
+        self.engine.remove_sprite(sprite_id)
+    }
+    
     #[wasm_bindgen]
     pub fn set_sprite_position(&mut self, sprite_id: u32, x: f32, y: f32) {
         self.engine.set_sprite_position(sprite_id, x, y);
@@ -77,6 +141,167 @@ impl WasmGameEngine {
         self.engine.set_sprite_color(sprite_id, r, g, b, a);
     }
     
+    // Scenes: JSON for level files, binary for savegames. Loading replaces
+    // the active world's entities and returns the new sprite ids.
+    #[wasm_bindgen]
+    pub fn save_scene(&self) -> String {
+        self.engine.save_scene()
+    }
+    
+    #[wasm_bindgen]
+    pub fn load_scene(&mut self, json: &str) -> Result<Vec<u32>, JsValue> {
+        self.engine.load_scene(json)
+    }
+    
+    #[wasm_bindgen]
+    pub fn save_game(&self) -> Vec<u8> {
+        self.engine.save_game()
+    }
+    
+    #[wasm_bindgen]
+    pub fn load_game(&mut self, bytes: &[u8]) -> Result<Vec<u32>, JsValue> {
+        self.engine.load_game(bytes)
+    }
+    
+    // Textures load in the background; the returned id is for sprites to
+    // refer to
+    #[wasm_bindgen]
+    pub fn load_texture(&mut self, path: &str) -> u32 {
+        self.engine.load_texture(path)
+    }
+    
+    #[wasm_bindgen]
+    pub fn release_texture(&mut self, texture_id: u32) -> bool {
+        self.engine.release_texture(texture_id)
+    }
+    
+    // Sprite shaders are files holding both stages, each after a
+    // `#shader vertex` or `#shader fragment` line
+    #[wasm_bindgen]
+    pub fn load_shader(&mut self, path: &str) -> u32 {
+        self.engine.load_shader(path)
+    }
+    
+    #[wasm_bindgen]
+    pub fn release_shader(&mut self, shader_id: u32) -> bool {
+        self.engine.release_shader(shader_id)
+    }
+    
+    // `None` goes back to the built-in shader
+    #[wasm_bindgen]
+    pub fn set_sprite_shader(&mut self, shader_id: Option<u32>) -> bool {
+        self.engine.set_sprite_shader(shader_id)
+    }
+    
+    // Sounds load in the background, like textures
+    #[wasm_bindgen]
+    pub fn load_sound(&mut self, path: &str) -> u32 {
+        self.engine.load_sound(path)
+    }
+    
+    #[wasm_bindgen]
+    pub fn release_sound(&mut self, sound_id: u32) -> bool {
+        self.engine.release_sound(sound_id)
+    }
+    
+    #[wasm_bindgen]
+    pub fn play_sound(&mut self, sound_id: u32, volume: f32) -> Result<bool, JsValue> {
+        self.engine.play_sound(sound_id, volume)
+    }
+    
+    #[wasm_bindgen]
+    pub fn play_music(&mut self, sound_id: u32) -> Result<bool, JsValue> {
+        self.engine.play_music(sound_id)
+    }
+    
+    #[wasm_bindgen]
+    pub fn stop_all_sounds(&mut self) {
+        self.engine.audio_manager().stop_all_sounds();
+    }
+    
+    // Packs RGBA images into one texture: `sizes` holds each image's width
+    // and height in turn, `pixels` their pixels one after another. Returns
+    // the atlas's texture id.
+    #[wasm_bindgen]
+    pub fn build_atlas(
+        &mut self,
+        names: Vec<String>,
+        sizes: Vec<u32>,
+        pixels: Vec<u8>,
+        padding: u32,
+        extrusion: u32,
+    ) -> Result<u32, JsValue> {
+        self.engine.build_atlas(&names, &sizes, &pixels, padding, extrusion)
+    }
+    
+    // `layout` is the JSON written by the `pack-atlas` tool next to its image
+    #[wasm_bindgen]
+    pub fn load_atlas(&mut self, image_path: &str, layout: &str) -> Result<u32, JsValue> {
+        self.engine.load_atlas(image_path, layout)
+    }
+    
+    #[wasm_bindgen]
+    pub fn atlas_regions(&self, atlas_id: u32) -> Vec<String> {
+        self.engine.atlas_regions(atlas_id)
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_sprite_region(&mut self, sprite_id: u32, atlas_id: u32, region: &str) -> Result<(), JsValue> {
+        self.engine.set_sprite_region(sprite_id, atlas_id, region)
+    }
+    
+    #[wasm_bindgen]
+    pub fn texture_state(&self, texture_id: u32) -> Option<String> {
+        self.engine.texture_state(texture_id)
+    }
+    
+    #[wasm_bindgen]
+    pub fn load_progress(&self) -> f32 {
+        self.engine.load_progress()
+    }
+    
+    // Hot reloading, driven from JavaScript
+    #[wasm_bindgen]
+    pub fn reload_asset(&mut self, path: &str) -> u32 {
+        self.engine.reload_asset(path) as u32
+    }
+    
+    #[wasm_bindgen]
+    pub fn asset_paths(&self) -> Vec<String> {
+        self.engine.asset_paths()
+    }
+    
+    // `bytes` is an archive made by the `pack-assets` tool
+    #[wasm_bindgen]
+    pub fn use_asset_bundle(&mut self, bytes: Vec<u8>) -> Result<u32, JsValue> {
+        self.engine.use_asset_bundle(bytes).map(|count| count as u32)
+    }
+    
+    // Adds a Tiled map to the active world; `tilesets` is a JSON object of
+    // external tileset contents by path
+    #[wasm_bindgen]
+    pub fn load_tiled_map(&mut self, contents: &str, tilesets: &str) -> Result<Vec<u32>, JsValue> {
+        self.engine.load_tiled_map(contents, tilesets)
+    }
+    
+    // Reflected component fields by path, e.g. "Transform.x" or "Sprite",
+    // with values passed as JSON
+    #[wasm_bindgen]
+    pub fn get_sprite_property(&self, sprite_id: u32, path: &str) -> Result<String, JsValue> {
+        self.engine.get_sprite_property(sprite_id, path)
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_sprite_property(&mut self, sprite_id: u32, path: &str, json: &str) -> Result<(), JsValue> {
+        self.engine.set_sprite_property(sprite_id, path, json)
+    }
+    
+    // JSON object mapping each reflected component to its field types
+    #[wasm_bindgen]
+    pub fn component_types(&self) -> String {
+        self.engine.component_types()
+    }
+    
     #[wasm_bindgen]
     pub fn handle_key_down(&mut self, key_code: u32) {
         self.engine.handle_key_down(key_code);
@@ -103,6 +328,16 @@ impl WasmGameEngine {
     }
 }
 
+// Entry point for web workers sharing this module's memory (a build with the
+// `atomics` target feature): each worker that calls it joins the pool that
+// parallel systems run on, and never returns. Without any, systems run on
+// the main thread.
+#[cfg(all(target_arch = "wasm32", target_feature = "atomics"))]
+#[wasm_bindgen]
+pub fn thread_pool_worker() {
+    core::TaskPool::global().run_worker();
+}
+
 // Called when the wasm module is instantiated
 #[wasm_bindgen(start)]
 pub fn main() {
@@ -12,6 +12,11 @@ impl Mat4 {
         }
     }
     
+    // `data` lists the matrix column by column, the layout WebGL expects
+    pub fn from_column_major(data: [f32; 16]) -> Self {
+        Self { data }
+    }
+    
     pub fn identity() -> Self {
         Self {
             data: [
@@ -88,6 +93,7 @@ impl Mat4 {
         }
     }
     
+    #[allow(clippy::too_many_arguments)]
     pub fn look_at(eye_x: f32, eye_y: f32, eye_z: f32,
                    center_x: f32, center_y: f32, center_z: f32,
                    up_x: f32, up_y: f32, up_z: f32) -> Self {
@@ -147,19 +153,27 @@ impl Mat4 {
     }
 }
 
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Mul<Mat4> for Mat4 {
     type Output = Mat4;
     
+    // Standard matrix product: `a * b` applies `b` first, then `a`. Storage is
+    // column-major, so element (row, col) lives at `data[col * 4 + row]`.
     fn mul(self, other: Mat4) -> Mat4 {
         let mut result = Mat4::new();
         
-        for row in 0..4 {
-            for col in 0..4 {
+        for col in 0..4 {
+            for row in 0..4 {
                 let mut sum = 0.0;
                 for k in 0..4 {
-                    sum += self.get(row, k) * other.get(k, col);
+                    sum += self.data[k * 4 + row] * other.data[col * 4 + k];
                 }
-                result.set(row, col, sum);
+                result.data[col * 4 + row] = sum;
             }
         }
         
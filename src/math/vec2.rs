@@ -48,7 +48,7 @@ impl Vec2 {
     }
     
     pub fn lerp(a: Vec2, b: Vec2, t: f32) -> Vec2 {
-        let t = t.max(0.0).min(1.0);
+        let t = t.clamp(0.0, 1.0);
         Vec2 {
             x: a.x + (b.x - a.x) * t,
             y: a.y + (b.y - a.y) * t,
@@ -59,7 +59,7 @@ impl Vec3 {
     }
     
     pub fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
-        let t = t.max(0.0).min(1.0);
+        let t = t.clamp(0.0, 1.0);
         Vec3 {
             x: a.x + (b.x - a.x) * t,
             y: a.y + (b.y - a.y) * t,
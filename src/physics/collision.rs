@@ -1,3 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::ecs::{Component, Entity, Reflect, Transform};
+
 #[derive(Clone, Copy, Debug)]
 pub struct AABB {
     pub x: f32,
@@ -48,6 +52,45 @@ impl AABB {
     }
 }
 
+// Collision shape of an entity: a box whose top-left corner sits at
+// `offset` from the entity's `Transform` position. Triggers report overlaps
+// without being pushed apart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collider {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub is_trigger: bool,
+}
+
+impl Collider {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            ..Self::default()
+        }
+    }
+    
+    pub fn with_offset(mut self, offset_x: f32, offset_y: f32) -> Self {
+        self.offset_x = offset_x;
+        self.offset_y = offset_y;
+        self
+    }
+    
+    pub fn trigger(mut self) -> Self {
+        self.is_trigger = true;
+        self
+    }
+    
+    // The box in the same space as `transform`, ignoring rotation and scale
+    pub fn aabb(&self, transform: &Transform) -> AABB {
+        AABB::new(transform.x + self.offset_x, transform.y + self.offset_y, self.width, self.height)
+    }
+}
+
 pub struct CollisionDetection {
     // Could contain spatial partitioning structures like quadtree in the future
 }
@@ -114,6 +157,12 @@ impl CollisionDetection {
     }
 }
 
+impl Default for CollisionDetection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Collision response data
 #[derive(Clone, Copy, Debug)]
 pub struct CollisionInfo {
@@ -125,6 +174,15 @@ pub struct CollisionInfo {
     pub contact_y: f32,
 }
 
+// A hit between two entities, sent through `Events<CollisionEvent>` so any
+// system can react to it. `info` is resolved from `a`'s point of view.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub info: CollisionInfo,
+}
+
 impl CollisionInfo {
     pub fn resolve_aabb_collision(a: &AABB, b: &AABB) -> Option<CollisionInfo> {
         if !a.intersects(b) {
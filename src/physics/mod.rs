@@ -2,4 +2,4 @@ pub mod physics_world;
 pub mod collision;
 
 pub use physics_world::PhysicsWorld;
-pub use collision::{AABB, CollisionDetection};
+pub use collision::{AABB, Collider, CollisionDetection};
@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::ecs::{Component, Reflect};
 use crate::physics::collision::{AABB, CollisionDetection};
 
 pub struct PhysicsWorld {
@@ -26,7 +29,7 @@ impl PhysicsWorld {
     pub fn update(&mut self, delta_time: f32) {
         // Fixed timestep physics simulation
         let mut accumulator = 0.0;
-        let mut current_time = delta_time;
+        let current_time = delta_time;
         
         accumulator += current_time;
         
@@ -37,7 +40,7 @@ impl PhysicsWorld {
         }
     }
     
-    fn physics_step(&mut self, dt: f32) {
+    fn physics_step(&mut self, _dt: f32) {
         // This is where we would update all physics bodies
         // For now, it's a placeholder for the physics integration
         
@@ -63,9 +66,17 @@ impl PhysicsWorld {
     }
 }
 
-// Physics body component (could be added to ECS)
-#[derive(Clone, Copy, Debug)]
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Physics body component
+#[derive(Clone, Copy, Debug, Component, Reflect, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RigidBody {
+    #[serde(with = "infinite_as_null")]
     pub mass: f32,
     pub velocity_x: f32,
     pub velocity_y: f32,
@@ -76,6 +87,24 @@ pub struct RigidBody {
     pub friction: f32,
 }
 
+// JSON has no infinity, so the infinite mass of static bodies is written
+// as null
+mod infinite_as_null {
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_infinite() {
+            serializer.serialize_none()
+        } else {
+            serializer.serialize_f32(*value)
+        }
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::INFINITY))
+    }
+}
+
 impl RigidBody {
     pub fn new(mass: f32) -> Self {
         Self {
@@ -126,3 +155,9 @@ impl RigidBody {
         }
     }
 }
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
@@ -0,0 +1,285 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{check_tile_count, decode_tiles, image_relative_to_map, Layer, LayerKind, MapObject, Property, Shape, TiledError, TiledMap};
+use crate::graphics::tilemap::Tileset;
+
+// The .tmj layout, as far as the loader cares
+#[derive(Deserialize)]
+struct RawMap {
+    width: u32,
+    height: u32,
+    #[serde(rename = "tilewidth")]
+    tile_width: u32,
+    #[serde(rename = "tileheight")]
+    tile_height: u32,
+    #[serde(default = "orthogonal")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    tilesets: Vec<RawTileset>,
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+fn orthogonal() -> String {
+    "orthogonal".to_string()
+}
+
+fn visible() -> bool {
+    true
+}
+
+fn opaque() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct RawTileset {
+    #[serde(rename = "firstgid", default)]
+    first_gid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    image: Option<String>,
+    #[serde(rename = "tilewidth", default)]
+    tile_width: u32,
+    #[serde(rename = "tileheight", default)]
+    tile_height: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(rename = "tilecount", default)]
+    tile_count: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+}
+
+#[derive(Deserialize)]
+struct RawLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default = "visible")]
+    visible: bool,
+    #[serde(default = "opaque")]
+    opacity: f32,
+    #[serde(rename = "offsetx", default)]
+    offset_x: f32,
+    #[serde(rename = "offsety", default)]
+    offset_y: f32,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+    // Tile layers
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<Value>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    // Object layers
+    #[serde(default)]
+    objects: Vec<RawObject>,
+    // Groups
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    class: String,
+    #[serde(rename = "type", default)]
+    legacy_type: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    gid: Option<u32>,
+    #[serde(default = "visible")]
+    visible: bool,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<RawPoint>>,
+    polyline: Option<Vec<RawPoint>>,
+    text: Option<Value>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(rename = "propertytype")]
+    property_type: Option<String>,
+    #[serde(default)]
+    value: Value,
+}
+
+pub(super) fn parse_map(
+    json: &str,
+    read_tileset: &mut dyn FnMut(&str) -> Result<String, TiledError>,
+) -> Result<TiledMap, TiledError> {
+    let raw: RawMap = serde_json::from_str(json).map_err(|error| TiledError::InvalidJson(error.to_string()))?;
+    if raw.orientation != "orthogonal" {
+        return Err(TiledError::Unsupported(format!("{} maps", raw.orientation)));
+    }
+    if raw.infinite {
+        return Err(TiledError::Unsupported("infinite maps".to_string()));
+    }
+    
+    let mut tilesets = Vec::with_capacity(raw.tilesets.len());
+    for tileset in raw.tilesets {
+        tilesets.push(match tileset.source {
+            Some(source) => {
+                let contents = read_tileset(&source)?;
+                let mut external = parse_tileset(&contents)?;
+                external.first_gid = tileset.first_gid;
+                external.image = external.image.map(|image| image_relative_to_map(&source, image));
+                external
+            }
+            None => convert_tileset(tileset),
+        });
+    }
+    
+    Ok(TiledMap {
+        width: raw.width,
+        height: raw.height,
+        tile_width: raw.tile_width,
+        tile_height: raw.tile_height,
+        tilesets,
+        layers: raw.layers.into_iter().map(convert_layer).collect::<Result<_, _>>()?,
+        properties: convert_properties(raw.properties),
+    })
+}
+
+// An external tileset file, .tsj or .tsx
+pub(super) fn parse_tileset(contents: &str) -> Result<Tileset, TiledError> {
+    if contents.trim_start().starts_with('<') {
+        return super::xml::parse_tileset(contents);
+    }
+    let raw: RawTileset = serde_json::from_str(contents).map_err(|error| TiledError::InvalidJson(error.to_string()))?;
+    Ok(convert_tileset(raw))
+}
+
+fn convert_tileset(raw: RawTileset) -> Tileset {
+    Tileset {
+        first_gid: raw.first_gid,
+        name: raw.name,
+        image: raw.image,
+        tile_width: raw.tile_width,
+        tile_height: raw.tile_height,
+        columns: raw.columns,
+        tile_count: raw.tile_count,
+        margin: raw.margin,
+        spacing: raw.spacing,
+    }
+}
+
+fn convert_layer(raw: RawLayer) -> Result<Layer, TiledError> {
+    let kind = match raw.kind.as_str() {
+        "tilelayer" => {
+            let tiles = match raw.data {
+                Some(Value::Array(cells)) => cells
+                    .iter()
+                    .map(|cell| {
+                        cell.as_u64()
+                            .and_then(|gid| u32::try_from(gid).ok())
+                            .ok_or_else(|| TiledError::InvalidMap(format!("bad tile `{}` in layer `{}`", cell, raw.name)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                Some(Value::String(data)) => {
+                    let cells = (raw.width as usize).saturating_mul(raw.height as usize);
+                    decode_tiles(&data, raw.encoding.as_deref(), raw.compression.as_deref(), cells)?
+                }
+                Some(_) | None => {
+                    return Err(TiledError::Unsupported(format!("tile layer `{}` without inline data", raw.name)));
+                }
+            };
+            check_tile_count(&raw.name, raw.width, raw.height, &tiles)?;
+            LayerKind::Tiles { width: raw.width, height: raw.height, tiles }
+        }
+        "objectgroup" => LayerKind::Objects(raw.objects.into_iter().map(convert_object).collect()),
+        "group" => LayerKind::Group(raw.layers.into_iter().map(convert_layer).collect::<Result<_, _>>()?),
+        "imagelayer" => LayerKind::Image,
+        other => return Err(TiledError::InvalidMap(format!("unknown layer type `{}`", other))),
+    };
+    
+    Ok(Layer {
+        name: raw.name,
+        visible: raw.visible,
+        opacity: raw.opacity,
+        offset_x: raw.offset_x,
+        offset_y: raw.offset_y,
+        properties: convert_properties(raw.properties),
+        kind,
+    })
+}
+
+fn convert_object(raw: RawObject) -> MapObject {
+    let points = |points: Vec<RawPoint>| points.into_iter().map(|point| (point.x, point.y)).collect();
+    let shape = if let Some(polygon) = raw.polygon {
+        Shape::Polygon(points(polygon))
+    } else if let Some(polyline) = raw.polyline {
+        Shape::Polyline(points(polyline))
+    } else if raw.ellipse {
+        Shape::Ellipse
+    } else if raw.point {
+        Shape::Point
+    } else if raw.text.is_some() {
+        Shape::Text
+    } else {
+        Shape::Rectangle
+    };
+    
+    MapObject {
+        id: raw.id,
+        name: raw.name,
+        class: if raw.class.is_empty() { raw.legacy_type } else { raw.class },
+        x: raw.x,
+        y: raw.y,
+        width: raw.width,
+        height: raw.height,
+        rotation: raw.rotation,
+        gid: raw.gid.filter(|&gid| gid != 0),
+        visible: raw.visible,
+        shape,
+        properties: convert_properties(raw.properties),
+    }
+}
+
+fn convert_properties(raw: Vec<RawProperty>) -> Vec<Property> {
+    raw.into_iter()
+        .map(|property| Property {
+            name: property.name,
+            value: property.value,
+            class: property.property_type.filter(|_| property.kind == "class"),
+        })
+        .collect()
+}
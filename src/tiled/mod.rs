@@ -0,0 +1,446 @@
+// Loading levels made in the Tiled map editor (https://www.mapeditor.org),
+// from either its JSON (.tmj) or XML (.tmx) format
+mod json;
+mod xml;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::core::ecs::{merge, Component, Entity, Name, Prefab, PrefabError, Prefabs, Transform, TypeRegistry, World};
+use crate::graphics::tilemap::{Tilemap, Tileset};
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io { path: String, message: String },
+    InvalidJson(String),
+    InvalidXml(String),
+    // Well-formed, but not a map Tiled would write
+    InvalidMap(String),
+    Unsupported(String),
+    // The map refers to a tileset file, but was parsed without access to files
+    ExternalTileset(String),
+    // An object's class or custom properties didn't fit the registered
+    // components
+    Object { id: u32, error: PrefabError },
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledError::Io { path, message } => write!(f, "failed to read `{}`: {}", path, message),
+            TiledError::InvalidJson(message) => write!(f, "invalid Tiled JSON: {}", message),
+            TiledError::InvalidXml(message) => write!(f, "invalid Tiled XML: {}", message),
+            TiledError::InvalidMap(message) => write!(f, "invalid Tiled map: {}", message),
+            TiledError::Unsupported(feature) => write!(f, "unsupported Tiled feature: {}", feature),
+            TiledError::ExternalTileset(source) => {
+                write!(f, "external tileset `{}` needs the map to be loaded from a file", source)
+            }
+            TiledError::Object { id, error } => write!(f, "object {}: {}", id, error),
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+// A parsed Tiled map. Only orthogonal, finite maps are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<Layer>,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub properties: Vec<Property>,
+    pub kind: LayerKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerKind {
+    // Global tile ids, row by row
+    Tiles { width: u32, height: u32, tiles: Vec<u32> },
+    Objects(Vec<MapObject>),
+    Group(Vec<Layer>),
+    Image,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    // Called "type" before Tiled 1.9
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Degrees, clockwise
+    pub rotation: f32,
+    // Set for tile objects, which are anchored at their bottom-left corner
+    pub gid: Option<u32>,
+    pub visible: bool,
+    pub shape: Shape,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Ellipse,
+    Point,
+    // Points relative to the object's position
+    Polygon(Vec<(f32, f32)>),
+    Polyline(Vec<(f32, f32)>),
+    Text,
+}
+
+// A custom property. Strings, files and colors become JSON strings, ints,
+// floats and object references numbers, and class properties objects of
+// their members.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub value: Value,
+    // The custom type of a class property
+    pub class: Option<String>,
+}
+
+// The Tiled object an entity was spawned from
+#[derive(Clone, Debug, Default, PartialEq, Component, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledObject {
+    pub id: u32,
+    pub class: String,
+    pub gid: Option<u32>,
+}
+
+// Custom properties of a map, layer or object that didn't map onto a
+// component
+#[derive(Clone, Debug, Default, PartialEq, Component, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TiledProperties(pub BTreeMap<String, Value>);
+
+impl TiledProperties {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+}
+
+impl TiledMap {
+    // Parses a map in either format, which is told apart by its first
+    // character. `read_tileset` is asked for the contents of external
+    // tileset files by their path relative to the map.
+    pub fn parse(
+        contents: &str,
+        read_tileset: &mut dyn FnMut(&str) -> Result<String, TiledError>,
+    ) -> Result<Self, TiledError> {
+        if contents.trim_start().starts_with('<') {
+            xml::parse_map(contents, read_tileset)
+        } else {
+            json::parse_map(contents, read_tileset)
+        }
+    }
+    
+    // Parses a .tmj map that embeds all of its tilesets
+    pub fn from_json(json: &str) -> Result<Self, TiledError> {
+        json::parse_map(json, &mut |source| Err(TiledError::ExternalTileset(source.to_string())))
+    }
+    
+    // Parses a .tmx map that embeds all of its tilesets
+    pub fn from_xml(xml: &str) -> Result<Self, TiledError> {
+        xml::parse_map(xml, &mut |source| Err(TiledError::ExternalTileset(source.to_string())))
+    }
+    
+    // Reads a map from disk along with any external tilesets next to it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TiledError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&read_file(path)?, &mut |source| read_file(&directory.join(source)))
+    }
+    
+    // Spawns the map under a new root entity, which it returns: a child
+    // entity per layer (groups nest), carrying a `Tilemap` for tile layers,
+    // and below object layers an entity per object.
+    //
+    // Objects get a `Transform` and, depending on their kind, a `Sprite`
+    // (tile objects) or a `Collider` (rectangles, ellipses and polygons). An
+    // object whose class names a registered prefab starts from that prefab.
+    // Custom properties then map onto components registered with the world's
+    // `Prefabs`: "Velocity.x" sets one field, and a class property whose type
+    // (or name) is a component sets several. Other properties end up in a
+    // `TiledProperties` component. Nothing is spawned if an object fails.
+    pub fn spawn(&self, world: &mut World) -> Result<Entity, TiledError> {
+        let mut nodes = Vec::new();
+        let root = Prefab::new().with(Transform::new(0.0, 0.0));
+        nodes.push((None, with_properties(root, &self.properties)));
+        for layer in &self.layers {
+            self.plan_layer(world, layer, 0, true, &mut nodes)?;
+        }
+        
+        let entities: Vec<Entity> = nodes.iter().map(|(_, prefab)| prefab.spawn(world)).collect();
+        for (index, (parent, _)) in nodes.iter().enumerate() {
+            if let Some(parent) = *parent {
+                world.set_parent(entities[index], entities[parent]);
+            }
+        }
+        Ok(entities[0])
+    }
+    
+    fn plan_layer(
+        &self,
+        world: &mut World,
+        layer: &Layer,
+        parent: usize,
+        visible: bool,
+        nodes: &mut Vec<(Option<usize>, Prefab)>,
+    ) -> Result<(), TiledError> {
+        let visible = visible && layer.visible;
+        let mut prefab = Prefab::new()
+            .with(Transform::new(layer.offset_x, layer.offset_y))
+            .with(Name::new(layer.name.as_str()));
+        if let LayerKind::Tiles { width, height, tiles } = &layer.kind {
+            prefab = prefab.with(Tilemap {
+                width: *width,
+                height: *height,
+                tile_width: self.tile_width as f32,
+                tile_height: self.tile_height as f32,
+                tiles: tiles.clone(),
+                tilesets: self.tilesets.clone(),
+                opacity: layer.opacity,
+                visible,
+            });
+        }
+        
+        let index = nodes.len();
+        nodes.push((Some(parent), with_properties(prefab, &layer.properties)));
+        match &layer.kind {
+            LayerKind::Objects(objects) => {
+                for object in objects {
+                    let prefab = object_prefab(world, object, visible)
+                        .map_err(|error| TiledError::Object { id: object.id, error })?;
+                    nodes.push((Some(index), prefab));
+                }
+            }
+            LayerKind::Group(layers) => {
+                for layer in layers {
+                    self.plan_layer(world, layer, index, visible, nodes)?;
+                }
+            }
+            LayerKind::Tiles { .. } | LayerKind::Image => {}
+        }
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, TiledError> {
+    std::fs::read_to_string(path).map_err(|error| TiledError::Io {
+        path: path.display().to_string(),
+        message: error.to_string(),
+    })
+}
+
+fn with_properties(prefab: Prefab, properties: &[Property]) -> Prefab {
+    if properties.is_empty() {
+        return prefab;
+    }
+    let properties = properties.iter().map(|property| (property.name.clone(), property.value.clone()));
+    prefab.with(TiledProperties(properties.collect()))
+}
+
+fn object_prefab(world: &World, object: &MapObject, visible: bool) -> Result<Prefab, PrefabError> {
+    let base = world.resource::<Prefabs>().and_then(|prefabs| prefabs.get(&object.class).cloned()).unwrap_or_default();
+    
+    // Tile objects hang up from their anchor
+    let y = if object.gid.is_some() { object.y - object.height } else { object.y };
+    let mut overrides = Map::new();
+    overrides.insert("Transform".to_string(), serde_json::json!({
+        "x": object.x,
+        "y": y,
+        "rotation": object.rotation.to_radians(),
+    }));
+    if !object.name.is_empty() {
+        overrides.insert("Name".to_string(), Value::from(object.name.as_str()));
+    }
+    
+    let size = (object.width, object.height);
+    let collider = match &object.shape {
+        _ if object.gid.is_some() => None,
+        Shape::Rectangle | Shape::Ellipse if size.0 > 0.0 && size.1 > 0.0 => Some((0.0, 0.0, size.0, size.1)),
+        Shape::Polygon(points) | Shape::Polyline(points) if !points.is_empty() => {
+            let (min_x, max_x) = bounds(points.iter().map(|point| point.0));
+            let (min_y, max_y) = bounds(points.iter().map(|point| point.1));
+            Some((min_x, min_y, max_x - min_x, max_y - min_y))
+        }
+        _ => None,
+    };
+    if object.gid.is_some() && visible && object.visible {
+        overrides.insert("Sprite".to_string(), serde_json::json!({ "width": size.0, "height": size.1 }));
+    }
+    if let Some((offset_x, offset_y, width, height)) = collider {
+        overrides.insert("Collider".to_string(), serde_json::json!({
+            "offset_x": offset_x,
+            "offset_y": offset_y,
+            "width": width,
+            "height": height,
+        }));
+    }
+    
+    let mut leftover = BTreeMap::new();
+    world.with_type_registry(|types| object_overrides(types, object, &mut overrides, &mut leftover));
+    
+    let overrides = Value::Object(overrides).to_string();
+    let mut prefab = world.with_type_registry(|types| types.apply_overrides(&base, &overrides))?;
+    prefab = prefab.with(TiledObject {
+        id: object.id,
+        class: object.class.clone(),
+        gid: object.gid,
+    });
+    if !leftover.is_empty() {
+        prefab = prefab.with(TiledProperties(leftover));
+    }
+    Ok(prefab)
+}
+
+// Sorts the object's custom properties into component fields and the rest
+fn object_overrides(
+    types: &TypeRegistry,
+    object: &MapObject,
+    overrides: &mut Map<String, Value>,
+    leftover: &mut BTreeMap<String, Value>,
+) {
+    for property in &object.properties {
+        if let Some((component, path)) = property.name.split_once('.') {
+            // Unregistered names are passed on too, so the error names them
+            let mut target = overrides.entry(component.to_string()).or_insert_with(|| Value::Object(Map::new()));
+            for field in path.split('.') {
+                if !target.is_object() {
+                    *target = Value::Object(Map::new());
+                }
+                target = target
+                    .as_object_mut()
+                    .expect("just made an object")
+                    .entry(field.to_string())
+                    .or_insert(Value::Null);
+            }
+            *target = property.value.clone();
+            continue;
+        }
+        
+        let component = [property.class.as_deref(), Some(property.name.as_str())]
+            .into_iter()
+            .flatten()
+            .find(|name| property.value.is_object() && types.contains(name));
+        match component {
+            Some(component) => merge(overrides.entry(component.to_string()).or_insert(Value::Null), property.value.clone()),
+            None => {
+                leftover.insert(property.name.clone(), property.value.clone());
+            }
+        }
+    }
+
+}
+
+fn bounds(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)))
+}
+
+// Checks that a tile layer has a tile per cell. The cell count is checked
+// too, as a crafted map could make it wrap around.
+fn check_tile_count(layer: &str, width: u32, height: u32, tiles: &[u32]) -> Result<(), TiledError> {
+    if width.checked_mul(height).is_none_or(|cells| tiles.len() != cells as usize) {
+        return Err(TiledError::InvalidMap(format!(
+            "layer `{}` has {} tiles for a {}x{} grid",
+            layer,
+            tiles.len(),
+            width,
+            height
+        )));
+    }
+    Ok(())
+}
+
+// Tile layer data: CSV, or base64 of little-endian u32s, which may be
+// zlib-compressed. `cells` is the layer's size, past which inflating stops.
+fn decode_tiles(data: &str, encoding: Option<&str>, compression: Option<&str>, cells: usize) -> Result<Vec<u32>, TiledError> {
+    let compression = compression.filter(|compression| !compression.is_empty());
+    if let Some(compression) = compression.filter(|&compression| compression != "zlib") {
+        return Err(TiledError::Unsupported(format!(
+            "{} compressed tile layers (save the map with CSV, or uncompressed or zlib-compressed Base64 layer format)",
+            compression
+        )));
+    }
+    
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(str::trim)
+            .filter(|cell| !cell.is_empty())
+            .map(|cell| cell.parse::<u32>().map_err(|_| TiledError::InvalidMap(format!("bad tile `{}` in CSV data", cell))))
+            .collect(),
+        Some("base64") => {
+            let mut bytes = decode_base64(data)?;
+            if compression.is_some() {
+                bytes = decompress_to_vec_zlib_with_limit(&bytes, cells.saturating_mul(4))
+                    .map_err(|error| TiledError::InvalidMap(format!("bad zlib tile data: {}", error)))?;
+            }
+            if bytes.len() % 4 != 0 {
+                return Err(TiledError::InvalidMap("base64 tile data isn't a whole number of tiles".to_string()));
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|chunk| u32::from_le_bytes(chunk.try_into().expect("chunks of 4")))
+                .collect())
+        }
+        Some(other) => Err(TiledError::Unsupported(format!("`{}` tile layer encoding", other))),
+        None => Err(TiledError::InvalidMap("tile layer data without an encoding".to_string())),
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, TiledError> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(TiledError::InvalidMap(format!("invalid base64 character `{}`", byte as char))),
+        };
+        // Only the bits not yet written out are kept
+        buffer = (buffer << 6 | value as u32) & 0xfff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+// Makes a tileset's image path relative to the map rather than to the
+// tileset file at `source`
+fn image_relative_to_map(source: &str, image: String) -> String {
+    match source.rsplit_once('/') {
+        Some((directory, _)) => format!("{}/{}", directory, image),
+        None => image,
+    }
+}
@@ -0,0 +1,225 @@
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
+
+use super::{check_tile_count, decode_tiles, image_relative_to_map, Layer, LayerKind, MapObject, Property, Shape, TiledError, TiledMap};
+use crate::graphics::tilemap::Tileset;
+
+pub(super) fn parse_map(
+    xml: &str,
+    read_tileset: &mut dyn FnMut(&str) -> Result<String, TiledError>,
+) -> Result<TiledMap, TiledError> {
+    let document = Document::parse(xml).map_err(|error| TiledError::InvalidXml(error.to_string()))?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        return Err(TiledError::InvalidMap(format!("expected a <map>, found <{}>", map.tag_name().name())));
+    }
+    
+    let orientation = map.attribute("orientation").unwrap_or("orthogonal");
+    if orientation != "orthogonal" {
+        return Err(TiledError::Unsupported(format!("{} maps", orientation)));
+    }
+    if map.attribute("infinite") == Some("1") {
+        return Err(TiledError::Unsupported("infinite maps".to_string()));
+    }
+    
+    let mut tilesets = Vec::new();
+    for element in elements(map, "tileset") {
+        let first_gid = parse_attribute(element, "firstgid", 0)?;
+        let tileset = match element.attribute("source") {
+            Some(source) => {
+                let contents = read_tileset(source)?;
+                let mut external = super::json::parse_tileset(&contents)?;
+                external.image = external.image.map(|image| image_relative_to_map(source, image));
+                external
+            }
+            None => convert_tileset(element)?,
+        };
+        tilesets.push(Tileset { first_gid, ..tileset });
+    }
+    
+    Ok(TiledMap {
+        width: required_attribute(map, "width")?,
+        height: required_attribute(map, "height")?,
+        tile_width: required_attribute(map, "tilewidth")?,
+        tile_height: required_attribute(map, "tileheight")?,
+        tilesets,
+        layers: convert_layers(map)?,
+        properties: convert_properties(map)?,
+    })
+}
+
+// An external .tsx tileset file
+pub(super) fn parse_tileset(xml: &str) -> Result<Tileset, TiledError> {
+    let document = Document::parse(xml).map_err(|error| TiledError::InvalidXml(error.to_string()))?;
+    let tileset = document.root_element();
+    if !tileset.has_tag_name("tileset") {
+        return Err(TiledError::InvalidMap(format!("expected a <tileset>, found <{}>", tileset.tag_name().name())));
+    }
+    convert_tileset(tileset)
+}
+
+fn convert_tileset(element: Node) -> Result<Tileset, TiledError> {
+    Ok(Tileset {
+        first_gid: 0,
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        image: elements(element, "image").next().and_then(|image| image.attribute("source")).map(str::to_string),
+        tile_width: parse_attribute(element, "tilewidth", 0)?,
+        tile_height: parse_attribute(element, "tileheight", 0)?,
+        columns: parse_attribute(element, "columns", 0)?,
+        tile_count: parse_attribute(element, "tilecount", 0)?,
+        margin: parse_attribute(element, "margin", 0)?,
+        spacing: parse_attribute(element, "spacing", 0)?,
+    })
+}
+
+// The layers directly inside a map or group, in document order
+fn convert_layers(parent: Node) -> Result<Vec<Layer>, TiledError> {
+    let mut layers = Vec::new();
+    for element in parent.children().filter(Node::is_element) {
+        let name = element.attribute("name").unwrap_or_default().to_string();
+        let kind = match element.tag_name().name() {
+            "layer" => {
+                let width = required_attribute(element, "width")?;
+                let height = required_attribute(element, "height")?;
+                let tiles = convert_tile_data(element, &name, (width as usize).saturating_mul(height as usize))?;
+                check_tile_count(&name, width, height, &tiles)?;
+                LayerKind::Tiles { width, height, tiles }
+            }
+            "objectgroup" => LayerKind::Objects(elements(element, "object").map(convert_object).collect::<Result<_, _>>()?),
+            "group" => LayerKind::Group(convert_layers(element)?),
+            "imagelayer" => LayerKind::Image,
+            _ => continue,
+        };
+        
+        layers.push(Layer {
+            name,
+            visible: element.attribute("visible") != Some("0"),
+            opacity: parse_attribute(element, "opacity", 1.0)?,
+            offset_x: parse_attribute(element, "offsetx", 0.0)?,
+            offset_y: parse_attribute(element, "offsety", 0.0)?,
+            properties: convert_properties(element)?,
+            kind,
+        });
+    }
+    Ok(layers)
+}
+
+fn convert_tile_data(layer: Node, name: &str, cells: usize) -> Result<Vec<u32>, TiledError> {
+    let data = elements(layer, "data")
+        .next()
+        .ok_or_else(|| TiledError::InvalidMap(format!("tile layer `{}` has no <data>", name)))?;
+    if elements(data, "chunk").next().is_some() {
+        return Err(TiledError::Unsupported("infinite maps".to_string()));
+    }
+    
+    match data.attribute("encoding") {
+        // Plain XML: one <tile> per cell, with empty cells lacking a gid
+        None => elements(data, "tile").map(|tile| parse_attribute(tile, "gid", 0)).collect(),
+        encoding => decode_tiles(data.text().unwrap_or_default(), encoding, data.attribute("compression"), cells),
+    }
+}
+
+fn convert_object(element: Node) -> Result<MapObject, TiledError> {
+    let mut shape = Shape::Rectangle;
+    for child in element.children().filter(Node::is_element) {
+        shape = match child.tag_name().name() {
+            "ellipse" => Shape::Ellipse,
+            "point" => Shape::Point,
+            "polygon" => Shape::Polygon(parse_points(child)?),
+            "polyline" => Shape::Polyline(parse_points(child)?),
+            "text" => Shape::Text,
+            _ => continue,
+        };
+    }
+    
+    // Tiled 1.9 renamed "type" to "class"
+    let class = element.attribute("class").or_else(|| element.attribute("type")).unwrap_or_default();
+    Ok(MapObject {
+        id: parse_attribute(element, "id", 0)?,
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        class: class.to_string(),
+        x: parse_attribute(element, "x", 0.0)?,
+        y: parse_attribute(element, "y", 0.0)?,
+        width: parse_attribute(element, "width", 0.0)?,
+        height: parse_attribute(element, "height", 0.0)?,
+        rotation: parse_attribute(element, "rotation", 0.0)?,
+        gid: Some(parse_attribute(element, "gid", 0)?).filter(|&gid| gid != 0),
+        visible: element.attribute("visible") != Some("0"),
+        shape,
+        properties: convert_properties(element)?,
+    })
+}
+
+fn parse_points(element: Node) -> Result<Vec<(f32, f32)>, TiledError> {
+    let points = element.attribute("points").unwrap_or_default();
+    points
+        .split_whitespace()
+        .map(|point| {
+            let (x, y) = point.split_once(',')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        })
+        .map(|point| point.ok_or_else(|| TiledError::InvalidMap(format!("bad points `{}`", points))))
+        .collect()
+}
+
+// The <properties> directly inside `element`
+fn convert_properties(element: Node) -> Result<Vec<Property>, TiledError> {
+    let Some(properties) = elements(element, "properties").next() else {
+        return Ok(Vec::new());
+    };
+    
+    let mut result = Vec::new();
+    for property in elements(properties, "property") {
+        let kind = property.attribute("type").unwrap_or("string");
+        let value = if kind == "class" {
+            let members = convert_properties(property)?
+                .into_iter()
+                .map(|member| (member.name, member.value))
+                .collect::<Map<_, _>>();
+            Value::Object(members)
+        } else {
+            // Multi-line strings are stored as the element's text
+            let text = property.attribute("value").or_else(|| property.text()).unwrap_or_default();
+            convert_value(kind, text)
+                .ok_or_else(|| TiledError::InvalidMap(format!("bad {} property `{}`", kind, text)))?
+        };
+        
+        result.push(Property {
+            name: property.attribute("name").unwrap_or_default().to_string(),
+            value,
+            class: property.attribute("propertytype").filter(|_| kind == "class").map(str::to_string),
+        });
+    }
+    Ok(result)
+}
+
+// Typed the way the JSON format writes them
+fn convert_value(kind: &str, text: &str) -> Option<Value> {
+    let value = match kind {
+        "int" | "object" => Value::from(text.parse::<i64>().ok()?),
+        "float" => Value::from(text.parse::<f64>().ok()?),
+        "bool" => Value::Bool(text.parse().ok()?),
+        _ => Value::from(text),
+    };
+    Some(value)
+}
+
+fn elements<'a, 'input>(parent: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    parent.children().filter(move |child| child.has_tag_name(name))
+}
+
+fn parse_attribute<T: std::str::FromStr>(element: Node, name: &str, default: T) -> Result<T, TiledError> {
+    match element.attribute(name) {
+        Some(_) => required_attribute(element, name),
+        None => Ok(default),
+    }
+}
+
+fn required_attribute<T: std::str::FromStr>(element: Node, name: &str) -> Result<T, TiledError> {
+    let tag = element.tag_name().name();
+    let text = element
+        .attribute(name)
+        .ok_or_else(|| TiledError::InvalidMap(format!("<{}> is missing `{}`", tag, name)))?;
+    text.parse()
+        .map_err(|_| TiledError::InvalidMap(format!("bad `{}` attribute `{}` on <{}>", name, text, tag)))
+}
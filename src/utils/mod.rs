@@ -1,5 +1,3 @@
-use wasm_bindgen::prelude::*;
-
 // When the `console_error_panic_hook` feature is enabled, we can call the
 // `set_panic_hook` function at least once during initialization, and then
 // we will get better error messages if our code ever panics.
@@ -74,6 +72,7 @@ impl PerformanceTimer {
 }
 
 // Random number utilities
+#[derive(Clone)]
 pub struct Random {
     state: u64,
 }
@@ -119,6 +118,12 @@ impl Random {
     }
 }
 
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Color utilities
 #[derive(Clone, Copy, Debug)]
 pub struct Color {
@@ -153,7 +158,7 @@ impl Color {
     }
     
     pub fn lerp(a: Color, b: Color, t: f32) -> Color {
-        let t = t.max(0.0).min(1.0);
+        let t = t.clamp(0.0, 1.0);
         Color {
             r: a.r + (b.r - a.r) * t,
             g: a.g + (b.g - a.g) * t,
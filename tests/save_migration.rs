@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use wasm_game_engine::core::ecs::{Component, Scene, SceneError, Transform, World};
+
+// The game's health component as of schema 2. Its history:
+//   0: `Hp`, a bare number out of a fixed 10, and a `DebugLabel` component
+//   1: `Hp` became `Health { current, max }`; `DebugLabel` was removed
+//   2: `current` became `value`, and health regenerates
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+struct Health {
+    value: f32,
+    max: f32,
+    regen: f32,
+}
+
+fn hp_to_health(value: Value) -> Result<Option<(String, Value)>, String> {
+    let hp = value.as_f64().ok_or_else(|| format!("expected a number, got {}", value))?;
+    Ok(Some(("Health".to_string(), json!({ "current": hp, "max": 10 }))))
+}
+
+fn drop_component(_: Value) -> Result<Option<(String, Value)>, String> {
+    Ok(None)
+}
+
+fn add_regen(value: Value) -> Result<Option<(String, Value)>, String> {
+    let max = value["max"].as_f64().ok_or("missing `max`")?;
+    Ok(Some(("Health".to_string(), json!({ "value": value["current"], "max": max, "regen": max / 10.0 }))))
+}
+
+fn world_at_schema_2() -> World {
+    let mut world = World::new();
+    world.type_registry_mut().register_component::<Health>("Health");
+    let registry = world.scene_registry_mut();
+    registry.set_schema_version(2);
+    registry.register_migration("Hp", 0, hp_to_health);
+    registry.register_migration("DebugLabel", 0, drop_component);
+    registry.register_migration("Health", 1, add_regen);
+    world
+}
+
+fn fixture(file: &str) -> Scene {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(file);
+    Scene::from_json(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn loads_schema_0_through_every_migration() {
+    let scene = fixture("save_v0.json");
+    assert_eq!(scene.schema_version, 0);
+    
+    let mut world = world_at_schema_2();
+    let entities = world.load_scene(&scene).unwrap();
+    assert_eq!(world.entity_count(), 2);
+    
+    let player = entities[&0];
+    assert_eq!(*world.get_component::<Health>(player).unwrap(), Health { value: 7.0, max: 10.0, regen: 1.0 });
+    let transform = world.get_component::<Transform>(player).unwrap();
+    assert_eq!((transform.x, transform.y), (12.0, 34.0));
+    
+    let other = entities[&1];
+    assert_eq!(world.get_component::<Health>(other).unwrap().value, 3.0);
+}
+
+#[test]
+fn loads_schema_1_through_the_remaining_migration() {
+    let mut world = world_at_schema_2();
+    let entities = world.load_scene(&fixture("save_v1.json")).unwrap();
+    assert_eq!(*world.get_component::<Health>(entities[&0]).unwrap(), Health { value: 5.0, max: 10.0, regen: 1.0 });
+}
+
+#[test]
+fn migrated_saves_are_stamped_with_the_current_schema() {
+    let mut world = world_at_schema_2();
+    world.load_scene(&fixture("save_v0.json")).unwrap();
+    let saved = world.save_scene();
+    assert_eq!(saved.schema_version, 2);
+    assert!(saved.entities.iter().all(|entity| !entity.components.contains_key("Hp")));
+    
+    // Already current, so no migration runs again
+    let mut reloaded = world_at_schema_2();
+    let entities = reloaded.load_scene(&saved).unwrap();
+    assert_eq!(reloaded.get_component::<Health>(entities[&0]).unwrap().value, 7.0);
+}
+
+#[test]
+fn removed_components_fail_without_a_migration() {
+    let mut world = World::new();
+    world.type_registry_mut().register_component::<Health>("Health");
+    world.scene_registry_mut().set_schema_version(2);
+    match world.load_scene(&fixture("save_v0.json")) {
+        Err(SceneError::UnknownComponent(name)) => assert_eq!(name, "DebugLabel"),
+        other => panic!("expected an unknown component, got {:?}", other),
+    }
+}
+
+#[test]
+fn failed_migrations_leave_the_world_alone() {
+    let mut scene = fixture("save_v0.json");
+    scene.entities[1].components.insert("Hp".to_string(), json!("lots"));
+    
+    let mut world = world_at_schema_2();
+    match world.load_scene(&scene) {
+        Err(SceneError::MigrationFailed { component, from, message }) => {
+            assert_eq!((component.as_str(), from), ("Hp", 0));
+            assert!(message.contains("expected a number"), "{}", message);
+        }
+        other => panic!("expected a failed migration, got {:?}", other),
+    }
+    assert_eq!(world.entity_count(), 0);
+}
+
+#[test]
+fn newer_schemas_are_rejected() {
+    let mut scene = fixture("save_v1.json");
+    scene.schema_version = 3;
+    
+    let mut world = world_at_schema_2();
+    match world.load_scene(&scene) {
+        Err(SceneError::UnsupportedSchema { version, supported }) => assert_eq!((version, supported), (3, 2)),
+        other => panic!("expected an unsupported schema, got {:?}", other),
+    }
+    assert_eq!(world.entity_count(), 0);
+}
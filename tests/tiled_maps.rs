@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+use wasm_game_engine::core::ecs::{Children, Entity, Name, Sprite, Transform, Velocity, World};
+use wasm_game_engine::graphics::tilemap::Tilemap;
+use wasm_game_engine::physics::collision::Collider;
+use wasm_game_engine::physics::physics_world::RigidBody;
+use wasm_game_engine::tiled::{LayerKind, Shape, TiledError, TiledMap, TiledObject, TiledProperties};
+
+// The same level saved in both formats, sharing an external tileset
+fn map_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/maps").join(file)
+}
+
+fn spawn(file: &str) -> (World, Entity) {
+    let map = TiledMap::load(map_path(file)).unwrap();
+    let mut world = World::new();
+    let root = map.spawn(&mut world).unwrap();
+    (world, root)
+}
+
+fn named(world: &World, name: &str) -> Entity {
+    world.find_by_name(name).unwrap_or_else(|| panic!("no entity named `{}`", name))
+}
+
+fn children(world: &World, entity: Entity) -> Vec<Entity> {
+    world.get_component::<Children>(entity).map_or_else(Vec::new, |children| children.0.clone())
+}
+
+#[test]
+fn parses_both_formats_from_disk() {
+    for file in ["level1.tmj", "level1.tmx"] {
+        let map = TiledMap::load(map_path(file)).unwrap();
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (5, 4, 16, 16), "{}", file);
+        
+        // The external tileset is read from next to the map
+        assert_eq!(map.tilesets.len(), 1);
+        assert_eq!(map.tilesets[0].name, "terrain");
+        assert_eq!(map.tilesets[0].image.as_deref(), Some("terrain.png"));
+        assert_eq!((map.tilesets[0].columns, map.tilesets[0].tile_count), (4, 8));
+        
+        let names: Vec<&str> = map.layers.iter().map(|layer| layer.name.as_str()).collect();
+        assert_eq!(names, ["Ground", "Decoration", "Entities"]);
+        let LayerKind::Objects(objects) = &map.layers[2].kind else {
+            panic!("Entities isn't an object layer");
+        };
+        assert_eq!(objects.len(), 4);
+        assert_eq!(objects[2].shape, Shape::Polygon(vec![(0.0, 0.0), (16.0, 16.0), (0.0, 16.0)]));
+        assert_eq!(objects[3].class, "Door");
+    }
+}
+
+#[test]
+fn spawns_tile_layers_as_tilemaps() {
+    for file in ["level1.tmj", "level1.tmx"] {
+        let (world, root) = spawn(file);
+        assert_eq!(children(&world, root).len(), 3, "{}", file);
+        
+        let ground = world.get_component::<Tilemap>(named(&world, "Ground")).unwrap();
+        assert_eq!((ground.width, ground.height), (5, 4));
+        assert_eq!((ground.tile_width, ground.tile_height), (16.0, 16.0));
+        assert_eq!(ground.tiles, [1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 3, 3, 3, 3, 3]);
+        assert_eq!(ground.tilesets.len(), 1);
+        assert!(ground.visible);
+        
+        // Base64 data keeps the flip flags in the high bits
+        let decoration = world.get_component::<Tilemap>(named(&world, "Decoration")).unwrap();
+        assert_eq!(decoration.opacity, 0.5);
+        assert_eq!(decoration.tiles.iter().filter(|&&tile| tile != 0).count(), 1);
+        assert_eq!(decoration.tiles[7], 0x8000_0005);
+        
+        assert!(!world.has_component::<Tilemap>(named(&world, "Entities")));
+    }
+}
+
+#[test]
+fn spawns_objects_with_components() {
+    for file in ["level1.tmj", "level1.tmx"] {
+        let (world, _) = spawn(file);
+        let layer = named(&world, "Entities");
+        assert_eq!(children(&world, layer).len(), 4, "{}", file);
+        
+        // A tile object hangs up from its bottom-left anchor and gets a sprite
+        let player = named(&world, "Player");
+        let transform = world.get_component::<Transform>(player).unwrap();
+        assert_eq!((transform.x, transform.y), (16.0, 32.0));
+        let sprite = world.get_component::<Sprite>(player).unwrap();
+        assert_eq!((sprite.width, sprite.height), (16.0, 16.0));
+        assert!(!world.has_component::<Collider>(player));
+        assert_eq!(world.get_component::<TiledObject>(player).unwrap().gid, Some(4));
+        
+        // Custom properties land on the registered components
+        assert_eq!(world.get_component::<Velocity>(player).unwrap().x, 40.0);
+        let body = world.get_component::<RigidBody>(player).unwrap();
+        assert_eq!((body.mass, body.bounciness), (2.0, 0.25));
+        
+        let wall = named(&world, "Wall");
+        let transform = world.get_component::<Transform>(wall).unwrap();
+        assert_eq!((transform.x, transform.y), (64.0, 0.0));
+        let collider = world.get_component::<Collider>(wall).unwrap();
+        assert_eq!((collider.offset_x, collider.offset_y, collider.width, collider.height), (0.0, 0.0, 16.0, 64.0));
+        assert!(!collider.is_trigger);
+        assert!(!world.has_component::<Sprite>(wall));
+        
+        // Polygons collide over their bounding box
+        let slope = named(&world, "Slope");
+        let collider = world.get_component::<Collider>(slope).unwrap();
+        assert_eq!((collider.width, collider.height), (16.0, 16.0));
+        
+        let exit = named(&world, "Exit");
+        assert!(world.get_component::<Collider>(exit).unwrap().is_trigger);
+        assert_eq!(world.get_component::<TiledObject>(exit).unwrap().class, "Door");
+        let properties = world.get_component::<TiledProperties>(exit).unwrap();
+        assert_eq!(properties.get("target"), Some(&json!("level2")));
+        assert!(properties.get("Collider.is_trigger").is_none());
+    }
+}
+
+#[test]
+fn map_properties_stay_on_the_root() {
+    let (world, root) = spawn("level1.tmx");
+    let properties = world.get_component::<TiledProperties>(root).unwrap();
+    assert_eq!(properties.get("music"), Some(&json!("audio/level1.ogg")));
+    assert!(world.get_component::<Name>(root).is_none());
+}
+
+#[test]
+fn both_formats_spawn_the_same_world() {
+    let scene = |file: &str| {
+        let (mut world, _) = spawn(file);
+        let registry = world.type_registry_mut();
+        registry.register_component::<TiledObject>("TiledObject");
+        registry.register_component::<TiledProperties>("TiledProperties");
+        world.save_scene()
+    };
+    let json = scene("level1.tmj");
+    assert_eq!(json.entities.len(), 8);
+    assert_eq!(json, scene("level1.tmx"));
+}
+
+// 65536 x 65536 cells wraps a u32 around to 0, matching the empty data
+#[test]
+fn layers_too_large_to_count_are_invalid() {
+    let json = r#"{
+        "width": 65536, "height": 65536, "tilewidth": 16, "tileheight": 16,
+        "layers": [{ "type": "tilelayer", "name": "Huge", "width": 65536, "height": 65536, "data": [] }]
+    }"#;
+    let xml = r#"<map orientation="orthogonal" width="65536" height="65536" tilewidth="16" tileheight="16">
+        <layer name="Huge" width="65536" height="65536"><data encoding="csv"></data></layer>
+    </map>"#;
+    for result in [TiledMap::from_json(json), TiledMap::from_xml(xml)] {
+        match result {
+            Err(TiledError::InvalidMap(message)) => assert!(message.contains("65536x65536"), "{}", message),
+            other => panic!("expected an invalid map, got {:?}", other.map(|map| map.layers.len())),
+        }
+    }
+}
+
+#[test]
+fn reads_zlib_compressed_layers() {
+    let layer = |data: &str| {
+        format!(
+            r#"<map orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+                <layer name="Ground" width="2" height="2"><data encoding="base64" compression="zlib">{}</data></layer>
+            </map>"#,
+            data
+        )
+    };
+    let map = TiledMap::from_xml(&layer("eJxjZGBgYAJiZiBmZWBoAAAA5ACM")).unwrap();
+    let LayerKind::Tiles { tiles, .. } = &map.layers[0].kind else {
+        panic!("Ground isn't a tile layer");
+    };
+    assert_eq!(tiles, &[1, 2, 3, 0x8000_0005]);
+    
+    // Five tiles don't fit a 2x2 layer, and inflating stops short of them
+    match TiledMap::from_xml(&layer("eJxjZGBgYAJiZiBmAWJWIAYAAKAAEA==")) {
+        Err(TiledError::InvalidMap(message)) => assert!(message.contains("zlib"), "{}", message),
+        other => panic!("expected an invalid map, got {:?}", other.map(|map| map.layers.len())),
+    }
+    let gzip = layer("H4sI").replace("zlib", "gzip");
+    assert!(matches!(TiledMap::from_xml(&gzip), Err(TiledError::Unsupported(_))));
+}